@@ -30,7 +30,8 @@ export type LoadOpts =  {
 const DEFAULT_WITNESS_URL = "https://rebasedemo.spruceid.workers.dev"
 
 // This returns a client config pointed to the Spruce ID witness.
-export const defaultClientConfig = (): ClientConfig => {
+// Pass the witness's DID for verify to accept the credentials it issues.
+export const defaultClientConfig = (witness_did?: string): ClientConfig => {
     return {
         endpoints: { 
             instructions: `${DEFAULT_WITNESS_URL}/instructions`,
@@ -38,7 +39,8 @@ export const defaultClientConfig = (): ClientConfig => {
             witness_jwt: `${DEFAULT_WITNESS_URL}/witness_jwt`,
             witness_ld: `${DEFAULT_WITNESS_URL}/witness_ld`,
             verify: `${DEFAULT_WITNESS_URL}/verify`
-        }
+        },
+        witness_did
     }
 };
 
@@ -9,7 +9,6 @@ use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
-
 pub struct DappPreferencesAttestationContent {
     pub id: String,
     pub dark_mode: bool,
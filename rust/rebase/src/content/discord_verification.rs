@@ -0,0 +1,96 @@
+use crate::types::{
+    defs::{Content, Subject},
+    enums::subject::Subjects,
+    error::ContentError,
+};
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use ssi::{one_or_many::OneOrMany, vc::Evidence};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct DiscordVerificationContent {
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub subject: Subjects,
+    pub channel_id: String,
+    pub message_id: String,
+    // When the message was posted, if it was looked up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    pub statement: String,
+    pub signature: String,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Content for DiscordVerificationContent {
+    fn context(&self) -> Result<serde_json::Value, ContentError> {
+        Ok(json!([
+            "https://www.w3.org/2018/credentials/v1",
+            "https://spec.rebase.xyz/contexts/v1",
+            "https://schema.org/"
+        ]))
+    }
+
+    fn evidence(&self) -> Result<Option<OneOrMany<Evidence>>, ContentError> {
+        let mut evidence_map = std::collections::HashMap::new();
+        evidence_map.insert(
+            "username".to_string(),
+            serde_json::Value::String(self.username.clone()),
+        );
+        if let Some(id) = &self.user_id {
+            evidence_map.insert("userId".to_string(), serde_json::Value::String(id.clone()));
+        }
+        evidence_map.insert(
+            "channelId".to_string(),
+            serde_json::Value::String(self.channel_id.clone()),
+        );
+        evidence_map.insert(
+            "messageId".to_string(),
+            serde_json::Value::String(self.message_id.clone()),
+        );
+        evidence_map.insert(
+            "timestamp".to_string(),
+            serde_json::Value::String(
+                self.timestamp
+                    .clone()
+                    .unwrap_or_else(|| Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+            ),
+        );
+
+        let evidence = Evidence {
+            id: None,
+            type_: vec!["DiscordVerificationMessage".to_string()],
+            property_set: Some(evidence_map),
+        };
+
+        Ok(Some(OneOrMany::One(evidence)))
+    }
+
+    fn subject(&self) -> Result<serde_json::Value, ContentError> {
+        // Profiles can only be linked to by user ID.
+        match &self.user_id {
+            Some(id) => Ok(json!({
+                "id": self.subject.did()?,
+                "sameAs": format!("https://discord.com/users/{}", id)
+            })),
+            None => Ok(json!({
+                "id": self.subject.did()?,
+            })),
+        }
+    }
+
+    fn types(&self) -> Result<Vec<String>, ContentError> {
+        Ok(vec![
+            "VerifiableCredential".to_string(),
+            "DiscordVerification".to_string(),
+        ])
+    }
+}
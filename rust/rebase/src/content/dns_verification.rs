@@ -17,6 +17,8 @@ use wasm_bindgen::prelude::*;
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct DnsVerificationContent {
     pub domain: String,
+    // What the signature follows in the TXT record, kept so the record can be found again.
+    pub prefix: String,
     pub subject: Subjects,
 }
 
@@ -44,6 +46,11 @@ impl Content for DnsVerificationContent {
             serde_json::Value::String("https://cloudflare-dns.com/dns-query".to_string()),
         );
 
+        evidence_map.insert(
+            "prefix".to_string(),
+            serde_json::Value::String(self.prefix.clone()),
+        );
+
         let evidence = Evidence {
             id: None,
             type_: vec!["DnsVerificationMessage".to_string()],
@@ -13,6 +13,66 @@ pub fn context_loader() -> Result<ContextLoader, ContentError> {
         // NOTE: This doesn't actually line up at all, but SSI accepts it.
         serde_json::to_string(&json!({
             "@context": {
+              "BlueskyVerification": {
+                "@id": "https://w3id.org/rebase#BlueskyVerification",
+                "@context": {
+                  "sameAs": {
+                    "@id": "https://schema.org/sameAs",
+                    "@type": "@id"
+                  }
+                }
+              },
+              "BlueskyVerificationMessage": {
+                "@id": "https://w3id.org/rebase#BlueskyVerificationMessage",
+                "@context": {
+                  "handle": {
+                    "@id": "https://schema.org/name",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "postUrl": {
+                    "@id": "https://schema.org/url",
+                    "@type": "@id"
+                  },
+                  "timestamp": {
+                    "@id": "https://schema.org/temporal",
+                    "@type": "https://schema.org/DateTime"
+                  }
+                }
+              },
+              "DiscordVerification": {
+                "@id": "https://w3id.org/rebase#DiscordVerification",
+                "@context": {
+                  "sameAs": {
+                    "@id": "https://schema.org/sameAs",
+                    "@type": "@id"
+                  }
+                }
+              },
+              "DiscordVerificationMessage": {
+                "@id": "https://w3id.org/rebase#DiscordVerificationMessage",
+                "@context": {
+                  "channelId": {
+                    "@id": "https://w3id.org/rebase#channelId",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "messageId": {
+                    "@id": "https://w3id.org/rebase#messageId",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "timestamp": {
+                    "@id": "https://schema.org/temporal",
+                    "@type": "https://schema.org/DateTime"
+                  },
+                  "userId": {
+                    "@id": "https://w3id.org/rebase#userId",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "username": {
+                    "@id": "https://schema.org/name",
+                    "@type": "https://schema.org/Text"
+                  }
+                }
+              },
               "DnsVerification": {
                 "@id": "https://w3id.org/rebase#DnsVerification",
                 "@context": {
@@ -29,6 +89,10 @@ pub fn context_loader() -> Result<ContextLoader, ContentError> {
                     "@id": "https://schema.org/url",
                     "@type": "@id"
                   },
+                  "prefix": {
+                    "@id": "https://w3id.org/rebase#prefix",
+                    "@type": "https://schema.org/Text"
+                  },
                   "timestamp": {
                     "@id": "https://schema.org/temporal",
                     "@type": "https://schema.org/DateTime"
@@ -57,6 +121,36 @@ pub fn context_loader() -> Result<ContextLoader, ContentError> {
                   }
                 }
               },
+              "ForgeVerification": {
+                "@id": "https://w3id.org/rebase#ForgeVerification",
+                "@context": {
+                  "sameAs": {
+                    "@id": "https://schema.org/sameAs",
+                    "@type": "@id"
+                  }
+                }
+              },
+              "ForgeVerificationMessage": {
+                "@id": "https://w3id.org/rebase#ForgeVerificationMessage",
+                "@context": {
+                  "domain": {
+                    "@id": "https://schema.org/domain",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "handle": {
+                    "@id": "https://schema.org/name",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "snippetId": {
+                    "@id": "https://schema.org/identifier",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "timestamp": {
+                    "@id": "https://schema.org/temporal",
+                    "@type": "https://schema.org/DateTime"
+                  }
+                }
+              },
               "GitHubVerification": {
                 "@id": "https://w3id.org/rebase#GitHubVerification",
                 "@context": {
@@ -83,6 +177,36 @@ pub fn context_loader() -> Result<ContextLoader, ContentError> {
                   }
                 }
               },
+              "MastodonVerification": {
+                "@id": "https://w3id.org/rebase#MastodonVerification",
+                "@context": {
+                  "sameAs": {
+                    "@id": "https://schema.org/sameAs",
+                    "@type": "@id"
+                  }
+                }
+              },
+              "MastodonVerificationMessage": {
+                "@id": "https://w3id.org/rebase#MastodonVerificationMessage",
+                "@context": {
+                  "domain": {
+                    "@id": "https://schema.org/domain",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "handle": {
+                    "@id": "https://schema.org/name",
+                    "@type": "https://schema.org/Text"
+                  },
+                  "statusUrl": {
+                    "@id": "https://schema.org/url",
+                    "@type": "@id"
+                  },
+                  "timestamp": {
+                    "@id": "https://schema.org/temporal",
+                    "@type": "https://schema.org/DateTime"
+                  }
+                }
+              },
               "RedditVerification": {
                 "@id": "https://w3id.org/rebase#RedditVerification",
                 "@context": {
@@ -3,8 +3,12 @@ use crate::{
     proof::attestation::proof::AttestationProof,
     statement::attestation::statement::AttestationStatement,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
-        error::FlowError,
+        defs::{
+            with_chain_id, EthRpcOpts, Flow, Instructions, Issuer, Proof, ResolverOpts, Statement,
+            StatementResponse, TypedData,
+        },
+        enums::subject::{Pkh, Subjects},
+        error::{FlowError, SubjectError},
     },
 };
 use async_trait::async_trait;
@@ -15,7 +19,29 @@ use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
-pub struct AttestationFlow {}
+pub struct AttestationFlow {
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+}
+
+// typed_data is the statement as EIP-712 typed data bound to the subject's chain. Only
+// Ethereum subjects are offered typed data, others sign the plain-text statement.
+fn typed_data<S: Statement>(
+    subject: &Subjects,
+    statement: &S,
+) -> Result<Option<TypedData>, FlowError> {
+    match subject {
+        Subjects::Pkh(Pkh::Eip155(x)) => Ok(Some(with_chain_id(
+            statement.generate_typed_data()?,
+            &x.chain_id,
+        )?)),
+        _ => Ok(None),
+    }
+}
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
@@ -26,7 +52,7 @@ impl Flow<AttestationContent, AttestationStatement, AttestationProof> for Attest
             statement: "Fill out the presented form to create content in the form of a credential."
                 .to_string(),
             statement_schema: schema_for!(AttestationStatement),
-            signature: "Sign a plain-text version of the content created in the previous step."
+            signature: "Sign a plain-text version of the content created in the previous step, or its typed data with an Ethereum wallet."
                 .to_string(),
             witness: "Present the signature and the content object to the witness to have it transformed into a credential.".to_string(),
             witness_schema: schema_for!(AttestationProof)
@@ -41,6 +67,7 @@ impl Flow<AttestationContent, AttestationStatement, AttestationProof> for Attest
         Ok(StatementResponse {
             statement: statement.generate_statement()?,
             delimiter: None,
+            typed_data: typed_data(&statement.subject(), &statement)?,
         })
     }
 
@@ -50,10 +77,33 @@ impl Flow<AttestationContent, AttestationStatement, AttestationProof> for Attest
         _issuer: I,
     ) -> Result<AttestationContent, FlowError> {
         let stmt = proof.generate_statement()?;
-        proof
-            .subject()
-            .valid_signature(&stmt, &proof.signature())
-            .await?;
+        let subject = proof.subject();
+        if let Err(e) = subject
+            .valid_signature_with_opts(
+                &stmt,
+                &proof.signature(),
+                &self.resolver_opts,
+                &self.eth_rpc,
+            )
+            .await
+        {
+            // Ethereum wallets may have signed the typed data handed out by statement instead.
+            let typed = match typed_data(&subject, &proof)? {
+                Some(t) => t,
+                None => return Err(e.into()),
+            };
+            subject
+                .valid_typed_data_signature_with_rpc(&typed, &proof.signature(), &self.eth_rpc)
+                .await
+                .map_err(|te| match (e, te) {
+                    // An outage from either check is reported rather than a bad signature.
+                    (e @ SubjectError::Upstream(_), _) => e,
+                    (_, te @ SubjectError::Upstream(_)) => te,
+                    (e, te) => {
+                        SubjectError::Validation(format!("{}; nor as typed data: {}", e, te))
+                    }
+                })?;
+        }
         Ok(proof.to_content(&stmt, &proof.signature())?)
     }
 }
@@ -64,7 +114,7 @@ mod tests {
     use crate::{
         proof::attestation::basic_post_attestation::BasicPostAttestationProof,
         statement::attestation::basic_post_attestation::BasicPostAttestationStatement,
-        test_util::util::test_did_keypair,
+        test_util::util::{test_did_keypair, test_secp256k1_keypair, test_sign_typed_data},
         types::defs::{Issuer, Statement},
     };
 
@@ -72,7 +122,10 @@ mod tests {
     async fn mock_attestation_flow() {
         let (subj1, iss1) = test_did_keypair().await.unwrap();
         let (_, iss2) = test_did_keypair().await.unwrap();
-        let flow = AttestationFlow {};
+        let flow = AttestationFlow {
+            resolver_opts: None,
+            eth_rpc: None,
+        };
 
         let statement = BasicPostAttestationStatement {
             subject: subj1,
@@ -111,4 +164,68 @@ mod tests {
             panic!("Flow approved bad signature")
         }
     }
+
+    #[tokio::test]
+    async fn typed_data_attestation_flow() {
+        let (subj, iss) = test_secp256k1_keypair().unwrap();
+        let (_, other) = test_secp256k1_keypair().unwrap();
+        let flow = AttestationFlow {
+            resolver_opts: None,
+            eth_rpc: None,
+        };
+        let statement = BasicPostAttestationStatement {
+            subject: subj,
+            reply_to: None,
+            body: "World".to_string(),
+            title: "Hello".to_string(),
+        };
+
+        let res = flow
+            .statement(
+                AttestationStatement::BasicPostAttestation(statement.clone()),
+                iss.clone(),
+            )
+            .await
+            .unwrap();
+        let typed_data = res.typed_data.unwrap();
+        assert_eq!(typed_data.primary_type, "BasicPostAttestation");
+
+        let proof = BasicPostAttestationProof {
+            statement: statement.clone(),
+            signature: test_sign_typed_data(&iss.jwk, &typed_data).unwrap(),
+        };
+        flow.validate_proof(AttestationProof::BasicPostAttestation(proof), iss.clone())
+            .await
+            .unwrap();
+
+        let bad_proof = BasicPostAttestationProof {
+            statement: statement.clone(),
+            signature: test_sign_typed_data(&other.jwk, &typed_data).unwrap(),
+        };
+        match flow
+            .validate_proof(
+                AttestationProof::BasicPostAttestation(bad_proof),
+                iss.clone(),
+            )
+            .await
+        {
+            Ok(_) => panic!("Flow approved bad signature"),
+            // Both the plain-text and the typed data checks are reported.
+            Err(e) => assert!(e.to_string().contains("nor as typed data"), "{}", e),
+        }
+
+        // Typed data that isn't bound to the subject's chain is refused.
+        let unbound = statement.generate_typed_data().unwrap();
+        let unbound_proof = BasicPostAttestationProof {
+            statement,
+            signature: test_sign_typed_data(&iss.jwk, &unbound).unwrap(),
+        };
+        if flow
+            .validate_proof(AttestationProof::BasicPostAttestation(unbound_proof), iss)
+            .await
+            .is_ok()
+        {
+            panic!("Flow approved typed data without a chain id")
+        }
+    }
 }
@@ -0,0 +1,340 @@
+use crate::{
+    content::bluesky_verification::BlueskyVerificationContent as Ctnt,
+    proof::bluesky_verification::BlueskyVerificationProof as Prf,
+    statement::bluesky_verification::BlueskyVerificationStatement as Stmt,
+    types::{
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
+        error::FlowError,
+    },
+};
+
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::Client;
+use schemars::schema_for;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use url::Url;
+use wasm_bindgen::prelude::*;
+
+// The public AppView, which serves the XRPC methods used here without authentication.
+const API_URL: &str = "https://public.api.bsky.app";
+
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct BlueskyVerificationFlow {
+    pub delimiter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ResolveHandleResponse {
+    pub did: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProfileResponse {
+    pub did: String,
+    pub handle: String,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PostAuthor {
+    pub did: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PostRecord {
+    pub text: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Post {
+    pub author: PostAuthor,
+    pub record: PostRecord,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PostsResponse {
+    pub posts: Vec<Post>,
+}
+
+impl BlueskyVerificationFlow {
+    // xrpc calls the XRPC query method with the given parameters.
+    async fn xrpc<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, FlowError> {
+        let u = make_url(&self.http_opts, API_URL, &format!("/xrpc/{}", method))?;
+        send_request(&self.http_opts, Client::new().get(u).query(query))
+            .await?
+            .json()
+            .await
+            .map_err(|e| FlowError::BadLookup(e.to_string()))
+    }
+
+    // post_text finds the text of the post at post_url, which must have been made by did.
+    async fn post_text(
+        &self,
+        statement: &Stmt,
+        did: &str,
+        post_url: &str,
+    ) -> Result<String, FlowError> {
+        let invalid = || FlowError::Validation(format!("post url {} invalid", post_url));
+        let u = Url::parse(post_url).map_err(|_| invalid())?;
+        if u.host_str() != Some("bsky.app") {
+            return Err(invalid());
+        }
+
+        let segments: Vec<&str> = u.path_segments().ok_or_else(invalid)?.collect();
+        let (actor, rkey) = match segments.as_slice() {
+            ["profile", actor, "post", rkey] => (*actor, *rkey),
+            _ => return Err(invalid()),
+        };
+
+        if !actor.eq_ignore_ascii_case(&statement.handle) && actor != did {
+            return Err(FlowError::Validation(format!(
+                "post url is not for {}",
+                statement.handle
+            )));
+        }
+
+        let r = Regex::new(r"^[a-zA-Z0-9._:~-]{1,512}$")
+            .map_err(|_| FlowError::BadLookup("could not generate rkey regex".to_string()))?;
+        if !r.is_match(rkey) {
+            return Err(invalid());
+        }
+
+        let uri = format!("at://{}/app.bsky.feed.post/{}", did, rkey);
+        let res: PostsResponse = self
+            .xrpc("app.bsky.feed.getPosts", &[("uris", &uri)])
+            .await?;
+        let post = res
+            .posts
+            .into_iter()
+            .next()
+            .ok_or_else(|| FlowError::NotFound(format!("post {} not found", uri)))?;
+
+        if post.author.did != did {
+            return Err(FlowError::Validation(format!(
+                "post was made by {} not {}",
+                post.author.did, did
+            )));
+        }
+
+        Ok(post.record.text)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Flow<Ctnt, Stmt, Prf> for BlueskyVerificationFlow {
+    fn instructions(&self) -> Result<Instructions, FlowError> {
+        Ok(Instructions {
+            statement: "Enter your Bluesky handle to verify and include in a signed message using your wallet.".to_string(),
+            statement_schema: schema_for!(Stmt),
+            signature: "Sign the message presented to you containing your Bluesky handle and additional information.".to_string(),
+            witness: "Post the statement and signature, or add them to your profile description, to create a link between your identifier and Bluesky handle.".to_string(),
+            witness_schema: schema_for!(Prf),
+        })
+    }
+
+    async fn statement<I: Issuer + Send + Clone>(
+        &self,
+        statement: Stmt,
+        _issuer: I,
+    ) -> Result<StatementResponse, FlowError> {
+        Ok(StatementResponse {
+            delimiter: Some(self.delimiter.to_owned()),
+            typed_data: None,
+            statement: statement.generate_statement()?,
+        })
+    }
+
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
+    async fn validate_proof<I: Issuer + Send>(
+        &self,
+        proof: Prf,
+        _issuer: I,
+    ) -> Result<Ctnt, FlowError> {
+        // Handles are domain names.
+        let handle = Regex::new(
+            r"^([a-zA-Z0-9]([a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?\.)+[a-zA-Z]([a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?$",
+        )
+        .map_err(|_| FlowError::BadLookup("could not generate handle regex".to_string()))?;
+        if !handle.is_match(&proof.statement.handle) {
+            return Err(FlowError::Validation("handle invalid".to_string()));
+        }
+
+        let resolved: ResolveHandleResponse = self
+            .xrpc(
+                "com.atproto.identity.resolveHandle",
+                &[("handle", &proof.statement.handle)],
+            )
+            .await?;
+        let did = resolved.did;
+
+        let text = match &proof.post_url {
+            Some(post_url) => self.post_text(&proof.statement, &did, post_url).await?,
+            None => {
+                let profile: ProfileResponse = self
+                    .xrpc("app.bsky.actor.getProfile", &[("actor", &did)])
+                    .await?;
+                // The profile's handle is only set if it resolves back to the same DID.
+                if profile.did != did
+                    || !profile.handle.eq_ignore_ascii_case(&proof.statement.handle)
+                {
+                    return Err(FlowError::Validation(format!(
+                        "handle mismatch, expected: {}, got: {}",
+                        proof.statement.handle, profile.handle
+                    )));
+                }
+                profile.description.unwrap_or_default()
+            }
+        };
+
+        let mut a = text.split(&self.delimiter);
+        match (a.next(), a.next()) {
+            (Some(stmt), Some(sig)) => {
+                let expected = proof.statement.generate_statement()?;
+                if stmt.trim() != expected {
+                    return Err(FlowError::Validation(format!(
+                        "statement mismatch, expected: {}, got: {}",
+                        expected,
+                        stmt.trim()
+                    )));
+                }
+                let sig = sig.trim();
+                proof
+                    .statement
+                    .subject
+                    .valid_signature_with_opts(&expected, sig, &self.resolver_opts, &self.eth_rpc)
+                    .await?;
+                Ok(proof.to_content(&expected, sig)?)
+            }
+            _ => Err(FlowError::NotFound(format!(
+                "Failed to find the statement and signature posted by {}",
+                proof.statement.handle
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::util::{test_did_keypair, MockIssuer, StubTransport},
+        types::defs::Issuer,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const DID: &str = "did:plc:alice";
+    const POST: &str = "https://bsky.app/profile/alice.bsky.social/post/3kabc";
+
+    fn stub_flow(text: String, author: &str) -> BlueskyVerificationFlow {
+        BlueskyVerificationFlow {
+            delimiter: "\n\n".to_string(),
+            http_opts: Some(HttpOpts {
+                base_url: Some("http://stub.test".to_string()),
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![
+                        (
+                            "http://stub.test/xrpc/com.atproto.identity.resolveHandle".to_string(),
+                            json!({ "did": DID }).to_string(),
+                        ),
+                        (
+                            "http://stub.test/xrpc/app.bsky.actor.getProfile".to_string(),
+                            json!({
+                                "did": DID,
+                                "handle": "alice.bsky.social",
+                                "description": text
+                            })
+                            .to_string(),
+                        ),
+                        (
+                            "http://stub.test/xrpc/app.bsky.feed.getPosts".to_string(),
+                            json!({
+                                "posts": [{
+                                    "author": { "did": author },
+                                    "record": { "text": text }
+                                }]
+                            })
+                            .to_string(),
+                        ),
+                    ],
+                })),
+            }),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn bluesky_stub_transport() {
+        let i = MockIssuer {};
+        let (subj1, iss1) = test_did_keypair().await.unwrap();
+        let (_, iss2) = test_did_keypair().await.unwrap();
+
+        let proof = Prf {
+            statement: Stmt {
+                subject: subj1,
+                handle: "alice.bsky.social".to_owned(),
+            },
+            post_url: Some(POST.to_owned()),
+        };
+        let statement = proof.generate_statement().unwrap();
+        let signature = iss1.sign(&statement).await.unwrap();
+        let text = format!("{}\n\n{}", statement, signature);
+
+        let flow = stub_flow(text.clone(), DID);
+        flow.credential(proof.clone(), iss1.clone()).await.unwrap();
+
+        let profile = Prf {
+            post_url: None,
+            ..proof.clone()
+        };
+        flow.validate_proof(profile, i.clone()).await.unwrap();
+
+        // Make sure it fails correctly:
+        let bad_sig = format!("{}\n\n{}", statement, iss2.sign(&statement).await.unwrap());
+        let flow = stub_flow(bad_sig, DID);
+        if flow.validate_proof(proof.clone(), i.clone()).await.is_ok() {
+            panic!("Approved bad signature");
+        };
+
+        let flow = stub_flow(text.clone(), "did:plc:mallory");
+        if flow.validate_proof(proof.clone(), i.clone()).await.is_ok() {
+            panic!("Approved post by another account");
+        };
+
+        let elsewhere = Prf {
+            post_url: Some("https://bsky.app/profile/mallory.bsky.social/post/3kabc".to_owned()),
+            ..proof
+        };
+        let flow = stub_flow(text, DID);
+        if flow.validate_proof(elsewhere, i).await.is_ok() {
+            panic!("Approved post url for another handle");
+        };
+    }
+}
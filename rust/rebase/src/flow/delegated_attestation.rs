@@ -1,25 +1,28 @@
 use crate::{
     content::delegated_attestation::content::DelegatedAttestationContent,
     issuer::ed25519::Ed25519Jwk,
-    proof::delegated_attestation::{parse_siwe_recap, DelegatedAttestationProof},
+    proof::delegated_attestation::{verify_siwe_recap, DelegatedAttestationProof},
     statement::attestation::statement::AttestationStatement,
     types::{
-        defs::{
-            eip55, resolve_key, to_action, DIDKey, Flow, Instructions, Issuer, Message, Proof,
-            Statement, StatementResponse, Subject,
+        capability::{
+            caip122::SignInMessage,
+            recap::{recap_cid, IssuanceStore},
+            revocation::{MemoryRevocationStore, RevocationStore, SignedRevocation},
         },
-        enums::{
-            attestation::Attestation,
-            subject::{Pkh, Subjects},
+        defs::{
+            make_resolver, resolve_key, to_action, EthRpcOpts, Flow, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse, Subject,
         },
+        enums::attestation::Attestation,
         error::FlowError,
+        nonce::{check_rate_limit, nonce_store, NonceStore, RateLimit},
     },
 };
 use async_trait::async_trait;
-use did_web::DIDWeb;
+use chrono::{DateTime, Utc};
 use schemars::schema_for;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
@@ -27,6 +30,77 @@ use wasm_bindgen::prelude::*;
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct DelegatedAttestationFlow {
     pub service_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // Counts issuances for the max_issuances caveat. Without one, ReCaps with that caveat are
+    // refused. Not serializable, so must be set in code after the flow is deserialized.
+    #[serde(skip)]
+    pub issuance_store: Option<Arc<dyn IssuanceStore>>,
+    // Records revoked ReCaps, defaults to an in-process store.
+    // Not serializable, so must be set in code after the flow is deserialized.
+    #[serde(skip)]
+    pub revocation_store: Option<Arc<dyn RevocationStore>>,
+    // SIWE messages must be for this domain, e.g. "example.com", so ReCaps signed for another
+    // site can't be used here.
+    pub expected_domain: String,
+    // Limits how many credentials may be issued per delegator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    // Remembers the SIWE nonces used by each delegator, defaults to an in-process store.
+    // Not serializable, so must be set in code after the flow is deserialized.
+    #[serde(skip)]
+    pub nonce_store: Option<Arc<dyn NonceStore>>,
+}
+
+static DEFAULT_REVOCATION_STORE: MemoryRevocationStore = MemoryRevocationStore::new();
+
+impl DelegatedAttestationFlow {
+    fn revocations(&self) -> &dyn RevocationStore {
+        match &self.revocation_store {
+            Some(s) => s.as_ref(),
+            None => &DEFAULT_REVOCATION_STORE,
+        }
+    }
+
+    // revoke records a delegator's signed revocation, after which validate_proof refuses the
+    // ReCaps it targets until they expire.
+    pub async fn revoke(&self, req: &SignedRevocation) -> Result<(), FlowError> {
+        let r = &req.revocation;
+        if r.service_key != self.service_key {
+            return Err(FlowError::Validation(format!(
+                "Revocation is for {} not {}",
+                r.service_key, self.service_key
+            )));
+        }
+
+        let statement = r.generate_statement()?;
+        // did:key delegators are resolved the same way as the delegate.
+        r.delegator
+            .valid_signature_with_opts(
+                &statement,
+                &req.signature,
+                &self.resolver_opts,
+                &self.eth_rpc,
+            )
+            .await?;
+
+        let key = r.key().map_err(|e| FlowError::Validation(e.to_string()))?;
+        let expires = r
+            .expires()
+            .map_err(|e| FlowError::Validation(e.to_string()))?;
+        if expires <= Utc::now() {
+            return Err(FlowError::Validation(
+                "Revocation has already expired".to_string(),
+            ));
+        }
+        self.revocations()
+            .revoke(&key, expires)
+            .await
+            .map_err(|e| FlowError::Validation(e.to_string()))
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -54,6 +128,7 @@ impl Flow<DelegatedAttestationContent, AttestationStatement, DelegatedAttestatio
         Ok(StatementResponse {
             statement: statement.generate_statement()?,
             delimiter: None,
+            typed_data: None,
         })
     }
 
@@ -62,25 +137,48 @@ impl Flow<DelegatedAttestationContent, AttestationStatement, DelegatedAttestatio
         proof: DelegatedAttestationProof,
         _issuer: I,
     ) -> Result<DelegatedAttestationContent, FlowError> {
-        // Check that the SIWE message is valid.
-        // TODO: Use Message's own verify methods instead?
-        let parsed_recap = parse_siwe_recap(&proof.siwe_message, &self.service_key)?;
-        parsed_recap
-            .subject
-            .valid_signature(&proof.siwe_message, &proof.siwe_signature)
-            .await?;
+        // Check that the SIWE message and any ReCaps it was delegated from are valid.
+        let parsed_recap = verify_siwe_recap(
+            &proof.siwe_message,
+            &proof.siwe_signature,
+            &self.service_key,
+            &proof.parents,
+            &self.resolver_opts,
+            &self.eth_rpc,
+            Some(self.revocations()),
+        )
+        .await?;
 
-        // Create the SIWE message to use a source of information for validation.
-        let m = Message::from_str(&proof.siwe_message).map_err(|e| {
-            FlowError::BadLookup(format!("Failed to parse ReCap into Message: {}", e))
-        })?;
-
-        // Use SIWE's own validation to determine if the ReCap is valid from a timing perspective
-        if !m.valid_now() {
-            return Err(FlowError::BadLookup(
-                "Capability is not valid at this time".to_string(),
+        // Check that the SIWE message was meant for this witness.
+        if self.expected_domain.is_empty() {
+            return Err(FlowError::Misconfigured(
+                "No expected SIWE domain configured".to_string(),
             ));
         }
+        let message = SignInMessage::from_str(&proof.siwe_message)
+            .map_err(|e| FlowError::Validation(e.to_string()))?;
+        if message.domain != self.expected_domain {
+            return Err(FlowError::Validation(format!(
+                "SIWE message is for domain {} not {}",
+                message.domain, self.expected_domain
+            )));
+        }
+
+        let nonce = message
+            .nonce
+            .as_ref()
+            .ok_or_else(|| FlowError::Validation("SIWE message is missing a nonce".to_string()))?;
+        // The nonce and issuance counts are kept until the ReCap expires, so it must.
+        let expires = message
+            .expiration_time
+            .as_ref()
+            .ok_or_else(|| {
+                FlowError::Validation("SIWE message is missing an expiration time".to_string())
+            })
+            .and_then(|t| {
+                DateTime::parse_from_rfc3339(t).map_err(|e| FlowError::Validation(e.to_string()))
+            })?
+            .with_timezone(&Utc);
 
         // Check that the attestation type is supported by the ReCap
         let (t, _) = proof.attestation.to_statement()?;
@@ -92,32 +190,29 @@ impl Flow<DelegatedAttestationContent, AttestationStatement, DelegatedAttestatio
         }
 
         // Check that the attestation's subject is that of the delegator
-        let subject = proof.attestation.subject();
-        let address = match subject {
-            Subjects::Pkh(Pkh::Eip155(x)) => x.address,
-            // TODO: Support other keys?
-            _ => {
-                return Err(FlowError::BadLookup(format!(
-                    "Subject expected to be did:pkh:eip155, got: {}",
-                    subject.did()?
-                )))
-            }
-        };
+        parsed_recap.check_subject(&proof.attestation.subject())?;
 
-        if address != eip55(&m.address) {
-            return Err(FlowError::BadLookup(format!(
-                "Attestation subject is {} but SIWE signer is {}",
-                address,
-                eip55(&m.address)
-            )));
-        }
+        // Check the attestation's content against the ReCap's caveats
+        let caveats = parsed_recap.caveats(&t);
+        caveats
+            .check(&proof.attestation)
+            .map_err(|e| FlowError::Validation(e.to_string()))?;
+        let issuances = match caveats.max_issuances {
+            Some(max) => Some((
+                self.issuance_store.as_ref().ok_or_else(|| {
+                    FlowError::Validation(
+                        "This witness cannot enforce max_issuances caveats".to_string(),
+                    )
+                })?,
+                max,
+            )),
+            None => None,
+        };
 
         // Check that the attestation signature is valid.
         // Create the Public Key to check the signature by parsing the SIWE message.
-        let full_delegate_did = m.uri.to_string();
-        let split_did: Vec<String> = m
-            .uri
-            .to_string()
+        let full_delegate_did = parsed_recap.delegate.clone();
+        let split_did: Vec<String> = full_delegate_did
             .split('#')
             .map(|s| s.to_string())
             .collect();
@@ -132,22 +227,9 @@ impl Flow<DelegatedAttestationContent, AttestationStatement, DelegatedAttestatio
         let key_name = split_did[1].clone();
 
         // Create JWK from DID Url
-        let delegate_jwk = if delegate_did.starts_with("did:key:") {
-            let resolver = DIDKey {};
-            resolve_key(&full_delegate_did, &resolver)
-                .await
-                .map_err(|e| FlowError::BadLookup(format!("Could not build JWK from DID: {}", e)))?
-        } else if delegate_did.starts_with("did:web:") {
-            let resolver = DIDWeb {};
-            resolve_key(&full_delegate_did, &resolver)
-                .await
-                .map_err(|e| FlowError::BadLookup(format!("Could not build JWK from DID: {}", e)))?
-        } else {
-            return Err(FlowError::BadLookup(format!(
-                "Delegate DID must be of did:web or did:key, got {}",
-                delegate_did
-            )));
-        };
+        let delegate_jwk = resolve_key(&full_delegate_did, &make_resolver(&self.resolver_opts))
+            .await
+            .map_err(|e| FlowError::BadLookup(format!("Could not build JWK from DID: {}", e)))?;
 
         // Generate Rebase Subject, then call valid_signature.
         let json_jwk = serde_json::to_string(&delegate_jwk).map_err(|e| {
@@ -161,6 +243,48 @@ impl Flow<DelegatedAttestationContent, AttestationStatement, DelegatedAttestatio
             )
             .await?;
 
+        // Only attestations signed by the delegate count against the delegator's rate limit.
+        check_rate_limit(
+            nonce_store(&self.nonce_store),
+            &self.rate_limit,
+            "delegated_attestation",
+            &parsed_recap.subject.did()?,
+        )
+        .await?;
+
+        // A ReCap is used for every attestation in its session, but its nonce may not be
+        // reused by the delegator for a different ReCap.
+        if !nonce_store(&self.nonce_store)
+            .bind(
+                &format!("delegated_attestation:{}:{}", message.address, nonce),
+                &recap_cid(&proof.siwe_message).to_string(),
+                expires,
+            )
+            .await?
+        {
+            return Err(FlowError::Validation(format!(
+                "SIWE nonce {} has already been used",
+                nonce
+            )));
+        }
+
+        // Count the issuance last, so rejected proofs don't use up the ReCap. Counts are kept
+        // per root ReCap, so re-delegations share their parent's allowance.
+        if let Some((store, max)) = issuances {
+            let key = format!("{}:{}", parsed_recap.root, to_action(&t));
+            if !store
+                .try_increment(&key, max, expires)
+                .await
+                .map_err(|e| FlowError::Validation(e.to_string()))?
+            {
+                return Err(FlowError::Validation(format!(
+                    "ReCap allows at most {} issuances of {}",
+                    max,
+                    to_action(&t)
+                )));
+            }
+        }
+
         // Victory
         Ok(proof.to_content(&proof.generate_statement()?, &proof.attestation_signature)?)
     }
@@ -170,9 +294,85 @@ impl Flow<DelegatedAttestationContent, AttestationStatement, DelegatedAttestatio
 mod tests {
     use super::*;
     use crate::{
-        statement::attestation::basic_post_attestation::BasicPostAttestationStatement,
-        test_util::util::{test_did_keypair, test_eth_did},
+        proof::delegated_attestation::{parse_siwe_recap, SignedReCap, RECAP_PREFIX},
+        statement::attestation::{
+            basic_post_attestation::BasicPostAttestationStatement,
+            follow_attestation::FollowAttestationStatement,
+        },
+        test_util::util::{test_did_keypair, test_eth_did, test_solana_keypair},
+        types::{
+            capability::{
+                recap::{
+                    recap_cid, redelegation_message, Caveats, DelegatedCapability,
+                    MemoryIssuanceStore,
+                },
+                revocation::{Revocation, RevocationTarget},
+            },
+            defs::SessionConfig,
+            enums::{attestation::AttestationTypes, subject::Subjects},
+            nonce::{MemoryNonceStore, RateLimit},
+        },
     };
+    use base64::engine::{general_purpose::STANDARD_NO_PAD as BASE64, Engine as _};
+    use ssi::jws::sign_bytes;
+    use std::collections::BTreeMap;
+    use url::Url;
+
+    const SERVICE_KEY: &str = "rebase:did:web:rebasedemokey.pages.dev";
+
+    // Builds a CAIP-122 ReCap authorizing delegate to issue basic posts.
+    fn recap_message(namespace: &str, address: &str, delegate: &str) -> String {
+        recap_message_with_caveats(
+            namespace,
+            address,
+            delegate,
+            "issue/basic_post_attestation",
+            "[{}]",
+        )
+    }
+
+    fn recap_message_with_caveats(
+        namespace: &str,
+        address: &str,
+        delegate: &str,
+        action: &str,
+        caveats: &str,
+    ) -> String {
+        let recap = format!(
+            "{{\"att\":{{\"{}\":{{\"{}\":{}}}}},\"prf\":[]}}",
+            SERVICE_KEY, action, caveats
+        );
+        format!(
+            "localhost:8080 wants you to sign in with your {} account:\n{}\n\nURI: {}\nVersion: 1\nNonce: 6JQhF2R1wBhfF6ONV\nIssued At: 2023-09-27T17:11:32.013Z\nExpiration Time: 2123-09-27T17:11:32.014Z\nResources:\n- {}{}",
+            namespace,
+            address,
+            delegate,
+            RECAP_PREFIX,
+            BASE64.encode(recap)
+        )
+    }
+
+    fn post(subject: Subjects) -> AttestationStatement {
+        AttestationStatement::BasicPostAttestation(BasicPostAttestationStatement {
+            title: "Hello".to_string(),
+            body: "World".to_string(),
+            reply_to: None,
+            subject,
+        })
+    }
+
+    fn flow() -> DelegatedAttestationFlow {
+        DelegatedAttestationFlow {
+            service_key: SERVICE_KEY.to_string(),
+            resolver_opts: None,
+            eth_rpc: None,
+            issuance_store: None,
+            revocation_store: None,
+            expected_domain: "localhost:8080".to_string(),
+            rate_limit: None,
+            nonce_store: Some(Arc::new(MemoryNonceStore::new())),
+        }
+    }
 
     #[tokio::test]
     async fn test_delegated_attestation() {
@@ -188,14 +388,510 @@ mod tests {
             service_key: "rebase:did:web:rebasedemokey.pages.dev".to_string(),
             siwe_message: "localhost:8080 wants you to sign in with your Ethereum account:\n0xdA3176d77c04632F2862B14E35bc6B4717FB5016\n\nI further authorize the stated URI to perform the following actions on my behalf: (1) 'issue': 'basic_post_attestation' for 'rebase:did:web:rebasedemokey.pages.dev'.\n\nURI: did:key:z6MkiqEVE7UdwpRncdBH5QQQ7THmd8DzuANApbmaXyXNKPSc#z6MkiqEVE7UdwpRncdBH5QQQ7THmd8DzuANApbmaXyXNKPSc\nVersion: 1\nChain ID: 1\nNonce: 6JQhF2R1wBhfF6ONV\nIssued At: 2023-09-27T17:11:32.013Z\nExpiration Time: 2123-09-27T17:11:32.014Z\nNot Before: 2022-09-27T17:11:32.013Z\nResources:\n- urn:recap:eyJhdHQiOnsicmViYXNlOmRpZDp3ZWI6cmViYXNlZGVtb2tleS5wYWdlcy5kZXYiOnsiaXNzdWUvYmFzaWNfcG9zdF9hdHRlc3RhdGlvbiI6W3t9XX19LCJwcmYiOltdfQ".to_string(),
             siwe_signature: "0xa5f8764d637cab627245b5e008b06f04c50361e34e2b19f1a940646373e7f1810385fd8d5c1501b7f0d899f95603cc4632bc9cd77454f24b5e0d64493657e6161c".to_string(),
+            parents: BTreeMap::new(),
         };
 
         let flow = DelegatedAttestationFlow {
             service_key: "rebase:did:web:rebasedemokey.pages.dev".to_string(),
+            resolver_opts: None,
+            eth_rpc: None,
+            issuance_store: None,
+            revocation_store: None,
+            expected_domain: "localhost:8080".to_string(),
+            rate_limit: None,
+            nonce_store: Some(Arc::new(MemoryNonceStore::new())),
         };
 
         let (_, issuer) = test_did_keypair().await.unwrap();
 
         flow.jwt(proof, issuer).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_solana_delegated_attestation() {
+        let (subject, jwk) = test_solana_keypair().unwrap();
+        let (delegate, delegate_issuer) = test_did_keypair().await.unwrap();
+        let siwe_message = recap_message(
+            "Solana",
+            &subject.display_id().unwrap(),
+            &delegate.verification_method().unwrap(),
+        );
+
+        let parsed = parse_siwe_recap(&siwe_message, SERVICE_KEY).unwrap();
+        assert_eq!(parsed.subject.did().unwrap(), subject.did().unwrap());
+
+        let attestation = post(subject);
+        let proof = DelegatedAttestationProof {
+            attestation_signature: delegate_issuer
+                .sign(&attestation.generate_statement().unwrap())
+                .await
+                .unwrap(),
+            attestation,
+            service_key: SERVICE_KEY.to_string(),
+            siwe_signature: hex::encode(
+                sign_bytes(ssi::jwk::Algorithm::EdDSA, siwe_message.as_bytes(), &jwk).unwrap(),
+            ),
+            siwe_message,
+            parents: BTreeMap::new(),
+        };
+
+        let (_, issuer) = test_did_keypair().await.unwrap();
+        flow().jwt(proof.clone(), issuer.clone()).await.unwrap();
+
+        // Another Solana account cannot be the subject.
+        let mut wrong_subject = proof;
+        let (other, _) = test_solana_keypair().unwrap();
+        wrong_subject.attestation = post(other);
+        wrong_subject.attestation_signature = delegate_issuer
+            .sign(&wrong_subject.attestation.generate_statement().unwrap())
+            .await
+            .unwrap();
+        if flow().jwt(wrong_subject, issuer).await.is_ok() {
+            panic!("Attestation about another account permitted");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_did_key_delegated_attestation() {
+        let (subject, subject_issuer) = test_did_keypair().await.unwrap();
+        let (delegate, delegate_issuer) = test_did_keypair().await.unwrap();
+        let siwe_message = recap_message(
+            "Ed25519",
+            &subject.did().unwrap(),
+            &delegate.verification_method().unwrap(),
+        );
+
+        let attestation = post(subject.clone());
+        let mut proof = DelegatedAttestationProof {
+            attestation_signature: delegate_issuer
+                .sign(&attestation.generate_statement().unwrap())
+                .await
+                .unwrap(),
+            attestation,
+            service_key: SERVICE_KEY.to_string(),
+            siwe_signature: subject_issuer.sign(&siwe_message).await.unwrap(),
+            siwe_message,
+            parents: BTreeMap::new(),
+        };
+
+        let (_, issuer) = test_did_keypair().await.unwrap();
+        flow().jwt(proof.clone(), issuer.clone()).await.unwrap();
+
+        // The ReCap must be signed by the delegator.
+        proof.siwe_signature = delegate_issuer.sign(&proof.siwe_message).await.unwrap();
+        if flow().jwt(proof, issuer).await.is_ok() {
+            panic!("ReCap signed by the delegate permitted");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replayed_nonce_delegated_attestation() {
+        let (subject, subject_issuer) = test_did_keypair().await.unwrap();
+        let (delegate, delegate_issuer) = test_did_keypair().await.unwrap();
+
+        let attestation = post(subject.clone());
+        let attestation_signature = delegate_issuer
+            .sign(&attestation.generate_statement().unwrap())
+            .await
+            .unwrap();
+        let proof = |siwe_message: String| {
+            let attestation = attestation.clone();
+            let attestation_signature = attestation_signature.clone();
+            let subject_issuer = subject_issuer.clone();
+            async move {
+                DelegatedAttestationProof {
+                    attestation,
+                    attestation_signature,
+                    service_key: SERVICE_KEY.to_string(),
+                    siwe_signature: subject_issuer.sign(&siwe_message).await.unwrap(),
+                    siwe_message,
+                    parents: BTreeMap::new(),
+                }
+            }
+        };
+
+        // Both ReCaps are signed with the same nonce.
+        let first = proof(recap_message(
+            "Ed25519",
+            &subject.did().unwrap(),
+            &delegate.verification_method().unwrap(),
+        ))
+        .await;
+        let second = proof(recap_message_with_caveats(
+            "Ed25519",
+            &subject.did().unwrap(),
+            &delegate.verification_method().unwrap(),
+            "issue/basic_post_attestation",
+            r#"[{"max_issuances":10}]"#,
+        ))
+        .await;
+
+        let limited = DelegatedAttestationFlow {
+            rate_limit: Some(RateLimit {
+                max_requests: 3,
+                window_minutes: 10,
+            }),
+            ..flow()
+        };
+        let (_, issuer) = test_did_keypair().await.unwrap();
+
+        // A ReCap is reused for every attestation in its session.
+        limited.jwt(first.clone(), issuer.clone()).await.unwrap();
+        limited.jwt(first.clone(), issuer.clone()).await.unwrap();
+        if limited.jwt(second, issuer.clone()).await.is_ok() {
+            panic!("ReCap with a reused nonce permitted");
+        }
+        // The refused ReCap didn't count against the rate limit.
+        limited.jwt(first.clone(), issuer.clone()).await.unwrap();
+        if limited.jwt(first.clone(), issuer.clone()).await.is_ok() {
+            panic!("Issuance beyond the rate limit permitted");
+        }
+
+        let elsewhere = DelegatedAttestationFlow {
+            expected_domain: "example.com".to_string(),
+            ..flow()
+        };
+        if elsewhere.jwt(first, issuer).await.is_ok() {
+            panic!("ReCap for another domain permitted");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caveats_delegated_attestation() {
+        let (subject, subject_issuer) = test_did_keypair().await.unwrap();
+        let (delegate, delegate_issuer) = test_did_keypair().await.unwrap();
+        let siwe_message = recap_message_with_caveats(
+            "Ed25519",
+            &subject.did().unwrap(),
+            &delegate.verification_method().unwrap(),
+            "issue/follow_attestation",
+            r#"[{"targets":["https://example.com/alice"],"max_issuances":2}]"#,
+        );
+        let siwe_signature = subject_issuer.sign(&siwe_message).await.unwrap();
+
+        let parsed = parse_siwe_recap(&siwe_message, SERVICE_KEY).unwrap();
+        assert_eq!(
+            parsed
+                .caveats(&AttestationTypes::FollowAttestation)
+                .max_issuances,
+            Some(2)
+        );
+
+        let follow = |target: &str| {
+            let attestation = AttestationStatement::FollowAttestation(FollowAttestationStatement {
+                subject: subject.clone(),
+                target: Url::parse(target).unwrap(),
+            });
+            let delegate_issuer = delegate_issuer.clone();
+            let siwe_message = siwe_message.clone();
+            let siwe_signature = siwe_signature.clone();
+            async move {
+                DelegatedAttestationProof {
+                    attestation_signature: delegate_issuer
+                        .sign(&attestation.generate_statement().unwrap())
+                        .await
+                        .unwrap(),
+                    attestation,
+                    service_key: SERVICE_KEY.to_string(),
+                    siwe_message,
+                    siwe_signature,
+                    parents: BTreeMap::new(),
+                }
+            }
+        };
+
+        let (_, issuer) = test_did_keypair().await.unwrap();
+        if flow()
+            .jwt(follow("https://example.com/alice").await, issuer.clone())
+            .await
+            .is_ok()
+        {
+            panic!("max_issuances permitted without an issuance store");
+        }
+
+        let flow = DelegatedAttestationFlow {
+            issuance_store: Some(Arc::new(MemoryIssuanceStore::new())),
+            ..flow()
+        };
+
+        if flow
+            .jwt(follow("https://example.com/mallory").await, issuer.clone())
+            .await
+            .is_ok()
+        {
+            panic!("Target outside of the caveats permitted");
+        }
+
+        for _ in 0..2 {
+            flow.jwt(follow("https://example.com/alice").await, issuer.clone())
+                .await
+                .unwrap();
+        }
+
+        if flow
+            .jwt(follow("https://example.com/alice").await, issuer)
+            .await
+            .is_ok()
+        {
+            panic!("Issuance beyond max_issuances permitted");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_config_caveats() {
+        let mut session_config: SessionConfig = serde_json::from_value(serde_json::json!({
+            "address": "0xdA3176d77c04632F2862B14E35bc6B4717FB5016",
+            "chainId": 1,
+            "domain": "localhost:8080",
+            "issuedAt": "2023-09-27T17:11:32.013Z",
+            "expirationTime": "2123-09-27T17:11:32.014Z",
+        }))
+        .unwrap();
+
+        let caveats = Caveats {
+            targets: Some(vec![Url::parse("https://example.com/alice").unwrap()]),
+            max_issuances: Some(1),
+            ..Default::default()
+        };
+        let message = session_config
+            .generate_message_with_caveats(
+                SERVICE_KEY,
+                &[
+                    DelegatedCapability {
+                        attestation_type: AttestationTypes::FollowAttestation,
+                        caveats: caveats.clone(),
+                    },
+                    (&AttestationTypes::BasicPostAttestation).into(),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let parsed = parse_siwe_recap(&message, SERVICE_KEY).unwrap();
+        assert_eq!(
+            parsed.caveats(&AttestationTypes::FollowAttestation),
+            caveats
+        );
+        assert!(parsed
+            .caveats(&AttestationTypes::BasicPostAttestation)
+            .is_empty());
+
+        if session_config
+            .generate_message_with_caveats(
+                SERVICE_KEY,
+                &[DelegatedCapability {
+                    attestation_type: AttestationTypes::BasicPostAttestation,
+                    caveats,
+                }],
+            )
+            .await
+            .is_ok()
+        {
+            panic!("Targets caveat permitted on basic posts");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redelegated_attestation() {
+        let (subject, subject_issuer) = test_did_keypair().await.unwrap();
+        let (session, session_issuer) = test_did_keypair().await.unwrap();
+        let (delegate, delegate_issuer) = test_did_keypair().await.unwrap();
+
+        let root = recap_message_with_caveats(
+            "Ed25519",
+            &subject.did().unwrap(),
+            &session.verification_method().unwrap(),
+            "issue/follow_attestation",
+            r#"[{"targets":["https://example.com/alice","https://example.com/bob"],"max_issuances":3}]"#,
+        );
+        let mut parents = BTreeMap::new();
+        parents.insert(
+            recap_cid(&root).to_string(),
+            SignedReCap {
+                signature: subject_issuer.sign(&root).await.unwrap(),
+                message: root.clone(),
+            },
+        );
+
+        let redelegate = |targets: &[&str], expiration_time: Option<&str>| {
+            redelegation_message(
+                &root,
+                &delegate.verification_method().unwrap(),
+                SERVICE_KEY,
+                &[DelegatedCapability {
+                    attestation_type: AttestationTypes::FollowAttestation,
+                    caveats: Caveats {
+                        targets: Some(targets.iter().map(|t| Url::parse(t).unwrap()).collect()),
+                        max_issuances: Some(1),
+                        ..Default::default()
+                    },
+                }],
+                expiration_time.map(|e| e.to_string()),
+            )
+            .unwrap()
+        };
+
+        let attestation = AttestationStatement::FollowAttestation(FollowAttestationStatement {
+            subject: subject.clone(),
+            target: Url::parse("https://example.com/alice").unwrap(),
+        });
+        let attestation_signature = delegate_issuer
+            .sign(&attestation.generate_statement().unwrap())
+            .await
+            .unwrap();
+        let proof =
+            |siwe_message: String, siwe_signature: String, parents| DelegatedAttestationProof {
+                attestation: attestation.clone(),
+                attestation_signature: attestation_signature.clone(),
+                service_key: SERVICE_KEY.to_string(),
+                siwe_message,
+                siwe_signature,
+                parents,
+            };
+
+        let fresh = || DelegatedAttestationFlow {
+            issuance_store: Some(Arc::new(MemoryIssuanceStore::new())),
+            ..flow()
+        };
+        let flow = fresh();
+        let (_, issuer) = test_did_keypair().await.unwrap();
+
+        let child = redelegate(&["https://example.com/alice"], None);
+        let child_signature = session_issuer.sign(&child).await.unwrap();
+        let parsed = parse_siwe_recap(&child, SERVICE_KEY).unwrap();
+        assert_eq!(parsed.subject.did().unwrap(), session.did().unwrap());
+
+        flow.jwt(
+            proof(child.clone(), child_signature.clone(), parents.clone()),
+            issuer.clone(),
+        )
+        .await
+        .unwrap();
+
+        if flow
+            .jwt(
+                proof(child.clone(), child_signature.clone(), parents.clone()),
+                issuer.clone(),
+            )
+            .await
+            .is_ok()
+        {
+            panic!("Issuance beyond the re-delegated max_issuances permitted");
+        }
+
+        let bad = [
+            // The parent is missing.
+            proof(child.clone(), child_signature.clone(), BTreeMap::new()),
+            // The child is not signed by the parent's delegate.
+            proof(
+                child.clone(),
+                delegate_issuer.sign(&child).await.unwrap(),
+                parents.clone(),
+            ),
+            // The child grants a target the parent does not.
+            {
+                let child = redelegate(&["https://example.com/carol"], None);
+                let sig = session_issuer.sign(&child).await.unwrap();
+                proof(child, sig, parents.clone())
+            },
+            // The child outlives the parent.
+            {
+                let child = redelegate(
+                    &["https://example.com/alice"],
+                    Some("2200-01-01T00:00:00.000Z"),
+                );
+                let sig = session_issuer.sign(&child).await.unwrap();
+                proof(child, sig, parents.clone())
+            },
+        ];
+
+        for p in bad {
+            if fresh().jwt(p, issuer.clone()).await.is_ok() {
+                panic!("Invalid ReCap chain permitted");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revoked_delegated_attestation() {
+        let (subject, subject_issuer) = test_did_keypair().await.unwrap();
+        let (delegate, delegate_issuer) = test_did_keypair().await.unwrap();
+        let siwe_message = recap_message(
+            "Ed25519",
+            &subject.did().unwrap(),
+            &delegate.verification_method().unwrap(),
+        );
+
+        let attestation = post(subject.clone());
+        let proof = DelegatedAttestationProof {
+            attestation_signature: delegate_issuer
+                .sign(&attestation.generate_statement().unwrap())
+                .await
+                .unwrap(),
+            attestation,
+            service_key: SERVICE_KEY.to_string(),
+            siwe_signature: subject_issuer.sign(&siwe_message).await.unwrap(),
+            siwe_message,
+            parents: BTreeMap::new(),
+        };
+
+        let revoke = |delegator: Subjects, target: RevocationTarget| async {
+            let revocation = Revocation {
+                delegator,
+                service_key: SERVICE_KEY.to_string(),
+                target,
+                expires: "2123-09-27T17:11:32.014Z".to_string(),
+            };
+            SignedRevocation {
+                signature: subject_issuer
+                    .sign(&revocation.generate_statement().unwrap())
+                    .await
+                    .unwrap(),
+                revocation,
+            }
+        };
+
+        for target in [
+            RevocationTarget::Nonce("6JQhF2R1wBhfF6ONV".to_string()),
+            RevocationTarget::Delegate(delegate.did().unwrap()),
+        ] {
+            let flow = DelegatedAttestationFlow {
+                revocation_store: Some(Arc::new(MemoryRevocationStore::new())),
+                ..flow()
+            };
+            let (_, issuer) = test_did_keypair().await.unwrap();
+            flow.jwt(proof.clone(), issuer.clone()).await.unwrap();
+
+            // Only the delegator can revoke their ReCaps.
+            let mut forged = revoke(subject.clone(), target.clone()).await;
+            forged.revocation.delegator = delegate.clone();
+            if flow.revoke(&forged).await.is_ok() {
+                panic!("Revocation signed by someone else permitted");
+            }
+
+            let mut elsewhere = revoke(subject.clone(), target.clone()).await;
+            elsewhere.revocation.service_key = "rebase:did:web:example.com".to_string();
+            if flow.revoke(&elsewhere).await.is_ok() {
+                panic!("Revocation for another witness permitted");
+            }
+
+            let mut expired = revoke(subject.clone(), target.clone()).await;
+            expired.revocation.expires = "2023-09-27T17:11:32.014Z".to_string();
+            expired.signature = subject_issuer
+                .sign(&expired.revocation.generate_statement().unwrap())
+                .await
+                .unwrap();
+            if flow.revoke(&expired).await.is_ok() {
+                panic!("Expired revocation permitted");
+            }
+            flow.jwt(proof.clone(), issuer.clone()).await.unwrap();
+
+            flow.revoke(&revoke(subject.clone(), target).await)
+                .await
+                .unwrap();
+            if flow.jwt(proof.clone(), issuer).await.is_ok() {
+                panic!("Revoked ReCap permitted");
+            }
+        }
+    }
 }
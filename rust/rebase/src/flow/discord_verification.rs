@@ -0,0 +1,312 @@
+use crate::{
+    content::discord_verification::DiscordVerificationContent as Ctnt,
+    proof::discord_verification::DiscordVerificationProof as Prf,
+    statement::discord_verification::DiscordVerificationStatement as Stmt,
+    types::{
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
+        error::FlowError,
+    },
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION},
+    Client,
+};
+use schemars::schema_for;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct DiscordVerificationFlow {
+    // The bot must be in the channels' servers and have the message content intent.
+    pub bot_token: String,
+    pub delimiter: String,
+    // The public channels statements may be posted in.
+    pub channel_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DiscordAuthor {
+    pub id: String,
+    pub username: String,
+    // "0" for accounts that have moved to unique usernames.
+    #[serde(default)]
+    pub discriminator: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DiscordMessage {
+    pub channel_id: String,
+    pub content: String,
+    pub author: DiscordAuthor,
+    pub timestamp: String,
+}
+
+impl DiscordAuthor {
+    // username is how the account is named in statements.
+    fn username(&self) -> String {
+        match self.discriminator.as_deref() {
+            None | Some("0") => self.username.clone(),
+            Some(d) => format!("{}#{}", self.username, d),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Flow<Ctnt, Stmt, Prf> for DiscordVerificationFlow {
+    fn instructions(&self) -> Result<Instructions, FlowError> {
+        Ok(Instructions {
+            statement: "Enter your Discord username to verify and include in a signed message using your wallet.".to_string(),
+            statement_schema: schema_for!(Stmt),
+            signature: "Sign the message presented to you containing your Discord username and additional information.".to_string(),
+            witness: "Post the statement and signature in the designated Discord channel to create a link between your identifier and Discord account.".to_string(),
+            witness_schema: schema_for!(Prf),
+        })
+    }
+
+    async fn statement<I: Issuer + Send + Clone>(
+        &self,
+        statement: Stmt,
+        _issuer: I,
+    ) -> Result<StatementResponse, FlowError> {
+        Ok(StatementResponse {
+            delimiter: Some(self.delimiter.to_owned()),
+            typed_data: None,
+            statement: statement.generate_statement()?,
+        })
+    }
+
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
+    async fn validate_proof<I: Issuer + Send>(
+        &self,
+        proof: Prf,
+        _issuer: I,
+    ) -> Result<Ctnt, FlowError> {
+        let snowflake = Regex::new(r"^[0-9]{1,20}$")
+            .map_err(|_| FlowError::BadLookup("could not generate id regex".to_string()))?;
+        if !snowflake.is_match(&proof.channel_id) || !snowflake.is_match(&proof.message_id) {
+            return Err(FlowError::Validation(
+                "channel or message id invalid".to_string(),
+            ));
+        }
+
+        if !self.channel_ids.contains(&proof.channel_id) {
+            return Err(FlowError::Validation(format!(
+                "channel {} is not one statements may be posted in",
+                proof.channel_id
+            )));
+        }
+
+        let mut headers = HeaderMap::new();
+        let s: HeaderValue = format!("Bot {}", &self.bot_token).parse().map_err(|_| {
+            FlowError::Misconfigured("failed to generate authorization header".to_string())
+        })?;
+        headers.insert(AUTHORIZATION, s);
+
+        let req = Client::new()
+            .get(make_url(
+                &self.http_opts,
+                "https://discord.com",
+                &format!(
+                    "/api/v10/channels/{}/messages/{}",
+                    proof.channel_id, proof.message_id
+                ),
+            )?)
+            .headers(headers);
+
+        let res: DiscordMessage = send_request(&self.http_opts, req)
+            .await?
+            .json()
+            .await
+            .map_err(|e| FlowError::BadLookup(e.to_string()))?;
+
+        if res.channel_id != proof.channel_id {
+            return Err(FlowError::Validation(format!(
+                "message was posted in {} not {}",
+                res.channel_id, proof.channel_id
+            )));
+        }
+
+        if !res
+            .author
+            .username()
+            .eq_ignore_ascii_case(&proof.statement.username)
+        {
+            return Err(FlowError::Validation(format!(
+                "unexpected username, wanted: {} got: {}",
+                proof.statement.username.to_lowercase(),
+                res.author.username().to_lowercase()
+            )));
+        }
+
+        if let Some(id) = &proof.statement.user_id {
+            if *id != res.author.id {
+                return Err(FlowError::Validation(format!(
+                    "unexpected user id, wanted: {} got: {}",
+                    id, res.author.id
+                )));
+            }
+        }
+
+        let mut a = res.content.split(&self.delimiter);
+        match (a.next(), a.next()) {
+            (Some(stmt), Some(sig)) => {
+                let expected = proof.statement.generate_statement()?;
+                if stmt.trim() != expected {
+                    return Err(FlowError::Validation(format!(
+                        "statement mismatch, expected: {}, got: {}",
+                        expected,
+                        stmt.trim()
+                    )));
+                }
+                let sig = sig.trim();
+                proof
+                    .statement
+                    .subject
+                    .valid_signature_with_opts(&expected, sig, &self.resolver_opts, &self.eth_rpc)
+                    .await?;
+                let posted = DateTime::parse_from_rfc3339(&res.timestamp)
+                    .map_err(|e| FlowError::BadLookup(e.to_string()))?;
+                Ok(Ctnt {
+                    user_id: Some(res.author.id),
+                    timestamp: Some(
+                        posted
+                            .with_timezone(&Utc)
+                            .to_rfc3339_opts(SecondsFormat::Millis, true),
+                    ),
+                    ..proof.to_content(&expected, sig)?
+                })
+            }
+            _ => Err(FlowError::Validation(
+                "Could not parse signature and statement from message".to_owned(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::util::{test_did_keypair, MockIssuer, StubTransport},
+        types::defs::Issuer,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const CHANNEL: &str = "1100000000000000001";
+    const MESSAGE: &str = "1200000000000000002";
+
+    fn stub_flow(content: String, username: &str) -> DiscordVerificationFlow {
+        DiscordVerificationFlow {
+            bot_token: "token".to_string(),
+            delimiter: "\n\n".to_string(),
+            channel_ids: vec![CHANNEL.to_string()],
+            http_opts: Some(HttpOpts {
+                base_url: Some("http://stub.test".to_string()),
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![(
+                        format!(
+                            "http://stub.test/api/v10/channels/{}/messages/{}",
+                            CHANNEL, MESSAGE
+                        ),
+                        json!({
+                            "channel_id": CHANNEL,
+                            "content": content,
+                            "timestamp": "2024-03-01T12:00:00.123000+00:00",
+                            "author": {
+                                "id": "1300000000000000003",
+                                "username": username,
+                                "discriminator": "0"
+                            }
+                        })
+                        .to_string(),
+                    )],
+                })),
+            }),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn discord_stub_transport() {
+        let i = MockIssuer {};
+        let (subj1, iss1) = test_did_keypair().await.unwrap();
+        let (_, iss2) = test_did_keypair().await.unwrap();
+
+        let proof = Prf {
+            statement: Stmt {
+                username: "alice".to_owned(),
+                user_id: None,
+                subject: subj1,
+            },
+            channel_id: CHANNEL.to_owned(),
+            message_id: MESSAGE.to_owned(),
+        };
+        let statement = proof.generate_statement().unwrap();
+        let signature = iss1.sign(&statement).await.unwrap();
+        let content = format!("{}\n\n{}", statement, signature);
+
+        let flow = stub_flow(content.clone(), "alice");
+        let c = flow.validate_proof(proof.clone(), i.clone()).await.unwrap();
+        assert_eq!(c.user_id.as_deref(), Some("1300000000000000003"));
+        assert_eq!(c.timestamp.as_deref(), Some("2024-03-01T12:00:00.123Z"));
+        flow.credential(proof.clone(), iss1.clone()).await.unwrap();
+
+        // Make sure it fails correctly:
+        let wrong_id = Prf {
+            statement: Stmt {
+                user_id: Some("1300000000000000004".to_owned()),
+                ..proof.statement.clone()
+            },
+            ..proof.clone()
+        };
+        if flow.validate_proof(wrong_id, i.clone()).await.is_ok() {
+            panic!("Approved message by another user id");
+        };
+
+        let elsewhere = Prf {
+            channel_id: "1100000000000000009".to_owned(),
+            ..proof.clone()
+        };
+        if flow.validate_proof(elsewhere, i.clone()).await.is_ok() {
+            panic!("Approved message in undesignated channel");
+        };
+
+        let flow = stub_flow(content, "mallory");
+        if flow.validate_proof(proof.clone(), i.clone()).await.is_ok() {
+            panic!("Approved message by another user");
+        };
+
+        let bad_sig = iss2.sign(&statement).await.unwrap();
+        let flow = stub_flow(format!("{}\n\n{}", statement, bad_sig), "alice");
+        if flow.validate_proof(proof, i).await.is_ok() {
+            panic!("Approved bad signature");
+        };
+    }
+}
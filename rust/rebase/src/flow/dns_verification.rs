@@ -2,7 +2,10 @@ use crate::{
     content::dns_verification::DnsVerificationContent as Ctnt,
     statement::dns_verification::DnsVerificationStatement as Stmt,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
         error::FlowError,
     },
 };
@@ -12,12 +15,23 @@ use reqwest::Client;
 use schemars::schema_for;
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
-use url::Url;
 use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
-pub struct DnsVerificationFlow {}
+pub struct DnsVerificationFlow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
+}
 
 #[derive(Deserialize, Debug)]
 pub struct DnsResponse {
@@ -52,26 +66,29 @@ impl Flow<Ctnt, Stmt, Stmt> for DnsVerificationFlow {
         Ok(StatementResponse {
             statement: statement.generate_statement()?,
             delimiter: None,
+            typed_data: None,
         })
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Stmt,
         _issuer: I,
     ) -> Result<Ctnt, FlowError> {
         let client = Client::new();
-        let request_url = format!(
-            "https://cloudflare-dns.com/dns-query?name={}&type=txt",
-            &proof.domain
-        );
-
-        let res: DnsResponse = client
-            .get(Url::parse(&request_url).map_err(|e| FlowError::BadLookup(e.to_string()))?)
-            .header("accept", "application/dns-json")
-            .send()
-            .await
-            .map_err(|e| FlowError::BadLookup(e.to_string()))?
+        let u = make_url(
+            &self.http_opts,
+            "https://cloudflare-dns.com",
+            &format!("/dns-query?name={}&type=txt", &proof.domain),
+        )?;
+
+        let req = client.get(u).header("accept", "application/dns-json");
+        let res: DnsResponse = send_request(&self.http_opts, req)
+            .await?
             .json()
             .await
             .map_err(|e| FlowError::BadLookup(e.to_string()))?;
@@ -85,14 +102,15 @@ impl Flow<Ctnt, Stmt, Stmt> for DnsVerificationFlow {
             if sig.starts_with(&proof.prefix) {
                 sig = sig.trim_start_matches(&proof.prefix);
                 let stmt = proof.generate_statement()?;
-                proof.subject.valid_signature(&stmt, sig).await?;
+                proof
+                    .subject
+                    .valid_signature_with_opts(&stmt, sig, &self.resolver_opts, &self.eth_rpc)
+                    .await?;
                 return Ok(proof.to_content(&stmt, sig)?);
             }
         }
 
-        Err(FlowError::BadLookup(
-            "expected record not found".to_string(),
-        ))
+        Err(FlowError::NotFound("expected record not found".to_string()))
     }
 }
 
@@ -101,15 +119,18 @@ mod tests {
     use super::*;
     use crate::{
         test_util::util::{
-            test_did_keypair, test_ed25519_did, test_eth_did, test_solana_did,
-            test_witness_signature, test_witness_statement, MockFlow, MockIssuer, TestKey,
-            TestWitness,
+            test_did_keypair, test_ed25519_did, test_eth_did, test_resolver_opts, test_solana_did,
+            test_witness_signature, test_witness_statement, MockFlow, MockIssuer, StubTransport,
+            TestKey, TestWitness,
         },
         types::{
-            defs::{Issuer, Proof, Statement, Subject},
+            defs::{check_validity, Issuer, Proof, Statement},
             enums::subject::Subjects,
         },
     };
+    use chrono::{DateTime, Duration, Utc};
+    use serde_json::json;
+    use std::sync::Arc;
 
     fn mock_proof(key: fn() -> Subjects) -> Stmt {
         Stmt {
@@ -140,6 +161,7 @@ mod tests {
             Ok(StatementResponse {
                 statement: statement.generate_statement()?,
                 delimiter: None,
+                typed_data: None,
             })
         }
 
@@ -159,7 +181,12 @@ mod tests {
 
             proof
                 .subject
-                .valid_signature(&self.statement, &self.signature)
+                .valid_signature_with_opts(
+                    &self.statement,
+                    &self.signature,
+                    &Some(test_resolver_opts()),
+                    &None,
+                )
                 .await?;
 
             Ok(proof
@@ -259,4 +286,70 @@ mod tests {
             panic!("Approved bad signature");
         };
     }
+
+    #[tokio::test]
+    async fn dns_stub_transport() {
+        let i = MockIssuer {};
+        let (subj1, iss1) = test_did_keypair().await.unwrap();
+        let (_, iss2) = test_did_keypair().await.unwrap();
+
+        let proof = Stmt {
+            subject: subj1,
+            domain: "example.com".to_owned(),
+            prefix: "rebase_sig=".to_owned(),
+        };
+
+        let statement = proof.generate_statement().unwrap();
+        let stub_flow = |signature: String| DnsVerificationFlow {
+            http_opts: Some(HttpOpts {
+                base_url: Some("http://stub.test".to_string()),
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![(
+                        "http://stub.test/dns-query?name=example.com".to_string(),
+                        json!({
+                            "Answer": [{
+                                "name": "example.com",
+                                "data": format!("\"rebase_sig={}\"", signature)
+                            }]
+                        })
+                        .to_string(),
+                    )],
+                })),
+            }),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: Some(60),
+        };
+
+        let flow = stub_flow(iss1.sign(&statement).await.unwrap());
+        flow.validate_proof(proof.clone(), i.clone()).await.unwrap();
+
+        let vc = flow.credential(proof.clone(), iss1.clone()).await.unwrap();
+        let issued: DateTime<Utc> = vc.issuance_date.clone().unwrap().into();
+        let expires: DateTime<Utc> = vc.expiration_date.clone().unwrap().into();
+        assert_eq!(expires - issued, Duration::minutes(60));
+        check_validity(&vc).unwrap();
+
+        let mut expired = vc.clone();
+        expired.expiration_date = Some((Utc::now() - Duration::minutes(1)).into());
+        if check_validity(&expired).is_ok() {
+            panic!("Accepted expired credential");
+        }
+
+        // A slightly fast issuer clock is tolerated, anything further ahead is not yet valid.
+        let mut early = vc.clone();
+        early.issuance_date = Some((Utc::now() + Duration::seconds(30)).into());
+        check_validity(&early).unwrap();
+        early.issuance_date = Some((Utc::now() + Duration::minutes(5)).into());
+        match check_validity(&early) {
+            Err(FlowError::NotYetValid(_)) => {}
+            r => panic!("Expected not yet valid, got {:?}", r),
+        }
+
+        // Make sure it fails correctly:
+        let flow = stub_flow(iss2.sign(&statement).await.unwrap());
+        if flow.validate_proof(proof, i).await.is_ok() {
+            panic!("Approved bad signature");
+        };
+    }
 }
@@ -3,8 +3,12 @@ use crate::{
     proof::email_verification::EmailVerificationProof as Prf,
     statement::email_verification::EmailVerificationStatement as Stmt,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse, Subject,
+        },
         error::FlowError,
+        nonce::{check_rate_limit, consume_challenge, nonce_store, NonceStore, RateLimit},
     },
 };
 
@@ -17,8 +21,8 @@ use reqwest::{
 use schemars::schema_for;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::sync::Arc;
 use tsify::Tsify;
-use url::Url;
 use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
@@ -32,6 +36,24 @@ pub struct SendGridBasicFlow {
     // Alternative is casting u64 to i64 and risking UB.
     pub max_elapsed_minutes: i64,
     pub subject_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // Limits how many challenge emails a subject may request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
+    // Remembers used challenges, defaults to an in-process store.
+    // Not serializable, so must be set in code after the flow is deserialized.
+    #[serde(skip)]
+    pub nonce_store: Option<Arc<dyn NonceStore>>,
 }
 
 // NOTE: This is forced into a trait so it can be made into an async trait
@@ -97,6 +119,18 @@ impl Flow<Ctnt, Stmt, Prf> for SendGridBasicFlow {
         stmt: Stmt,
         issuer: I,
     ) -> Result<StatementResponse, FlowError> {
+        // Limit both who asks and whose inbox is mailed, so switching DIDs doesn't allow
+        // flooding an address.
+        for key in [stmt.subject.did()?, stmt.email.to_lowercase()] {
+            check_rate_limit(
+                nonce_store(&self.nonce_store),
+                &self.rate_limit,
+                "email_verification",
+                &key,
+            )
+            .await?;
+        }
+
         let statement = stmt.generate_statement()?;
         let f = self.body(stmt.clone(), issuer.clone());
         let b = f.await?;
@@ -124,16 +158,15 @@ impl Flow<Ctnt, Stmt, Prf> for SendGridBasicFlow {
             }
         });
 
-        let u = Url::parse("https://api.sendgrid.com/v3/mail/send")
-            .map_err(|e| FlowError::BadLookup(format!("Failed to parse email API Url: {}", e)))?;
+        let u = make_url(&self.http_opts, "https://api.sendgrid.com", "/v3/mail/send")?;
 
         let key_header: HeaderValue =
             format!("Bearer {}", &self.api_key).parse().map_err(|_| {
-                FlowError::BadLookup("Failed to generate authorization header".to_string())
+                FlowError::Misconfigured("Failed to generate authorization header".to_string())
             })?;
 
         let content_header: HeaderValue = "application/json".parse().map_err(|_| {
-            FlowError::BadLookup("Failed to generate authorization header".to_string())
+            FlowError::Misconfigured("Failed to generate authorization header".to_string())
         })?;
 
         let mut headers = HeaderMap::new();
@@ -142,27 +175,26 @@ impl Flow<Ctnt, Stmt, Prf> for SendGridBasicFlow {
 
         let client = Client::new();
 
-        client
-            .post(u)
-            .headers(headers)
-            .json(&req)
-            .send()
-            .await
-            .map_err(|e| FlowError::BadLookup(format!("Could not send email: {}", e)))?;
+        send_request(&self.http_opts, client.post(u).headers(headers).json(&req)).await?;
 
         Ok(StatementResponse {
             statement,
             delimiter: None,
+            typed_data: None,
         })
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Prf,
         issuer: I,
     ) -> Result<Ctnt, FlowError> {
         if self.max_elapsed_minutes <= 0 {
-            return Err(FlowError::Validation(
+            return Err(FlowError::Misconfigured(
                 "Max elapsed minutes must be set to a number greater than 0".to_string(),
             ));
         }
@@ -182,7 +214,7 @@ impl Flow<Ctnt, Stmt, Prf> for SendGridBasicFlow {
             DateTime::parse_from_rfc3339(ts).map_err(|e| FlowError::Validation(e.to_string()))?;
 
         if now - Duration::minutes(self.max_elapsed_minutes) > then {
-            return Err(FlowError::Validation(
+            return Err(FlowError::Expired(
                 "Validation window has expired".to_string(),
             ));
         }
@@ -201,9 +233,18 @@ impl Flow<Ctnt, Stmt, Prf> for SendGridBasicFlow {
         proof
             .statement
             .subject
-            .valid_signature(&s, &proof.signature)
+            .valid_signature_with_opts(&s, &proof.signature, &self.resolver_opts, &self.eth_rpc)
             .await?;
 
+        // Within the window, each challenge may only be used once.
+        consume_challenge(
+            nonce_store(&self.nonce_store),
+            "email_verification",
+            ch,
+            then.with_timezone(&Utc) + Duration::minutes(self.max_elapsed_minutes),
+        )
+        .await?;
+
         Ok(proof.to_content(&s, &proof.signature)?)
     }
 }
@@ -216,10 +257,69 @@ mod tests {
 
     use super::*;
     use crate::{
-        test_util::util::test_did_keypair,
-        types::defs::{Issuer, Statement},
+        test_util::util::{test_did_keypair, StubTransport},
+        types::{
+            defs::{HttpOpts, Issuer, Statement},
+            enums::subject::Subjects,
+            nonce::MemoryNonceStore,
+        },
     };
 
+    #[tokio::test]
+    async fn test_statement_rate_limit() {
+        let (_, i) = test_did_keypair().await.unwrap();
+        let (subj1, _) = test_did_keypair().await.unwrap();
+        let (subj2, _) = test_did_keypair().await.unwrap();
+        let flow = SendGridBasicFlow {
+            api_key: "unimplemented".to_string(),
+            challenge_delimiter: ":::".to_string(),
+            from_addr: "unimplemented".to_string(),
+            from_name: "unimplemented".to_string(),
+            subject_name: "unimplemented".to_string(),
+            max_elapsed_minutes: 10,
+            http_opts: Some(HttpOpts {
+                base_url: None,
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![("https://api.sendgrid.com/".to_string(), "{}".to_string())],
+                })),
+            }),
+            rate_limit: Some(RateLimit {
+                max_requests: 1,
+                window_minutes: 10,
+            }),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+            nonce_store: Some(Arc::new(MemoryNonceStore::new())),
+        };
+        let stmt = |subject: &Subjects, email: &str| Stmt {
+            subject: subject.clone(),
+            email: email.to_string(),
+        };
+
+        flow.statement(stmt(&subj1, "victim@example.com"), i.clone())
+            .await
+            .unwrap();
+
+        // Another DID may not mail the same address.
+        if flow
+            .statement(stmt(&subj2, "Victim@example.com"), i.clone())
+            .await
+            .is_ok()
+        {
+            panic!("Rate limit bypassed by switching DIDs");
+        }
+
+        // Nor may the same DID mail another address.
+        if flow
+            .statement(stmt(&subj1, "other@example.com"), i)
+            .await
+            .is_ok()
+        {
+            panic!("Rate limit bypassed by switching addresses");
+        }
+    }
+
     #[tokio::test]
     async fn mock_email() {
         // get witness' issuer:
@@ -243,6 +343,12 @@ mod tests {
             from_name: "unimplemented".to_string(),
             subject_name: "unimplemented".to_string(),
             max_elapsed_minutes: 10,
+            http_opts: None,
+            rate_limit: None,
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+            nonce_store: Some(Arc::new(MemoryNonceStore::new())),
         };
 
         let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
@@ -266,6 +372,13 @@ mod tests {
 
         flow.jwt(ver_proof1.clone(), i.clone()).await.unwrap();
 
+        // Test it detects a replayed challenge.
+
+        match flow.jwt(ver_proof1.clone(), i.clone()).await {
+            Err(_) => {}
+            Ok(_) => panic!("Accepted replayed challenge"),
+        }
+
         // Test it detects a bad signature.
 
         let (_, iss2) = test_did_keypair().await.unwrap();
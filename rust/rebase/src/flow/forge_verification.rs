@@ -0,0 +1,357 @@
+use crate::{
+    content::forge_verification::ForgeVerificationContent as Ctnt,
+    proof::forge_verification::ForgeVerificationProof as Prf,
+    statement::forge_verification::ForgeVerificationStatement as Stmt,
+    types::{
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
+        error::FlowError,
+    },
+};
+
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, USER_AGENT},
+    Client, RequestBuilder,
+};
+use schemars::schema_for;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use url::Url;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, Tsify)]
+pub enum ForgeKind {
+    GitLab,
+    // Also covers Forgejo, e.g. codeberg.org, which serves the same API.
+    Gitea,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+pub struct ForgeInstance {
+    pub kind: ForgeKind,
+    // The base URL of the instance, e.g. https://gitlab.com
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ForgeVerificationFlow {
+    pub user_agent: String,
+    pub delimiter: String,
+    // The forges accounts can be linked on. Statements name one by its domain.
+    pub instances: Vec<ForgeInstance>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct GitLabSnippet {
+    pub author: GitLabAuthor,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct GitLabAuthor {
+    pub username: String,
+}
+
+impl ForgeVerificationFlow {
+    // instance finds the configured forge with the given domain.
+    fn instance(&self, domain: &str) -> Result<&ForgeInstance, FlowError> {
+        for i in &self.instances {
+            let u = Url::parse(&i.url).map_err(|e| {
+                FlowError::Misconfigured(format!("forge url {} invalid: {}", i.url, e))
+            })?;
+            if u.host_str()
+                .map(|h| h.eq_ignore_ascii_case(domain))
+                .unwrap_or(false)
+            {
+                return Ok(i);
+            }
+        }
+
+        Err(FlowError::Validation(format!(
+            "{} is not a supported forge",
+            domain
+        )))
+    }
+
+    fn get(&self, instance: &ForgeInstance, path: &str) -> Result<RequestBuilder, FlowError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            USER_AGENT,
+            self.user_agent.to_string().parse().map_err(|_| {
+                FlowError::Misconfigured("could not generate header for lookup".to_string())
+            })?,
+        );
+
+        let u = make_url(&self.http_opts, &instance.url, path)?;
+        Ok(Client::new().get(u).headers(headers))
+    }
+
+    async fn text(&self, req: RequestBuilder) -> Result<String, FlowError> {
+        send_request(&self.http_opts, req)
+            .await?
+            .text()
+            .await
+            .map_err(|e| FlowError::BadLookup(e.to_string()))
+    }
+
+    // snippet finds the text of the snippet or file, checking it belongs to the account.
+    async fn snippet(&self, instance: &ForgeInstance, proof: &Prf) -> Result<String, FlowError> {
+        let handle = Regex::new(r"^[a-zA-Z0-9_.-]+$")
+            .map_err(|_| FlowError::BadLookup("could not generate handle regex".to_string()))?;
+        if !handle.is_match(&proof.statement.handle) {
+            return Err(FlowError::Validation("handle invalid".to_string()));
+        }
+
+        match instance.kind {
+            ForgeKind::GitLab => {
+                let re = Regex::new(r"^[0-9]+$").map_err(|_| {
+                    FlowError::BadLookup("could not generate snippet id regex".to_string())
+                })?;
+                if !re.is_match(&proof.snippet_id) {
+                    return Err(FlowError::Validation("snippet id invalid".to_string()));
+                }
+
+                let path = format!("/api/v4/snippets/{}", proof.snippet_id);
+                let res: GitLabSnippet = send_request(&self.http_opts, self.get(instance, &path)?)
+                    .await?
+                    .json()
+                    .await
+                    .map_err(|e| FlowError::BadLookup(e.to_string()))?;
+
+                if !res
+                    .author
+                    .username
+                    .eq_ignore_ascii_case(&proof.statement.handle)
+                {
+                    return Err(FlowError::Validation(format!(
+                        "handle mismatch, expected: {}, got: {}",
+                        proof.statement.handle.to_lowercase(),
+                        res.author.username.to_lowercase()
+                    )));
+                }
+
+                self.text(self.get(instance, &format!("{}/raw", path))?)
+                    .await
+            }
+            ForgeKind::Gitea => {
+                // The file is looked up under the account's namespace, so only it can write it.
+                let re = Regex::new(r"^[a-zA-Z0-9_.-]+/[a-zA-Z0-9_./-]+$").map_err(|_| {
+                    FlowError::BadLookup("could not generate snippet id regex".to_string())
+                })?;
+                if !re.is_match(&proof.snippet_id)
+                    || proof.snippet_id.split('/').any(|s| s == ".." || s == ".")
+                {
+                    return Err(FlowError::Validation("snippet id invalid".to_string()));
+                }
+
+                let (repo, file) = proof
+                    .snippet_id
+                    .split_once('/')
+                    .ok_or_else(|| FlowError::Validation("snippet id invalid".to_string()))?;
+                let path = format!(
+                    "/api/v1/repos/{}/{}/raw/{}",
+                    proof.statement.handle, repo, file
+                );
+                self.text(self.get(instance, &path)?).await
+            }
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Flow<Ctnt, Stmt, Prf> for ForgeVerificationFlow {
+    fn instructions(&self) -> Result<Instructions, FlowError> {
+        Ok(Instructions {
+            statement: "Enter your account handle and the domain of the forge it is on to verify and include in a signed message using your wallet.".to_string(),
+            statement_schema: schema_for!(Stmt),
+            signature: "Sign the message presented to you containing your handle and additional information.".to_string(),
+            witness: "Create a public snippet with this message, or on Gitea and Forgejo a file in a public repository, to create a link between your identifier and your forge account.".to_string(),
+            witness_schema: schema_for!(Prf),
+        })
+    }
+
+    async fn statement<I: Issuer + Send + Clone>(
+        &self,
+        statement: Stmt,
+        _issuer: I,
+    ) -> Result<StatementResponse, FlowError> {
+        self.instance(&statement.domain)?;
+        Ok(StatementResponse {
+            statement: statement.generate_statement()?,
+            delimiter: Some(self.delimiter.to_owned()),
+            typed_data: None,
+        })
+    }
+
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
+    async fn validate_proof<I: Issuer + Send>(
+        &self,
+        proof: Prf,
+        _issuer: I,
+    ) -> Result<Ctnt, FlowError> {
+        let instance = self.instance(&proof.statement.domain)?;
+        let text = self.snippet(instance, &proof).await?;
+
+        let mut a = text.split(&self.delimiter);
+        match (a.next(), a.next()) {
+            (Some(stmt), Some(sig)) => {
+                let expected = proof.statement.generate_statement()?;
+                if stmt.trim() != expected {
+                    return Err(FlowError::Validation(format!(
+                        "statement mismatch, expected: {}, got: {}",
+                        expected,
+                        stmt.trim()
+                    )));
+                }
+                let sig = sig.trim();
+                proof
+                    .statement
+                    .subject
+                    .valid_signature_with_opts(&expected, sig, &self.resolver_opts, &self.eth_rpc)
+                    .await?;
+                Ok(proof.to_content(&expected, sig)?)
+            }
+            _ => Err(FlowError::NotFound(format!(
+                "Failed to find the statement and signature in {}",
+                proof.snippet_id
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::util::{test_did_keypair, MockIssuer, StubTransport},
+        types::defs::Issuer,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn stub_flow(text: String, author: &str) -> ForgeVerificationFlow {
+        ForgeVerificationFlow {
+            user_agent: "rebase-test".to_string(),
+            delimiter: "\n\n".to_string(),
+            instances: vec![
+                ForgeInstance {
+                    kind: ForgeKind::GitLab,
+                    url: "https://gitlab.com".to_string(),
+                },
+                ForgeInstance {
+                    kind: ForgeKind::Gitea,
+                    url: "https://codeberg.org".to_string(),
+                },
+            ],
+            http_opts: Some(HttpOpts {
+                base_url: Some("http://stub.test".to_string()),
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![
+                        (
+                            "http://stub.test/api/v4/snippets/42/raw".to_string(),
+                            text.clone(),
+                        ),
+                        (
+                            "http://stub.test/api/v4/snippets/42".to_string(),
+                            json!({ "author": { "username": author } }).to_string(),
+                        ),
+                        (
+                            "http://stub.test/api/v1/repos/alice/rebase/raw/proof.txt".to_string(),
+                            text,
+                        ),
+                    ],
+                })),
+            }),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn forge_stub_transport() {
+        let i = MockIssuer {};
+        let (subj1, iss1) = test_did_keypair().await.unwrap();
+        let (_, iss2) = test_did_keypair().await.unwrap();
+
+        let gitlab = Prf {
+            snippet_id: "42".to_owned(),
+            statement: Stmt {
+                handle: "alice".to_owned(),
+                domain: "gitlab.com".to_owned(),
+                subject: subj1.clone(),
+            },
+        };
+        let statement = gitlab.generate_statement().unwrap();
+        let signature = iss1.sign(&statement).await.unwrap();
+        let flow = stub_flow(format!("{}\n\n{}", statement, signature), "alice");
+        flow.credential(gitlab.clone(), iss1.clone()).await.unwrap();
+
+        let codeberg = Prf {
+            snippet_id: "rebase/proof.txt".to_owned(),
+            statement: Stmt {
+                handle: "alice".to_owned(),
+                domain: "codeberg.org".to_owned(),
+                subject: subj1,
+            },
+        };
+        let statement = codeberg.generate_statement().unwrap();
+        let signature = iss1.sign(&statement).await.unwrap();
+        let flow = stub_flow(format!("{}\n\n{}", statement, signature), "alice");
+        flow.validate_proof(codeberg.clone(), i.clone())
+            .await
+            .unwrap();
+
+        // Make sure it fails correctly:
+        let statement = gitlab.generate_statement().unwrap();
+        let signature = iss1.sign(&statement).await.unwrap();
+        let flow = stub_flow(format!("{}\n\n{}", statement, signature), "mallory");
+        if flow.validate_proof(gitlab.clone(), i.clone()).await.is_ok() {
+            panic!("Approved snippet by another account");
+        };
+
+        let bad_sig = iss2.sign(&statement).await.unwrap();
+        let flow = stub_flow(format!("{}\n\n{}", statement, bad_sig), "alice");
+        if flow.validate_proof(gitlab.clone(), i.clone()).await.is_ok() {
+            panic!("Approved bad signature");
+        };
+
+        let unknown = Prf {
+            statement: Stmt {
+                domain: "git.example.org".to_owned(),
+                ..gitlab.statement.clone()
+            },
+            ..gitlab
+        };
+        if flow.validate_proof(unknown, i.clone()).await.is_ok() {
+            panic!("Approved unconfigured forge");
+        };
+
+        let escape = Prf {
+            snippet_id: "rebase/../../mallory/rebase/raw/proof.txt".to_owned(),
+            ..codeberg
+        };
+        if flow.validate_proof(escape, i).await.is_ok() {
+            panic!("Approved path outside of the account's namespace");
+        };
+    }
+}
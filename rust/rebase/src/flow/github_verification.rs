@@ -4,7 +4,10 @@ use crate::{
     statement::github_verification::GitHubVerificationStatement as Stmt,
     types::{
         error::FlowError,
-        defs::{Flow, StatementResponse, Issuer, Proof, Statement, Subject, Instructions},
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
     },
 };
 
@@ -18,7 +21,6 @@ use schemars::schema_for;
 use serde::{Deserialize, Serialize};
 use serde_json::map::Map;
 use tsify::Tsify;
-use url::Url;
 use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
@@ -26,6 +28,17 @@ use wasm_bindgen::prelude::*;
 pub struct GitHubVerificationFlow {
     pub user_agent: String,
     pub delimiter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
 }
 
 #[derive(Deserialize, Debug, Serialize)]
@@ -67,13 +80,17 @@ impl Flow<Ctnt, Stmt, Prf> for GitHubVerificationFlow {
     ) -> Result<StatementResponse, FlowError> {
         Ok(StatementResponse {
             statement: statement.generate_statement()?,
-            delimiter: Some(self.delimiter.to_owned())
+            delimiter: Some(self.delimiter.to_owned()),
+            typed_data: None,
         })
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
     async fn validate_proof<I: Issuer + Send>(&self, proof: Prf, _issuer: I) -> Result<Ctnt, FlowError> {
         let client = Client::new();
-        let request_url = format!("https://api.github.com/gists/{}", proof.gist_id);
         let re = Regex::new(r"^[a-zA-Z0-9]{32}$")
             .map_err(|_| FlowError::BadLookup("could not generate gist id regex".to_string()))?;
 
@@ -85,16 +102,18 @@ impl Flow<Ctnt, Stmt, Prf> for GitHubVerificationFlow {
         headers.insert(
             USER_AGENT,
             self.user_agent.to_string().parse().map_err(|_| {
-                FlowError::BadLookup("could not generate header for lookup".to_string())
+                FlowError::Misconfigured("could not generate header for lookup".to_string())
             })?,
         );
 
-        let res: GitHubResponse = client
-            .get(Url::parse(&request_url).map_err(|e| FlowError::BadLookup(e.to_string()))?)
-            .headers(headers)
-            .send()
-            .await
-            .map_err(|e| FlowError::BadLookup(e.to_string()))?
+        let u = make_url(
+            &self.http_opts,
+            "https://api.github.com",
+            &format!("/gists/{}", proof.gist_id),
+        )?;
+
+        let res: GitHubResponse = send_request(&self.http_opts, client.get(u).headers(headers))
+            .await?
             .json()
             .await
             .map_err(|e| FlowError::BadLookup(e.to_string()))?;
@@ -134,7 +153,11 @@ impl Flow<Ctnt, Stmt, Prf> for GitHubVerificationFlow {
                     if stmt != proof.statement.generate_statement()? {
                         continue;
                     }
-                    proof.statement.subject.valid_signature(stmt, sig).await?;
+                    proof
+                        .statement
+                        .subject
+                        .valid_signature_with_opts(stmt, sig, &self.resolver_opts, &self.eth_rpc)
+                        .await?;
                     return Ok(proof.to_content(stmt, sig)?)
                 }
                 _ => continue
@@ -142,7 +165,7 @@ impl Flow<Ctnt, Stmt, Prf> for GitHubVerificationFlow {
             
         }
 
-        Err(FlowError::BadLookup(
+        Err(FlowError::NotFound(
             // "Failed to find properly formatted gist".to_string(),
             format!("Failed to find files in: {}", s),
         ))
@@ -153,15 +176,17 @@ mod tests {
     use super::*;
     use crate::{
         test_util::util::{
-            test_ed25519_did, test_did_keypair, 
+            test_ed25519_did, test_did_keypair, test_resolver_opts,
             test_eth_did, test_solana_did, test_witness_signature, MockFlow,
-            MockIssuer, TestKey, TestWitness, test_witness_statement,
+            MockIssuer, TestKey, TestWitness, test_witness_statement, StubTransport,
         },
         types::{
             enums::subject::Subjects,
-            defs::{Issuer, Proof, Statement, Subject},
+            defs::{Issuer, Proof, Statement},
         },
     };
+    use serde_json::json;
+    use std::sync::Arc;
 
     fn mock_proof(key: fn() -> Subjects) -> Prf {
         Prf {
@@ -193,7 +218,8 @@ mod tests {
         ) -> Result<StatementResponse, FlowError> {
             Ok(StatementResponse {
                 statement: statement.generate_statement()?,
-                delimiter: Some("\n\n".to_string())
+                delimiter: Some("\n\n".to_string()),
+                typed_data: None,
             })
         }
 
@@ -207,7 +233,16 @@ mod tests {
                 return Err(FlowError::BadLookup("Mismatched statements".to_string()))
             }
 
-            proof.statement.subject.valid_signature(&self.statement, &self.signature).await?;
+            proof
+                .statement
+                .subject
+                .valid_signature_with_opts(
+                    &self.statement,
+                    &self.signature,
+                    &Some(test_resolver_opts()),
+                    &None,
+                )
+                .await?;
 
             Ok(proof
                 .to_content(&self.statement, &self.signature)
@@ -315,4 +350,56 @@ mod tests {
             panic!("Approved bad signature");
         };
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn github_stub_transport() {
+        let i = MockIssuer {};
+        let (subj1, iss1) = test_did_keypair().await.unwrap();
+        let (_, iss2) = test_did_keypair().await.unwrap();
+        let gist_id = "aaaabbbbccccddddeeeeffff00001111";
+
+        let proof = Prf {
+            statement: Stmt {
+                subject: subj1,
+                handle: "foo".to_owned(),
+            },
+            gist_id: gist_id.to_owned(),
+        };
+
+        let statement = proof.generate_statement().unwrap();
+        let stub_flow = |signature: String| GitHubVerificationFlow {
+            user_agent: "rebase-test".to_string(),
+            delimiter: "\n\n".to_string(),
+            http_opts: Some(HttpOpts {
+                base_url: Some("http://stub.test".to_string()),
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![(
+                        format!("http://stub.test/gists/{}", gist_id),
+                        json!({
+                            "files": {
+                                "rebase.txt": {
+                                    "content": format!("{}\n\n{}", statement, signature)
+                                }
+                            },
+                            "owner": { "login": "Foo" },
+                            "history": []
+                        })
+                        .to_string(),
+                    )],
+                })),
+            }),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+
+        let flow = stub_flow(iss1.sign(&statement).await.unwrap());
+        flow.validate_proof(proof.clone(), i.clone()).await.unwrap();
+
+        // Make sure it fails correctly:
+        let flow = stub_flow(iss2.sign(&statement).await.unwrap());
+        if flow.validate_proof(proof, i).await.is_ok() {
+            panic!("Approved bad signature");
+        };
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{
+    content::mastodon_verification::MastodonVerificationContent as Ctnt,
+    proof::mastodon_verification::MastodonVerificationProof as Prf,
+    statement::mastodon_verification::MastodonVerificationStatement as Stmt,
+    types::{
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
+        error::FlowError,
+    },
+};
+
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, ACCEPT},
+    Client,
+};
+use schemars::schema_for;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use url::Url;
+use wasm_bindgen::prelude::*;
+
+const ACTIVITY_JSON: &str = "application/activity+json";
+
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MastodonVerificationFlow {
+    pub delimiter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WebFingerLink {
+    pub rel: String,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub href: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WebFingerResponse {
+    pub subject: String,
+    #[serde(default)]
+    pub links: Vec<WebFingerLink>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ActorAttachment {
+    pub value: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ActivityPubActor {
+    pub id: String,
+    #[serde(rename = "preferredUsername")]
+    pub preferred_username: String,
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub attachment: Vec<ActorAttachment>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ActivityPubNote {
+    #[serde(rename = "attributedTo")]
+    pub attributed_to: String,
+    pub content: String,
+}
+
+impl MastodonVerificationFlow {
+    // get_json fetches an ActivityPub or WebFinger document. The URL must be https, and has its
+    // origin replaced by the configured base URL, if any.
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, u: &Url) -> Result<T, FlowError> {
+        if u.scheme() != "https" {
+            return Err(FlowError::Validation(format!("{} is not an https URL", u)));
+        }
+
+        let origin = u.origin().ascii_serialization();
+        let path = match u.query() {
+            Some(q) => format!("{}?{}", u.path(), q),
+            None => u.path().to_string(),
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, ACTIVITY_JSON.parse().unwrap());
+        let req = Client::new()
+            .get(make_url(&self.http_opts, &origin, &path)?)
+            .headers(headers);
+
+        send_request(&self.http_opts, req)
+            .await?
+            .json()
+            .await
+            .map_err(|e| FlowError::BadLookup(e.to_string()))
+    }
+
+    // actor finds the ActivityPub actor of the statement's account using WebFinger.
+    async fn actor(&self, statement: &Stmt) -> Result<ActivityPubActor, FlowError> {
+        let handle = Regex::new(r"^[a-zA-Z0-9_.-]+$")
+            .map_err(|_| FlowError::BadLookup("could not generate handle regex".to_string()))?;
+        if !handle.is_match(&statement.handle) {
+            return Err(FlowError::Validation("handle invalid".to_string()));
+        }
+
+        let mut webfinger = Url::parse(&format!(
+            "https://{}/.well-known/webfinger",
+            statement.domain
+        ))
+        .map_err(|_| FlowError::Validation("domain invalid".to_string()))?;
+        if webfinger.host_str() != Some(statement.domain.as_str()) || webfinger.port().is_some() {
+            return Err(FlowError::Validation("domain invalid".to_string()));
+        }
+        webfinger
+            .query_pairs_mut()
+            .append_pair("resource", &format!("acct:{}", statement.acct()));
+
+        let res: WebFingerResponse = self.get_json(&webfinger).await?;
+        if !res
+            .subject
+            .eq_ignore_ascii_case(&format!("acct:{}", statement.acct()))
+        {
+            return Err(FlowError::Validation(format!(
+                "acct mismatch, expected: {}, got: {}",
+                statement.acct(),
+                res.subject.trim_start_matches("acct:")
+            )));
+        }
+
+        let href = res
+            .links
+            .iter()
+            .find(|l| l.rel == "self" && l.type_.as_deref() == Some(ACTIVITY_JSON))
+            .and_then(|l| l.href.clone())
+            .ok_or_else(|| {
+                FlowError::NotFound(format!("no ActivityPub actor for {}", statement.acct()))
+            })?;
+        let actor_url = Url::parse(&href).map_err(|e| FlowError::BadLookup(e.to_string()))?;
+
+        let actor: ActivityPubActor = self.get_json(&actor_url).await?;
+        if !actor
+            .preferred_username
+            .eq_ignore_ascii_case(&statement.handle)
+        {
+            return Err(FlowError::Validation(format!(
+                "handle mismatch, expected: {}, got: {}",
+                statement.handle, actor.preferred_username
+            )));
+        }
+
+        // The actor document is only authoritative for actors on its own host.
+        let id = Url::parse(&actor.id).map_err(|e| FlowError::BadLookup(e.to_string()))?;
+        if id.host_str() != actor_url.host_str() {
+            return Err(FlowError::Validation(format!(
+                "actor {} was not served by its own host",
+                actor.id
+            )));
+        }
+
+        Ok(actor)
+    }
+}
+
+// html_to_text recovers the text of a post or bio from the HTML instances render it as.
+fn html_to_text(html: &str) -> String {
+    let html = html
+        .replace("</p><p>", "\n\n")
+        .replace("<br />", "\n")
+        .replace("<br/>", "\n")
+        .replace("<br>", "\n");
+
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Flow<Ctnt, Stmt, Prf> for MastodonVerificationFlow {
+    fn instructions(&self) -> Result<Instructions, FlowError> {
+        Ok(Instructions {
+            statement: "Enter your Mastodon username and the domain of its instance to verify and include in a signed message using your wallet.".to_string(),
+            statement_schema: schema_for!(Stmt),
+            signature: "Sign the message presented to you containing your Mastodon account and additional information.".to_string(),
+            witness: "Post the statement and signature as a public status, or add them to your bio, to create a link between your identifier and Mastodon account.".to_string(),
+            witness_schema: schema_for!(Prf),
+        })
+    }
+
+    async fn statement<I: Issuer + Send + Clone>(
+        &self,
+        statement: Stmt,
+        _issuer: I,
+    ) -> Result<StatementResponse, FlowError> {
+        Ok(StatementResponse {
+            delimiter: Some(self.delimiter.to_owned()),
+            typed_data: None,
+            statement: statement.generate_statement()?,
+        })
+    }
+
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
+    async fn validate_proof<I: Issuer + Send>(
+        &self,
+        proof: Prf,
+        _issuer: I,
+    ) -> Result<Ctnt, FlowError> {
+        let actor = self.actor(&proof.statement).await?;
+
+        let posts = match &proof.status_url {
+            Some(status_url) => {
+                let u = Url::parse(status_url)
+                    .map_err(|_| FlowError::Validation("status url invalid".to_string()))?;
+                // Otherwise any server could claim a status was posted by the account.
+                let id = Url::parse(&actor.id).map_err(|e| FlowError::BadLookup(e.to_string()))?;
+                if u.host_str() != id.host_str() {
+                    return Err(FlowError::Validation(format!(
+                        "status must be on the account's instance {}",
+                        id.host_str().unwrap_or_default()
+                    )));
+                }
+
+                let note: ActivityPubNote = self.get_json(&u).await?;
+                if note.attributed_to != actor.id {
+                    return Err(FlowError::Validation(format!(
+                        "status was posted by {} not {}",
+                        note.attributed_to, actor.id
+                    )));
+                }
+                vec![note.content]
+            }
+            None => actor
+                .summary
+                .into_iter()
+                .chain(actor.attachment.into_iter().filter_map(|a| a.value))
+                .collect(),
+        };
+
+        let expected = proof.statement.generate_statement()?;
+        for post in posts {
+            let text = html_to_text(&post);
+            let mut a = text.split(&self.delimiter);
+            match (a.next(), a.next()) {
+                (Some(stmt), Some(sig)) => {
+                    if stmt.trim() != expected {
+                        continue;
+                    }
+                    let sig = sig.trim();
+                    proof
+                        .statement
+                        .subject
+                        .valid_signature_with_opts(
+                            &expected,
+                            sig,
+                            &self.resolver_opts,
+                            &self.eth_rpc,
+                        )
+                        .await?;
+                    return Ok(proof.to_content(&expected, sig)?);
+                }
+                _ => continue,
+            }
+        }
+
+        Err(FlowError::NotFound(format!(
+            "Failed to find the statement and signature posted by {}",
+            proof.statement.acct()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::util::{test_did_keypair, MockIssuer, StubTransport},
+        types::defs::Issuer,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const ACTOR: &str = "https://mastodon.example/users/alice";
+    const STATUS: &str = "https://mastodon.example/@alice/1";
+
+    fn stub_flow(post: String, bio: bool) -> MastodonVerificationFlow {
+        let actor = json!({
+            "id": ACTOR,
+            "preferredUsername": "alice",
+            "summary": if bio { post.clone() } else { "<p>hello</p>".to_string() },
+            "attachment": []
+        });
+
+        MastodonVerificationFlow {
+            delimiter: "\n\n".to_string(),
+            http_opts: Some(HttpOpts {
+                base_url: Some("http://stub.test".to_string()),
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![
+                        (
+                            "http://stub.test/.well-known/webfinger".to_string(),
+                            json!({
+                                "subject": "acct:alice@mastodon.example",
+                                "links": [{
+                                    "rel": "self",
+                                    "type": ACTIVITY_JSON,
+                                    "href": ACTOR
+                                }]
+                            })
+                            .to_string(),
+                        ),
+                        (
+                            "http://stub.test/@alice/1".to_string(),
+                            json!({"attributedTo": ACTOR, "content": post}).to_string(),
+                        ),
+                        (
+                            "http://stub.test/users/alice".to_string(),
+                            actor.to_string(),
+                        ),
+                    ],
+                })),
+            }),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }
+    }
+
+    // post renders the statement and signature the way Mastodon does.
+    fn post(statement: &str, signature: &str) -> String {
+        format!("<p>{}</p><p>{}</p>", statement, signature)
+    }
+
+    #[tokio::test]
+    async fn mastodon_stub_transport() {
+        let i = MockIssuer {};
+        let (subj1, iss1) = test_did_keypair().await.unwrap();
+        let (_, iss2) = test_did_keypair().await.unwrap();
+
+        let proof = Prf {
+            statement: Stmt {
+                subject: subj1,
+                handle: "alice".to_owned(),
+                domain: "mastodon.example".to_owned(),
+            },
+            status_url: Some(STATUS.to_owned()),
+        };
+        let statement = proof.generate_statement().unwrap();
+        let signature = iss1.sign(&statement).await.unwrap();
+
+        let flow = stub_flow(post(&statement, &signature), false);
+        flow.credential(proof.clone(), iss1.clone()).await.unwrap();
+
+        let bio = Prf {
+            status_url: None,
+            ..proof.clone()
+        };
+        let flow = stub_flow(post(&statement, &signature), true);
+        flow.validate_proof(bio, i.clone()).await.unwrap();
+
+        // Make sure it fails correctly:
+        let flow = stub_flow(
+            post(&statement, &iss2.sign(&statement).await.unwrap()),
+            false,
+        );
+        if flow.validate_proof(proof.clone(), i.clone()).await.is_ok() {
+            panic!("Approved bad signature");
+        };
+
+        let elsewhere = Prf {
+            status_url: Some("https://other.example/@alice/1".to_owned()),
+            ..proof
+        };
+        let flow = stub_flow(post(&statement, &signature), false);
+        if flow.validate_proof(elsewhere, i).await.is_ok() {
+            panic!("Approved status from another instance");
+        };
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(
+            html_to_text("<p>a &amp; <a href=\"https://x\"><span class=\"invisible\">https://</span>x</a></p><p>b<br />c</p>"),
+            "a & https://x\n\nb\nc"
+        );
+    }
+}
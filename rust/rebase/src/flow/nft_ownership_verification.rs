@@ -3,9 +3,13 @@ use crate::{
     proof::nft_ownership_verification::NftOwnershipVerificationProof as Prf,
     statement::nft_ownership_verification::NftOwnershipVerificationStatement as Stmt,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
+        defs::{
+            make_url, send_request, AlchemyNetworks, EthRpcOpts, Flow, HttpOpts, Instructions,
+            Issuer, Proof, Statement, StatementResponse, Subject, CLOCK_SKEW_SECONDS,
+        },
         enums::subject::{Pkh, Subjects},
         error::FlowError,
+        nonce::{check_rate_limit, consume_challenge, nonce_store, NonceStore, RateLimit},
     },
 };
 
@@ -14,6 +18,7 @@ use chrono::{DateTime, Duration, Utc};
 use reqwest::Client;
 use schemars::schema_for;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tsify::Tsify;
 use url::Url;
 use wasm_bindgen::prelude::*;
@@ -46,6 +51,12 @@ impl Flow<Ctnt, Stmt, Prf> for NftOwnershipVerificationFlow {
         }
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        match self {
+            NftOwnershipVerificationFlow::Alchemy(x) => x.validity_minutes,
+        }
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Prf,
@@ -68,6 +79,35 @@ pub struct Alchemy {
     // This is checked for a negative value or 0 and errs if one is found
     // Alternative is casting u64 to i64 and risking UB.
     pub max_elapsed_minutes: i64,
+    // NOTE: A configured base_url replaces the per-network Alchemy host. Any {network} in it is
+    // replaced with the statement's network, e.g. https://{network}.proxy.example.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // Limits how often a subject may ask the witness to check their ownership.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
+    // Remembers used challenges, defaults to an in-process store.
+    // Not serializable, so must be set in code after the flow is deserialized.
+    #[serde(skip)]
+    pub nonce_store: Option<Arc<dyn NonceStore>>,
+}
+
+impl Alchemy {
+    // network_http_opts fills the network into a configured base_url.
+    fn network_http_opts(&self, network: &AlchemyNetworks) -> Option<HttpOpts> {
+        self.http_opts.clone().map(|o| HttpOpts {
+            base_url: o
+                .base_url
+                .map(|u| u.replace("{network}", &network.to_string())),
+            ..o
+        })
+    }
 }
 
 pub struct AlchemyPageResult {
@@ -83,7 +123,7 @@ impl Alchemy {
     // minutes are greater than 0.
     pub fn sanity_check(&self, timestamp: &str) -> Result<(), FlowError> {
         if self.max_elapsed_minutes <= 0 {
-            return Err(FlowError::Validation(
+            return Err(FlowError::Misconfigured(
                 "Max elapsed minutes must be set to a number greater than 0".to_string(),
             ));
         }
@@ -92,14 +132,14 @@ impl Alchemy {
         let then = DateTime::parse_from_rfc3339(timestamp)
             .map_err(|e| FlowError::Validation(e.to_string()))?;
 
-        if then > now {
-            return Err(FlowError::Validation(
+        if then > now + Duration::seconds(CLOCK_SKEW_SECONDS) {
+            return Err(FlowError::NotYetValid(
                 "Timestamp provided comes from the future".to_string(),
             ));
         }
 
         if now - Duration::minutes(self.max_elapsed_minutes) > then {
-            return Err(FlowError::Validation(
+            return Err(FlowError::Expired(
                 "Validation window has expired".to_string(),
             ));
         };
@@ -120,11 +160,8 @@ impl Alchemy {
             })?,
         };
 
-        let res: AlchemyNftRes = client
-            .get(u)
-            .send()
-            .await
-            .map_err(|e| FlowError::BadLookup(e.to_string()))?
+        let res: AlchemyNftRes = send_request(&self.http_opts, client.get(u))
+            .await?
             .json()
             .await
             .map_err(|e| FlowError::BadLookup(e.to_string()))?;
@@ -171,7 +208,7 @@ impl Flow<Ctnt, Stmt, Prf> for Alchemy {
         match stmt.subject {
             Subjects::Pkh(Pkh::Eip155(_)) => {}
             _ => {
-                return Err(FlowError::Validation(
+                return Err(FlowError::UnsupportedSubject(
                     "Currently only supports Ethereum NFTs".to_string(),
                 ))
             }
@@ -189,22 +226,57 @@ impl Flow<Ctnt, Stmt, Prf> for Alchemy {
         Ok(StatementResponse {
             statement: format!("{}{}{}", s, self.challenge_delimiter, sig),
             delimiter: None,
+            typed_data: None,
         })
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Prf,
         issuer: I,
     ) -> Result<Ctnt, FlowError> {
         self.sanity_check(&proof.statement.issued_at)?;
+        let s = proof.statement.generate_statement()?;
+        let f = issuer.sign(&s);
+        let sig = f.await?;
+        proof
+            .statement
+            .subject
+            .valid_signature_with_opts(
+                // Because the timestamp is within the expected bounds, the witness
+                // then can recreate the statement by recreating the challenge.
+                // This is not vulnerable to replay attacks after the
+                // max_elapsed_minutes has elapsed.
+                &format!("{}{}{}", s, &self.challenge_delimiter, sig),
+                &proof.signature,
+                &None,
+                &self.eth_rpc,
+            )
+            .await?;
 
-        let base = format!(
-            "https://{}.g.alchemy.com/nft/v2/{}/getNFTs?owner={}&withMetadata=false",
-            proof.statement.network.to_string(),
-            self.api_key,
-            proof.statement.subject.display_id()?
-        );
+        // Only subjects that signed the challenge count against the rate limit.
+        check_rate_limit(
+            nonce_store(&self.nonce_store),
+            &self.rate_limit,
+            "nft_ownership",
+            &proof.statement.subject.did()?,
+        )
+        .await?;
+
+        let base = make_url(
+            &self.network_http_opts(&proof.statement.network),
+            &format!("https://{}.g.alchemy.com", proof.statement.network),
+            &format!(
+                "/nft/v2/{}/getNFTs?owner={}&withMetadata=false",
+                self.api_key,
+                proof.statement.subject.display_id()?
+            ),
+        )?
+        .to_string();
 
         let client = Client::new();
 
@@ -228,28 +300,18 @@ impl Flow<Ctnt, Stmt, Prf> for Alchemy {
         }
 
         if !res.found {
-            return Err(FlowError::BadLookup(format!(
+            return Err(FlowError::NotFound(format!(
                 "Found no owned NFTs from contract {}",
                 proof.statement.contract_address
             )));
         }
 
-        let s = proof.statement.generate_statement()?;
-
-        let f = issuer.sign(&s);
-        let sig = f.await?;
-        proof
-            .statement
-            .subject
-            .valid_signature(
-                // Because the timestamp is within the expected bounds, the witness
-                // then can recreate the statement by recreating the challenge.
-                // This is not vulnerable to replay attacks after the
-                // max_elapsed_minutes has elapsed.
-                &format!("{}{}{}", s, &self.challenge_delimiter, sig),
-                &proof.signature,
-            )
-            .await?;
+        // Within the window, each challenge may only be used once.
+        let expires = DateTime::parse_from_rfc3339(&proof.statement.issued_at)
+            .map_err(|e| FlowError::Validation(e.to_string()))?
+            .with_timezone(&Utc)
+            + Duration::minutes(self.max_elapsed_minutes);
+        consume_challenge(nonce_store(&self.nonce_store), "nft_ownership", &s, expires).await?;
 
         Ok(proof.to_content(&s, &proof.signature)?)
     }
@@ -330,6 +392,7 @@ mod tests {
             Ok(StatementResponse {
                 statement: statement.generate_statement()?,
                 delimiter: Some("\n\n".to_string()),
+                typed_data: None,
             })
         }
 
@@ -367,4 +430,32 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[test]
+    fn base_url_network() {
+        let flow = Alchemy {
+            api_key: "key".to_string(),
+            challenge_delimiter: ":".to_string(),
+            max_elapsed_minutes: 15,
+            http_opts: Some(HttpOpts {
+                base_url: Some("http://{network}.stub.test".to_string()),
+                transport: None,
+            }),
+            eth_rpc: None,
+            rate_limit: None,
+            validity_minutes: None,
+            nonce_store: None,
+        };
+
+        let u = make_url(
+            &flow.network_http_opts(&AlchemyNetworks::PolygonMainnet),
+            "https://polygon-mainnet.g.alchemy.com",
+            "/nft/v2/key/getNFTs",
+        )
+        .unwrap();
+        assert_eq!(
+            u.as_str(),
+            "http://polygon-mainnet.stub.test/nft/v2/key/getNFTs"
+        );
+    }
 }
@@ -3,9 +3,13 @@ use crate::{
     proof::poap_ownership_verification::PoapOwnershipVerificationProof as Prf,
     statement::poap_ownership_verification::PoapOwnershipVerificationStatement as Stmt,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            Statement, StatementResponse, Subject, CLOCK_SKEW_SECONDS,
+        },
         enums::subject::{Pkh, Subjects},
         error::FlowError,
+        nonce::{check_rate_limit, consume_challenge, nonce_store, NonceStore, RateLimit},
     },
 };
 
@@ -17,8 +21,8 @@ use reqwest::{
 };
 use schemars::schema_for;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tsify::Tsify;
-use url::Url;
 use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
@@ -31,6 +35,21 @@ pub struct PoapOwnershipVerificationFlow {
     // This is checked for a negative value or 0 and errs if one is found
     // Alternative is casting u64 to i64 and risking UB.
     pub max_elapsed_minutes: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // Limits how often a subject may ask the witness to check their ownership.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
+    // Remembers used challenges, defaults to an in-process store.
+    // Not serializable, so must be set in code after the flow is deserialized.
+    #[serde(skip)]
+    pub nonce_store: Option<Arc<dyn NonceStore>>,
 }
 
 impl PoapOwnershipVerificationFlow {
@@ -39,7 +58,7 @@ impl PoapOwnershipVerificationFlow {
     // minutes are greater than 0.
     pub fn sanity_check(&self, timestamp: &str) -> Result<(), FlowError> {
         if self.max_elapsed_minutes <= 0 {
-            return Err(FlowError::Validation(
+            return Err(FlowError::Misconfigured(
                 "Max elapsed minutes must be set to a number greater than 0".to_string(),
             ));
         }
@@ -48,14 +67,14 @@ impl PoapOwnershipVerificationFlow {
         let then = DateTime::parse_from_rfc3339(timestamp)
             .map_err(|e| FlowError::Validation(e.to_string()))?;
 
-        if then > now {
-            return Err(FlowError::Validation(
+        if then > now + Duration::seconds(CLOCK_SKEW_SECONDS) {
+            return Err(FlowError::NotYetValid(
                 "Timestamp provided comes from the future".to_string(),
             ));
         }
 
         if now - Duration::minutes(self.max_elapsed_minutes) > then {
-            return Err(FlowError::Validation(
+            return Err(FlowError::Expired(
                 "Validation window has expired".to_string(),
             ));
         };
@@ -119,7 +138,7 @@ impl Flow<Ctnt, Stmt, Prf> for PoapOwnershipVerificationFlow {
         // Can POAPs be attached to non EIP155 DIDs?
         if let Subjects::Pkh(Pkh::Eip155(_)) = stmt.subject {
         } else {
-            return Err(FlowError::Validation(
+            return Err(FlowError::UnsupportedSubject(
                 "Currently only supports Ethereum Addresses for POAP Ownership flow".to_string(),
             ));
         }
@@ -136,36 +155,67 @@ impl Flow<Ctnt, Stmt, Prf> for PoapOwnershipVerificationFlow {
         Ok(StatementResponse {
             statement: format!("{}{}{}", s, self.challenge_delimiter, sig),
             delimiter: None,
+            typed_data: None,
         })
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Prf,
         issuer: I,
     ) -> Result<Ctnt, FlowError> {
         self.sanity_check(&proof.statement.issued_at)?;
+        let s = proof.statement.generate_statement()?;
+        let f = issuer.sign(&s);
+        let sig = f.await?;
+        proof
+            .statement
+            .subject
+            .valid_signature_with_opts(
+                // Because the timestamp is within the expected bounds, the witness
+                // then can recreate the statement by recreating the challenge.
+                // This is not vulnerable to replay attacks after the
+                // max_elapsed_minutes has elapsed.
+                &format!("{}{}{}", s, &self.challenge_delimiter, sig),
+                &proof.signature,
+                &None,
+                &self.eth_rpc,
+            )
+            .await?;
 
-        let u = Url::parse(&format!(
-            "https://api.poap.tech/actions/scan/{}",
-            proof.statement.subject.display_id()?
-        ))
-        .map_err(|e| FlowError::BadLookup(format!("Failed in API request: {}", e)))?;
+        // Only subjects that signed the challenge count against the rate limit.
+        check_rate_limit(
+            nonce_store(&self.nonce_store),
+            &self.rate_limit,
+            "poap_ownership",
+            &proof.statement.subject.did()?,
+        )
+        .await?;
+
+        let u = make_url(
+            &self.http_opts,
+            "https://api.poap.tech",
+            &format!("/actions/scan/{}", proof.statement.subject.display_id()?),
+        )?;
 
         let mut headers = HeaderMap::new();
         let hv: HeaderValue = self
             .api_key
             .parse()
-            .map_err(|_e| FlowError::BadLookup("Could not parse Header value".to_string()))?;
+            .map_err(|_e| FlowError::Misconfigured("Could not parse Header value".to_string()))?;
         let hn: HeaderName = "X-API-KEY"
             .to_string()
             .parse()
-            .map_err(|_e| FlowError::BadLookup("Could not parse Header name".to_string()))?;
+            .map_err(|_e| FlowError::Misconfigured("Could not parse Header name".to_string()))?;
         headers.insert(hn, hv);
 
         let client = Client::new();
-        let f = client.get(u).headers(headers).send();
-        let h = f.await.map_err(|e| FlowError::BadLookup(e.to_string()))?;
+        let f = send_request(&self.http_opts, client.get(u).headers(headers));
+        let h = f.await?;
         let f = h.json();
         let res: Vec<PoapResEntry> = f.await.map_err(|e| FlowError::BadLookup(e.to_string()))?;
 
@@ -178,28 +228,24 @@ impl Flow<Ctnt, Stmt, Prf> for PoapOwnershipVerificationFlow {
         }
 
         if !found {
-            return Err(FlowError::BadLookup(format!(
+            return Err(FlowError::NotFound(format!(
                 "Found no event with id {} in user's POAPs.",
                 proof.statement.event_id
             )));
         }
 
-        let s = proof.statement.generate_statement()?;
-        let f = issuer.sign(&s);
-        let sig = f.await?;
-
-        proof
-            .statement
-            .subject
-            .valid_signature(
-                // Because the timestamp is within the expected bounds, the witness
-                // then can recreate the statement by recreating the challenge.
-                // This is not vulnerable to replay attacks after the
-                // max_elapsed_minutes has elapsed.
-                &format!("{}{}{}", s, &self.challenge_delimiter, sig),
-                &proof.signature,
-            )
-            .await?;
+        // Within the window, each challenge may only be used once.
+        let expires = DateTime::parse_from_rfc3339(&proof.statement.issued_at)
+            .map_err(|e| FlowError::Validation(e.to_string()))?
+            .with_timezone(&Utc)
+            + Duration::minutes(self.max_elapsed_minutes);
+        consume_challenge(
+            nonce_store(&self.nonce_store),
+            "poap_ownership",
+            &s,
+            expires,
+        )
+        .await?;
 
         Ok(proof.to_content(&s, &proof.signature)?)
     }
@@ -209,13 +255,15 @@ impl Flow<Ctnt, Stmt, Prf> for PoapOwnershipVerificationFlow {
 mod tests {
     use super::*;
     use crate::{
+        issuer::secp256k1::Secp256k1Jwk,
         test_util::util::{
-            test_eth_did, test_witness_signature, test_witness_statement, MockFlow, MockIssuer,
-            TestKey, TestWitness,
+            test_did_keypair, test_eth_did, test_secp256k1_keypair, test_witness_signature,
+            test_witness_statement, MockFlow, MockIssuer, StubTransport, TestKey, TestWitness,
         },
         types::{
             defs::{Issuer, Proof, Statement, Subject},
             enums::subject::Subjects,
+            nonce::MemoryNonceStore,
         },
     };
 
@@ -251,6 +299,7 @@ mod tests {
             Ok(StatementResponse {
                 statement: statement.generate_statement()?,
                 delimiter: Some("\n\n".to_string()),
+                typed_data: None,
             })
         }
 
@@ -288,4 +337,88 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_rate_limit_after_signature() {
+        let (subject, subject_issuer) = test_secp256k1_keypair().unwrap();
+        let (_, other_issuer) = test_secp256k1_keypair().unwrap();
+        let event = serde_json::json!([{
+            "event": {
+                "id": 102213,
+                "fancy_id": "",
+                "name": "",
+                "event_url": "",
+                "image_url": "",
+                "country": "",
+                "city": "",
+                "description": "",
+                "year": 2023,
+                "start_date": "",
+                "end_date": "",
+                "expiry_date": "",
+                "supply": 1,
+            },
+            "tokenId": "1",
+            "owner": "",
+            "chain": "",
+            "created": "",
+        }]);
+        let flow = PoapOwnershipVerificationFlow {
+            api_key: "key".to_string(),
+            challenge_delimiter: "\n\n".to_string(),
+            max_elapsed_minutes: 10,
+            http_opts: Some(HttpOpts {
+                base_url: None,
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![("https://api.poap.tech/".to_string(), event.to_string())],
+                })),
+            }),
+            eth_rpc: None,
+            rate_limit: Some(RateLimit {
+                max_requests: 1,
+                window_minutes: 10,
+            }),
+            validity_minutes: None,
+            nonce_store: Some(Arc::new(MemoryNonceStore::new())),
+        };
+
+        let (_, witness) = test_did_keypair().await.unwrap();
+        let proof = |signer: Secp256k1Jwk, issued_at: String| {
+            let statement = Stmt {
+                subject: subject.clone(),
+                event_id: 102213,
+                issued_at,
+            };
+            let witness = witness.clone();
+            async move {
+                let s = statement.generate_statement().unwrap();
+                let challenge = witness.sign(&s).await.unwrap();
+                Prf {
+                    signature: signer
+                        .sign(&format!("{}\n\n{}", s, challenge))
+                        .await
+                        .unwrap(),
+                    statement,
+                }
+            }
+        };
+        let now = Utc::now();
+
+        // Proofs with bad signatures don't count against the rate limit.
+        for _ in 0..2 {
+            let p = proof(other_issuer.clone(), now.to_rfc3339()).await;
+            if flow.validate_proof(p, witness.clone()).await.is_ok() {
+                panic!("Invalid signature permitted");
+            }
+        }
+
+        let p = proof(subject_issuer.clone(), now.to_rfc3339()).await;
+        flow.validate_proof(p, witness.clone()).await.unwrap();
+
+        let earlier = (now - Duration::seconds(1)).to_rfc3339();
+        let p = proof(subject_issuer, earlier).await;
+        if flow.validate_proof(p, witness.clone()).await.is_ok() {
+            panic!("Rate limit not enforced");
+        }
+    }
 }
@@ -2,7 +2,10 @@ use crate::{
     content::reddit_verification::RedditVerificationContent as Ctnt,
     statement::reddit_verification::RedditVerificationStatement as Stmt,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
         error::FlowError,
     },
 };
@@ -14,7 +17,6 @@ use reqwest::{
 use schemars::schema_for;
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
-use url::Url;
 use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -36,6 +38,17 @@ pub struct AboutSubreddit {
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct RedditVerificationFlow {
     pub user_agent: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -59,42 +72,45 @@ impl Flow<Ctnt, Stmt, Stmt> for RedditVerificationFlow {
         Ok(StatementResponse {
             statement: statement.generate_statement()?,
             delimiter: None,
+            typed_data: None,
         })
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Stmt,
         _issuer: I,
     ) -> Result<Ctnt, FlowError> {
-        let u = format!("https:/www.reddit.com/user/{}/about/.json", proof.handle);
+        let u = make_url(
+            &self.http_opts,
+            "https://www.reddit.com",
+            &format!("/user/{}/about/.json", proof.handle),
+        )?;
         let client = Client::new();
         let mut headers = HeaderMap::new();
         headers.insert(
             USER_AGENT,
             self.user_agent.to_string().parse().map_err(|_| {
-                FlowError::BadLookup("could not generate header for lookup".to_string())
+                FlowError::Misconfigured("could not generate header for lookup".to_string())
             })?,
         );
 
-        let res: AboutWrapper = client
-            .get(Url::parse(&u).map_err(|e| {
-                FlowError::Validation(format!(
-                    "Failed to parse reddit about URL: {} -- Reason: {}",
-                    u, e
-                ))
-            })?)
-            .headers(headers)
-            .send()
-            .await
-            .map_err(|e| FlowError::BadLookup(e.to_string()))?
+        let res: AboutWrapper = send_request(&self.http_opts, client.get(u).headers(headers))
+            .await?
             .json()
             .await
             .map_err(|e| FlowError::BadLookup(e.to_string()))?;
 
         let stmt = proof.generate_statement()?;
         let sig = res.data.subreddit.public_description;
-        proof.subject.valid_signature(&stmt, &sig).await?;
+        proof
+            .subject
+            .valid_signature_with_opts(&stmt, &sig, &self.resolver_opts, &self.eth_rpc)
+            .await?;
 
         Ok(proof.to_content(&stmt, &sig)?)
     }
@@ -141,6 +157,7 @@ mod tests {
             Ok(StatementResponse {
                 statement: statement.generate_statement()?,
                 delimiter: None,
+                typed_data: None,
             })
         }
 
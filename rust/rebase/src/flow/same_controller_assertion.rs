@@ -3,7 +3,10 @@ use crate::{
     proof::same_controller_assertion::SameControllerAssertionProof as Prf,
     statement::same_controller_assertion::SameControllerAssertionStatement as Stmt,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
+        defs::{
+            EthRpcOpts, Flow, Instructions, Issuer, Proof, ResolverOpts, Statement,
+            StatementResponse,
+        },
         error::FlowError,
     },
 };
@@ -16,7 +19,14 @@ use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
-pub struct SameControllerAssertionFlow {}
+pub struct SameControllerAssertionFlow {
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+}
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
@@ -39,6 +49,7 @@ impl Flow<Ctnt, Stmt, Prf> for SameControllerAssertionFlow {
         Ok(StatementResponse {
             statement: statement.generate_statement()?,
             delimiter: None,
+            typed_data: None,
         })
     }
 
@@ -51,13 +62,13 @@ impl Flow<Ctnt, Stmt, Prf> for SameControllerAssertionFlow {
         proof
             .statement
             .id1
-            .valid_signature(&s, &proof.signature1)
+            .valid_signature_with_opts(&s, &proof.signature1, &self.resolver_opts, &self.eth_rpc)
             .await?;
 
         proof
             .statement
             .id2
-            .valid_signature(&s, &proof.signature2)
+            .valid_signature_with_opts(&s, &proof.signature2, &self.resolver_opts, &self.eth_rpc)
             .await?;
 
         // NOTE: The passed signature is discarded internally, using both found in proof.
@@ -70,9 +81,10 @@ mod tests {
     use crate::{
         test_util::util::{
             test_did_keypair, test_ed25519_did, test_ed25519_did_2, test_eth_did, test_eth_did_2,
-            test_solana_did, test_solana_did_2, test_witness_signature, MockIssuer, TestKey,
-            TestWitness, TEST_2KEY_ED25519_SIG_1, TEST_2KEY_ED25519_SIG_2, TEST_2KEY_ETH_SIG_1,
-            TEST_2KEY_ETH_SIG_2, TEST_2KEY_SOLANA_SIG_1, TEST_2KEY_SOLANA_SIG_2,
+            test_resolver_opts, test_solana_did, test_solana_did_2, test_witness_signature,
+            MockIssuer, TestKey, TestWitness, TEST_2KEY_ED25519_SIG_1, TEST_2KEY_ED25519_SIG_2,
+            TEST_2KEY_ETH_SIG_1, TEST_2KEY_ETH_SIG_2, TEST_2KEY_SOLANA_SIG_1,
+            TEST_2KEY_SOLANA_SIG_2,
         },
         types::enums::subject::Subjects,
     };
@@ -106,7 +118,10 @@ mod tests {
         .await
         .unwrap();
 
-        let flow = SameControllerAssertionFlow {};
+        let flow = SameControllerAssertionFlow {
+            resolver_opts: None,
+            eth_rpc: None,
+        };
 
         flow.unsigned_credential(p, test_eth_did(), issuer.clone())
             .await
@@ -205,7 +220,10 @@ mod tests {
     #[tokio::test]
     async fn test_ed25519_claim() {
         let issuer = MockIssuer {};
-        let flow = SameControllerAssertionFlow {};
+        let flow = SameControllerAssertionFlow {
+            resolver_opts: Some(test_resolver_opts()),
+            eth_rpc: None,
+        };
 
         // The valid case.
         let p = mock_proof(
@@ -314,7 +332,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_solana_claim() {
-        let flow = SameControllerAssertionFlow {};
+        let flow = SameControllerAssertionFlow {
+            resolver_opts: None,
+            eth_rpc: None,
+        };
         let issuer = MockIssuer {};
 
         // The valid case.
@@ -422,7 +443,10 @@ mod tests {
     #[tokio::test]
     async fn mock_same_controller_on_the_fly() {
         let i = MockIssuer {};
-        let flow = SameControllerAssertionFlow {};
+        let flow = SameControllerAssertionFlow {
+            resolver_opts: None,
+            eth_rpc: None,
+        };
         let (subj1, iss1) = test_did_keypair().await.unwrap();
         let (subj2, iss2) = test_did_keypair().await.unwrap();
         let (_, iss3) = test_did_keypair().await.unwrap();
@@ -2,7 +2,10 @@ use crate::{
     content::soundcloud_verification::SoundCloudVerificationContent as Ctnt,
     statement::soundcloud_verification::SoundCloudVerificationStatement as Stmt,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
         error::FlowError,
     },
 };
@@ -23,20 +26,31 @@ pub struct SoundCloudVerificationFlow {
     pub limit: u64,
     // Must be less that 10000 If less than limit, will only make one request.
     pub max_offset: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
 }
 
 impl SoundCloudVerificationFlow {
     fn is_valid(&self) -> Result<(), FlowError> {
         if self.limit > 200 {
-            Err(FlowError::Validation(
+            Err(FlowError::Misconfigured(
                 "limit must be less than or equal to 200".to_string(),
             ))
         } else if self.limit == 0 {
-            Err(FlowError::Validation(
+            Err(FlowError::Misconfigured(
                 "limit must be greater than 0".to_string(),
             ))
         } else if (self.max_offset + self.limit) > 10000 {
-            Err(FlowError::Validation(
+            Err(FlowError::Misconfigured(
                 "the sum of max_offset and limit must be less than 10000".to_string(),
             ))
         } else {
@@ -45,13 +59,14 @@ impl SoundCloudVerificationFlow {
     }
 
     fn generate_url(&self, proof: &Stmt, offset: &u64) -> Result<Url, FlowError> {
-        Url::parse(&format!(
-            "https://api-v2.soundcloud.com/search/users?q={}&client_id={}&limit={}&offset={}&app_locale=en",
-            proof.permalink,
-            self.client_id,
-            self.limit,
-            offset
-        )).map_err(|e| FlowError::BadLookup(format!("could not parse generated url, reason: {}", e)))
+        make_url(
+            &self.http_opts,
+            "https://api-v2.soundcloud.com",
+            &format!(
+                "/search/users?q={}&client_id={}&limit={}&offset={}&app_locale=en",
+                proof.permalink, self.client_id, self.limit, offset
+            ),
+        )
     }
 }
 
@@ -87,9 +102,14 @@ impl Flow<Ctnt, Stmt, Stmt> for SoundCloudVerificationFlow {
         Ok(StatementResponse {
             statement: statement.generate_statement()?,
             delimiter: None,
+            typed_data: None,
         })
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Stmt,
@@ -101,11 +121,8 @@ impl Flow<Ctnt, Stmt, Stmt> for SoundCloudVerificationFlow {
 
         while offset <= self.max_offset {
             let u = self.generate_url(&proof, &offset)?;
-            let res: SoundCloudRes = client
-                .get(u)
-                .send()
-                .await
-                .map_err(|e| FlowError::BadLookup(e.to_string()))?
+            let res: SoundCloudRes = send_request(&self.http_opts, client.get(u))
+                .await?
                 .json()
                 .await
                 .map_err(|e| FlowError::BadLookup(e.to_string()))?;
@@ -119,7 +136,15 @@ impl Flow<Ctnt, Stmt, Stmt> for SoundCloudVerificationFlow {
                     if permalink.to_lowercase() == proof.permalink.to_lowercase() {
                         if let Some(description) = entry.description {
                             let stmt = proof.generate_statement()?;
-                            proof.subject.valid_signature(&stmt, &description).await?;
+                            proof
+                                .subject
+                                .valid_signature_with_opts(
+                                    &stmt,
+                                    &description,
+                                    &self.resolver_opts,
+                                    &self.eth_rpc,
+                                )
+                                .await?;
                             return Ok(proof.to_content(&stmt, &description)?);
                         }
                     }
@@ -129,7 +154,7 @@ impl Flow<Ctnt, Stmt, Stmt> for SoundCloudVerificationFlow {
             offset += self.limit;
         }
 
-        Err(FlowError::BadLookup(format!(
+        Err(FlowError::NotFound(format!(
             "soundcloud profile {} not found after searching up to {} entries",
             proof.permalink,
             self.max_offset + self.limit
@@ -187,6 +212,7 @@ mod tests {
             Ok(StatementResponse {
                 statement: statement.generate_statement()?,
                 delimiter: None,
+                typed_data: None,
             })
         }
 
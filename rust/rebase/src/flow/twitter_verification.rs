@@ -3,7 +3,10 @@ use crate::{
     proof::twitter_verification::TwitterVerificationProof as Prf,
     statement::twitter_verification::TwitterVerificationStatement as Stmt,
     types::{
-        defs::{Flow, Instructions, Issuer, Proof, Statement, StatementResponse, Subject},
+        defs::{
+            make_url, send_request, EthRpcOpts, Flow, HttpOpts, Instructions, Issuer, Proof,
+            ResolverOpts, Statement, StatementResponse,
+        },
         error::FlowError,
     },
 };
@@ -17,7 +20,6 @@ use reqwest::{
 use schemars::schema_for;
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
-use url::Url;
 use wasm_bindgen::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
@@ -25,6 +27,17 @@ use wasm_bindgen::prelude::*;
 pub struct TwitterVerificationFlow {
     pub api_key: String,
     pub delimiter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+    // Resolves did:web and did:key subjects when checking their signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+    // Checks Ethereum contract wallet signatures through JSON-RPC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // How long issued credentials are valid for, or forever if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_minutes: Option<i64>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -68,10 +81,15 @@ impl Flow<Ctnt, Stmt, Prf> for TwitterVerificationFlow {
     ) -> Result<StatementResponse, FlowError> {
         Ok(StatementResponse {
             delimiter: Some(self.delimiter.to_owned()),
+            typed_data: None,
             statement: statement.generate_statement()?,
         })
     }
 
+    fn validity_minutes(&self) -> Option<i64> {
+        self.validity_minutes
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Prf,
@@ -79,7 +97,7 @@ impl Flow<Ctnt, Stmt, Prf> for TwitterVerificationFlow {
     ) -> Result<Ctnt, FlowError> {
         let mut headers = HeaderMap::new();
         let s: HeaderValue = format!("Bearer {}", &self.api_key).parse().map_err(|_| {
-            FlowError::BadLookup("failed to generate authorization header".to_string())
+            FlowError::Misconfigured("failed to generate authorization header".to_string())
         })?;
         headers.insert(AUTHORIZATION, s);
         let client = Client::new();
@@ -91,26 +109,27 @@ impl Flow<Ctnt, Stmt, Prf> for TwitterVerificationFlow {
 
         let tweet_id = url_vec[url_vec.len() - 1];
 
-        let res: TwitterResponse = client
-            .get(
-                Url::parse("https://api.twitter.com/2/tweets")
-                    .map_err(|e| FlowError::BadLookup(e.to_string()))?,
-            )
+        let req = client
+            .get(make_url(
+                &self.http_opts,
+                "https://api.twitter.com",
+                "/2/tweets",
+            )?)
             .query(&[
                 ("ids", tweet_id.to_owned()),
                 ("expansions", "author_id".to_string()),
                 ("user.fields", "username".to_string()),
             ])
-            .headers(headers)
-            .send()
-            .await
-            .map_err(|e| FlowError::BadLookup(e.to_string()))?
+            .headers(headers);
+
+        let res: TwitterResponse = send_request(&self.http_opts, req)
+            .await?
             .json()
             .await
             .map_err(|e| FlowError::BadLookup(e.to_string()))?;
 
         if res.includes.users.is_empty() {
-            return Err(FlowError::BadLookup("No users found".to_string()));
+            return Err(FlowError::NotFound("No users found".to_string()));
         };
 
         if proof.statement.handle.to_lowercase() != res.includes.users[0].username.to_lowercase() {
@@ -122,7 +141,7 @@ impl Flow<Ctnt, Stmt, Prf> for TwitterVerificationFlow {
         };
 
         if res.data.is_empty() {
-            return Err(FlowError::BadLookup("No users found".to_string()));
+            return Err(FlowError::NotFound("No users found".to_string()));
         };
 
         let mut a = res.data[0].text.split(&self.delimiter);
@@ -130,7 +149,11 @@ impl Flow<Ctnt, Stmt, Prf> for TwitterVerificationFlow {
         let maybe_sig = a.next();
         match (maybe_stmt, maybe_sig) {
             (Some(stmt), Some(sig)) => {
-                proof.statement.subject.valid_signature(stmt, sig).await?;
+                proof
+                    .statement
+                    .subject
+                    .valid_signature_with_opts(stmt, sig, &self.resolver_opts, &self.eth_rpc)
+                    .await?;
                 Ok(proof.to_content(stmt, sig)?)
             }
             _ => Err(FlowError::Validation(
@@ -144,15 +167,17 @@ mod tests {
     use super::*;
     use crate::{
         test_util::util::{
-            test_did_keypair, test_ed25519_did, test_eth_did, test_solana_did,
-            test_witness_signature, test_witness_statement, MockFlow, MockIssuer, TestKey,
-            TestWitness,
+            test_did_keypair, test_ed25519_did, test_eth_did, test_resolver_opts, test_solana_did,
+            test_witness_signature, test_witness_statement, HeaderStubTransport, MockFlow,
+            MockIssuer, StubTransport, TestKey, TestWitness,
         },
         types::{
-            defs::{Issuer, Proof, Statement, Subject},
+            defs::{Issuer, Proof, Statement},
             enums::subject::Subjects,
         },
     };
+    use serde_json::json;
+    use std::sync::Arc;
 
     fn mock_proof(key: fn() -> Subjects) -> Prf {
         Prf {
@@ -185,6 +210,7 @@ mod tests {
             Ok(StatementResponse {
                 statement: statement.generate_statement()?,
                 delimiter: Some("\n\n".to_owned()),
+                typed_data: None,
             })
         }
 
@@ -201,7 +227,12 @@ mod tests {
             proof
                 .statement
                 .subject
-                .valid_signature(&self.statement, &self.signature)
+                .valid_signature_with_opts(
+                    &self.statement,
+                    &self.signature,
+                    &Some(test_resolver_opts()),
+                    &None,
+                )
                 .await?;
 
             Ok(proof
@@ -306,4 +337,57 @@ mod tests {
             panic!("Approved bad signature");
         };
     }
+
+    #[tokio::test]
+    async fn twitter_stub_transport() {
+        let i = MockIssuer {};
+        let (subj1, iss1) = test_did_keypair().await.unwrap();
+
+        let proof = Prf {
+            statement: Stmt {
+                subject: subj1,
+                handle: "foo".to_owned(),
+            },
+            tweet_url: "https://twitter.com/foo/status/1234".to_owned(),
+        };
+        let statement = proof.generate_statement().unwrap();
+        let signature = iss1.sign(&statement).await.unwrap();
+
+        // The stub only answers if the rewritten request kept its query and bearer token.
+        let stub = |api_key: &str| {
+            TwitterVerificationFlow {
+            api_key: api_key.to_string(),
+            delimiter: "\n\n".to_string(),
+            http_opts: Some(HttpOpts {
+                base_url: Some("http://stub.test".to_string()),
+                transport: Some(Arc::new(HeaderStubTransport {
+                    headers: vec![("authorization".to_string(), "Bearer secret".to_string())],
+                    stub: StubTransport {
+                        routes: vec![(
+                            "http://stub.test/2/tweets?ids=1234&expansions=author_id&user.fields=username"
+                                .to_string(),
+                            json!({
+                                "data": [{ "text": format!("{}\n\n{}", statement, signature) }],
+                                "includes": { "users": [{ "username": "foo" }] }
+                            })
+                            .to_string(),
+                        )],
+                    },
+                })),
+            }),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }
+        };
+
+        stub("secret")
+            .validate_proof(proof.clone(), i.clone())
+            .await
+            .unwrap();
+
+        if stub("wrong").validate_proof(proof, i).await.is_ok() {
+            panic!("Approved lookup without the configured bearer token");
+        };
+    }
 }
@@ -1,12 +1,12 @@
 use crate::{
     context::context_loader::context_loader,
     types::{
-        defs::{DIDKey, Issuer, Subject},
+        defs::{make_resolver, Holder, Issuer, ResolverOpts, Subject},
         error::{IssuerError, SubjectError},
+        presentation::PresentationOptions,
     },
 };
 use async_trait::async_trait;
-use did_web::DIDWeb;
 use ed25519_dalek::{
     ed25519::signature::Signature, Keypair, PublicKey, SecretKey, Signer as InnerSigner, Verifier,
 };
@@ -16,7 +16,7 @@ use ssi::{
     jwk::{Params, JWK},
     ldp::Proof,
     one_or_many::OneOrMany,
-    vc::{Credential, LinkedDataProofOptions, URI},
+    vc::{Credential, LinkedDataProofOptions, Presentation, URI},
 };
 
 use serde::{Deserialize, Serialize};
@@ -26,14 +26,17 @@ pub struct Ed25519Jwk {
     pub did: String,
     pub jwk: JWK,
     pub key_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
 }
 
-// NOTE: This Issuer supports "did:web" and "did:key" variants neutrally
+// NOTE: This Issuer supports any DID method that make_resolver can resolve, as long as the
+// verification method is an ed25519 key.
 impl Ed25519Jwk {
     pub fn new(did: &str, jwk_str: &str, key_name: &str) -> Result<Self, IssuerError> {
-        if !did.starts_with("did:web:") && !did.starts_with("did:key:") {
+        if !did.starts_with("did:") {
             return Err(IssuerError::Internal(format!(
-                "Currently only supports ed25519 keys as did:web or did:key, got: {}",
+                "Expected a DID, got: {}",
                 did
             )));
         }
@@ -41,13 +44,29 @@ impl Ed25519Jwk {
         let jwk: JWK = serde_json::from_str(jwk_str)
             .map_err(|e| IssuerError::Internal(format!("deserialization error: {}", e)))?;
 
+        match &jwk.params {
+            Params::OKP(o) if o.curve == "Ed25519" => {}
+            _ => {
+                return Err(IssuerError::Internal(
+                    "Currently only supports ed25519 keys".to_string(),
+                ))
+            }
+        }
+
         Ok(Ed25519Jwk {
             did: did.to_owned(),
             jwk,
             key_name: key_name.to_owned(),
+            resolver_opts: None,
         })
     }
 
+    // with_resolver_opts has the issuer resolve its own DID using opts when making proofs.
+    pub fn with_resolver_opts(mut self, opts: Option<ResolverOpts>) -> Self {
+        self.resolver_opts = opts;
+        self
+    }
+
     pub fn to_keypair(&self) -> Result<Keypair, SubjectError> {
         match &self.jwk.clone().params {
             Params::OKP(o) => match &o.private_key {
@@ -127,53 +146,25 @@ impl Issuer for Ed25519Jwk {
 
     // generate_jwt takes a VC and returns it's formatted as a JWT:
     async fn generate_jwt(&self, credential: &Credential) -> Result<String, IssuerError> {
-        // TODO: Figure out how to generalize over did resolver
-        if self.did.starts_with("did:web") {
-            Ok(credential
-                .generate_jwt(
-                    Some(&self.jwk),
-                    &LinkedDataProofOptions {
-                        checks: None,
-                        created: None,
-                        eip712_domain: None,
-                        type_: None,
-                        verification_method: Some(URI::String(format!(
-                            "{}#{}",
-                            self.did()?,
-                            self.key_name
-                        ))),
-                        ..Default::default()
-                    },
-                    &DIDWeb,
-                )
-                .await
-                .map_err(|e| IssuerError::Jwt(format!("Failed to generate JWT: {}", e)))?)
-        } else if self.did.starts_with("did:key") {
-            Ok(credential
-                .generate_jwt(
-                    Some(&self.jwk),
-                    &LinkedDataProofOptions {
-                        checks: None,
-                        created: None,
-                        eip712_domain: None,
-                        type_: None,
-                        verification_method: Some(URI::String(format!(
-                            "{}#{}",
-                            self.did()?,
-                            self.key_name
-                        ))),
-                        ..Default::default()
-                    },
-                    &DIDKey,
-                )
-                .await
-                .map_err(|e| IssuerError::Jwt(format!("Failed to generate JWT: {}", e)))?)
-        } else {
-            Err(IssuerError::Vc(format!(
-                "Expected did:web or did:key, got: {}",
-                self.did
-            )))
-        }
+        credential
+            .generate_jwt(
+                Some(&self.jwk),
+                &LinkedDataProofOptions {
+                    checks: None,
+                    created: None,
+                    eip712_domain: None,
+                    type_: None,
+                    verification_method: Some(URI::String(format!(
+                        "{}#{}",
+                        self.did()?,
+                        self.key_name
+                    ))),
+                    ..Default::default()
+                },
+                &make_resolver(&self.resolver_opts),
+            )
+            .await
+            .map_err(|e| IssuerError::Jwt(format!("Failed to generate JWT: {}", e)))
     }
 
     // proof returns the linked data proof options for a given issuer type
@@ -186,51 +177,74 @@ impl Issuer for Ed25519Jwk {
             ..Default::default()
         };
 
-        // TODO: Figure out how to generalize over did resolver
-        if self.did.starts_with("did:web") {
-            Ok(Some(OneOrMany::One(
-                credential
-                    .generate_proof(
-                        &self.jwk,
-                        &lpdo,
-                        &DIDWeb,
-                        &mut context_loader().map_err(|e| IssuerError::Vc(e.to_string()))?,
-                    )
-                    .await
-                    .map_err(|e| {
-                        IssuerError::Proof(format!("Failed to generate LDP proof: {}", e))
-                    })?,
-            )))
-        } else if self.did.starts_with("did:key") {
-            Ok(Some(OneOrMany::One(
-                credential
-                    .generate_proof(
-                        &self.jwk,
-                        &lpdo,
-                        &DIDKey,
-                        &mut context_loader().map_err(|e| IssuerError::Vc(e.to_string()))?,
-                    )
-                    .await
-                    .map_err(|e| {
-                        IssuerError::Proof(format!("Failed to generate LDP proof: {}", e))
-                    })?,
-            )))
-        } else {
-            Err(IssuerError::Vc(format!(
-                "Expected did:web or did:key, got: {}",
-                self.did
-            )))
-        }
+        Ok(Some(OneOrMany::One(
+            credential
+                .generate_proof(
+                    &self.jwk,
+                    &lpdo,
+                    &make_resolver(&self.resolver_opts),
+                    &mut context_loader().map_err(|e| IssuerError::Vc(e.to_string()))?,
+                )
+                .await
+                .map_err(|e| IssuerError::Proof(format!("Failed to generate LDP proof: {}", e)))?,
+        )))
+    }
+}
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Holder for Ed25519Jwk {
+    async fn sign_vp(
+        &self,
+        vp: &mut Presentation,
+        opts: &PresentationOptions,
+    ) -> Result<(), IssuerError> {
+        let proof = vp
+            .generate_proof(
+                &self.jwk,
+                &opts.proof_options(Some(self.verification_method()?)),
+                &make_resolver(&self.resolver_opts),
+                &mut context_loader().map_err(|e| IssuerError::Vc(e.to_string()))?,
+            )
+            .await
+            .map_err(|e| IssuerError::Proof(format!("Failed to generate LDP proof: {}", e)))?;
+        vp.add_proof(proof);
+        Ok(())
+    }
+
+    async fn generate_vp_jwt(
+        &self,
+        vp: &Presentation,
+        opts: &PresentationOptions,
+    ) -> Result<String, IssuerError> {
+        vp.generate_jwt(
+            Some(&self.jwk),
+            &LinkedDataProofOptions {
+                checks: None,
+                created: None,
+                ..opts.proof_options(Some(self.verification_method()?))
+            },
+            &make_resolver(&self.resolver_opts),
+        )
+        .await
+        .map_err(|e| IssuerError::Jwt(format!("Failed to generate JWT: {}", e)))
     }
 }
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
         proof::attestation::basic_post_attestation::BasicPostAttestationProof,
         statement::attestation::basic_post_attestation::BasicPostAttestationStatement,
+        subject::ed25519::Ed25519Jwk as SubjEd25519,
         test_util::util::test_did_keypair,
-        types::defs::{get_verification_method, make_resolver, Content, Proof, Statement},
+        types::{
+            defs::{
+                get_verification_method, make_resolver, Content, DIDMethod, Proof, Source,
+                Statement,
+            },
+            enums::subject::{Jwk, Subjects},
+        },
     };
     use chrono::{SecondsFormat, Utc};
 
@@ -343,4 +357,70 @@ mod test {
             panic!("{}", res.errors.join(", "));
         };
     }
+
+    #[tokio::test]
+    async fn test_ed25519_did_jwk() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let did = did_jwk::DIDJWK
+            .generate(&Source::Key(&jwk.to_public()))
+            .unwrap();
+        let iss = Ed25519Jwk::new(&did, &serde_json::to_string(&jwk).unwrap(), "0").unwrap();
+        let subj = SubjEd25519::new(&did, "0").unwrap();
+
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let sig = iss.sign(&now).await.unwrap();
+        subj.valid_signature(&now, &sig).await.unwrap();
+        match Subjects::from_did(&did).unwrap() {
+            Subjects::Jwk(Jwk::Ed25519(x)) => {
+                assert_eq!(x.verification_method().unwrap(), format!("{}#0", did))
+            }
+            _ => panic!("Recovered did:jwk subject as another method"),
+        }
+
+        let statement = BasicPostAttestationStatement {
+            subject: Subjects::Jwk(Jwk::Ed25519(subj)),
+            reply_to: None,
+            title: "Hello".to_string(),
+            body: "World".to_string(),
+        };
+        let s = statement.generate_statement().unwrap();
+        let signature = iss.sign(&s).await.unwrap();
+        let proof = BasicPostAttestationProof {
+            statement,
+            signature: signature.clone(),
+        };
+        let content = proof.to_content(&s, &signature).unwrap();
+
+        let vc = content.credential(iss.clone()).await.unwrap();
+        let res = vc
+            .verify(
+                Some(LinkedDataProofOptions {
+                    verification_method: Some(URI::String(format!("{}#0", did))),
+                    ..Default::default()
+                }),
+                &make_resolver(&None),
+                &mut context_loader().unwrap(),
+            )
+            .await;
+
+        if !res.errors.is_empty() {
+            panic!("{}", res.errors.join(", "));
+        };
+
+        let jwt = content.jwt(iss).await.unwrap();
+        let res = Credential::verify_jwt(
+            &jwt,
+            Some(LinkedDataProofOptions {
+                verification_method: Some(URI::String(format!("{}#0", did))),
+                ..Default::default()
+            }),
+            &make_resolver(&None),
+            &mut context_loader().unwrap(),
+        )
+        .await;
+
+        if !res.errors.is_empty() {
+            panic!("{}", res.errors.join(", "));
+        };
+    }
 }
@@ -1 +1,3 @@
 pub mod ed25519;
+pub mod remote;
+pub mod secp256k1;
@@ -0,0 +1,341 @@
+use crate::{
+    context::context_loader::context_loader,
+    types::{
+        defs::{make_resolver, resolve_vm, Issuer, ResolverOpts, Subject},
+        error::{IssuerError, SubjectError},
+    },
+};
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use base64::engine::Engine as _;
+use ssi::{
+    jwk::{Algorithm, JWK},
+    jws::{sign_bytes, verify_bytes, Header},
+    ldp::{Proof, ProofSuite, SigningInput},
+    one_or_many::OneOrMany,
+    vc::{base64_encode_json, Credential, LinkedDataProofOptions, URI},
+};
+use std::sync::Arc;
+
+// RemoteSigner produces raw signatures with a key that never enters this process, such as one
+// held in an HSM, a PKCS#11 module or a signing sidecar. sign_bytes must return the signature
+// in the JWS encoding for the key's algorithm (e.g. the 64 byte r || s for ES256K).
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait RemoteSigner: std::fmt::Debug + Send + Sync {
+    async fn sign_bytes(&self, data: &[u8]) -> Result<Vec<u8>, IssuerError>;
+    // public_jwk must include "alg" unless it can be inferred from the key type.
+    fn public_jwk(&self) -> Result<JWK, IssuerError>;
+}
+
+// LocalSigner keeps the private JWK in memory. It exists for tests and local development.
+#[derive(Clone, Debug)]
+pub struct LocalSigner {
+    pub jwk: JWK,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl RemoteSigner for LocalSigner {
+    async fn sign_bytes(&self, data: &[u8]) -> Result<Vec<u8>, IssuerError> {
+        sign_bytes(algorithm(&self.jwk)?, data, &self.jwk)
+            .map_err(|e| IssuerError::Sign(e.to_string()))
+    }
+
+    fn public_jwk(&self) -> Result<JWK, IssuerError> {
+        Ok(self.jwk.to_public())
+    }
+}
+
+fn algorithm(jwk: &JWK) -> Result<Algorithm, IssuerError> {
+    jwk.get_algorithm()
+        .ok_or_else(|| IssuerError::Internal("could not determine jwk algorithm".to_string()))
+}
+
+#[derive(Clone, Debug)]
+pub struct RemoteIssuer {
+    pub did: String,
+    pub key_name: String,
+    pub signer: Arc<dyn RemoteSigner>,
+    pub resolver_opts: Option<ResolverOpts>,
+}
+
+// NOTE: LD proofs are limited to suites that sign plain bytes (e.g. Ed25519Signature2018,
+// EcdsaSecp256k1Signature2019, JsonWebSignature2020).
+impl RemoteIssuer {
+    // new errs unless the signer's key is the one the DID's verification method names, since
+    // valid_signature checks signatures against the signer's key. The DID is resolved using
+    // resolver_opts, here and when making proofs.
+    pub async fn new(
+        did: &str,
+        key_name: &str,
+        signer: Arc<dyn RemoteSigner>,
+        resolver_opts: Option<ResolverOpts>,
+    ) -> Result<Self, IssuerError> {
+        let vm = format!("{}#{}", did, key_name);
+        resolve_vm(&vm, &make_resolver(&resolver_opts))
+            .await
+            .map_err(|e| IssuerError::Internal(format!("could not resolve {}: {}", vm, e)))?
+            .match_jwk(&signer.public_jwk()?)
+            .map_err(|e| {
+                IssuerError::Internal(format!("signer key does not match {}: {}", vm, e))
+            })?;
+
+        Ok(RemoteIssuer {
+            did: did.to_owned(),
+            key_name: key_name.to_owned(),
+            signer,
+            resolver_opts,
+        })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Subject for RemoteIssuer {
+    fn did(&self) -> Result<String, SubjectError> {
+        Ok(self.did.to_owned())
+    }
+
+    fn display_id(&self) -> Result<String, SubjectError> {
+        Ok(self.did.to_owned())
+    }
+
+    fn verification_method(&self) -> Result<String, SubjectError> {
+        Ok(format!("{}#{}", &self.did, &self.key_name))
+    }
+
+    async fn valid_signature(&self, statement: &str, signature: &str) -> Result<(), SubjectError> {
+        let sig = hex::decode(signature).map_err(|e| SubjectError::Validation(e.to_string()))?;
+        let jwk = self
+            .signer
+            .public_jwk()
+            .map_err(|e| SubjectError::Validation(e.to_string()))?;
+        let alg = algorithm(&jwk).map_err(|e| SubjectError::Validation(e.to_string()))?;
+
+        verify_bytes(alg, statement.as_bytes(), &jwk, &sig)
+            .map_err(|e| SubjectError::Validation(e.to_string()))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Issuer for RemoteIssuer {
+    // sign takes plain text and returns the corresponding signature
+    async fn sign(&self, plain_text: &str) -> Result<String, IssuerError> {
+        let sig = self.signer.sign_bytes(plain_text.as_bytes()).await?;
+        Ok(hex::encode(sig))
+    }
+
+    // sign_vc takes a mutable reference to an incomplete VC and signs it.
+    async fn sign_vc(&self, credential: &mut Credential) -> Result<(), IssuerError> {
+        credential.proof = self.proof(credential).await?;
+        Ok(())
+    }
+
+    // generate_jwt takes a VC and returns it's formatted as a JWT:
+    async fn generate_jwt(&self, credential: &Credential) -> Result<String, IssuerError> {
+        let header = Header {
+            algorithm: algorithm(&self.signer.public_jwk()?)?,
+            key_id: Some(self.verification_method()?),
+            ..Default::default()
+        };
+
+        let claims = credential
+            .to_jwt_claims()
+            .map_err(|e| IssuerError::Jwt(e.to_string()))?;
+
+        let signing_input = format!(
+            "{}.{}",
+            base64_encode_json(&header).map_err(|e| IssuerError::Jwt(e.to_string()))?,
+            base64_encode_json(&claims).map_err(|e| IssuerError::Jwt(e.to_string()))?
+        );
+
+        let sig = self.signer.sign_bytes(signing_input.as_bytes()).await?;
+        Ok(format!("{}.{}", signing_input, BASE64.encode(sig)))
+    }
+
+    // proof returns the linked data proof options for a given issuer type
+    async fn proof(
+        &self,
+        credential: &Credential,
+    ) -> Result<Option<OneOrMany<Proof>>, IssuerError> {
+        let lpdo = LinkedDataProofOptions {
+            verification_method: Some(URI::String(self.verification_method()?)),
+            ..Default::default()
+        };
+
+        let preparation = credential
+            .prepare_proof(
+                &self.signer.public_jwk()?,
+                &lpdo,
+                &make_resolver(&self.resolver_opts),
+                &mut context_loader().map_err(|e| IssuerError::Vc(e.to_string()))?,
+            )
+            .await
+            .map_err(|e| IssuerError::Proof(format!("Failed to prepare LDP proof: {}", e)))?;
+
+        let sig = match &preparation.signing_input {
+            SigningInput::Bytes(b) => self.signer.sign_bytes(&b.0).await?,
+            _ => {
+                return Err(IssuerError::Proof(format!(
+                    "remote signing is not supported for {:?}",
+                    preparation.proof.type_
+                )))
+            }
+        };
+
+        Ok(Some(OneOrMany::One(
+            preparation
+                .proof
+                .type_
+                .complete(&preparation, &BASE64.encode(sig))
+                .await
+                .map_err(|e| IssuerError::Proof(format!("Failed to complete LDP proof: {}", e)))?,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        proof::attestation::basic_post_attestation::BasicPostAttestationProof,
+        statement::attestation::basic_post_attestation::BasicPostAttestationStatement,
+        test_util::util::{test_did_keypair, test_remote_issuer},
+        types::defs::{get_verification_method, Content, Proof, Statement},
+    };
+
+    #[tokio::test]
+    async fn test_remote_issuer_signatures() {
+        let (subj, iss) = test_remote_issuer().await.unwrap();
+        let (_, other) = test_remote_issuer().await.unwrap();
+        let sig = iss.sign("hello world").await.unwrap();
+
+        iss.valid_signature("hello world", &sig).await.unwrap();
+        // The subject form of the same did:key should accept it too.
+        subj.valid_signature("hello world", &sig).await.unwrap();
+
+        if other.valid_signature("hello world", &sig).await.is_ok() {
+            panic!("Other issuer accepted signature");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_issuer_mismatched_signer() {
+        let (_, iss) = test_remote_issuer().await.unwrap();
+        let (_, other) = test_remote_issuer().await.unwrap();
+        if RemoteIssuer::new(&iss.did, &iss.key_name, other.signer, None)
+            .await
+            .is_ok()
+        {
+            panic!("Accepted a signer that does not match the did");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_issuer_resolver_opts() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let did = "did:web:remote.rebase.test";
+        let doc = serde_json::json!([{
+            "@context": "https://www.w3.org/ns/did/v1",
+            "id": did,
+            "verificationMethod": [{
+                "id": format!("{}#controller", did),
+                "type": "Ed25519VerificationKey2018",
+                "controller": did,
+                "publicKeyJwk": jwk.to_public(),
+            }],
+            "assertionMethod": [format!("{}#controller", did)],
+        }]);
+        let mut opts = ResolverOpts::default();
+        opts.add_static_documents(&doc.to_string()).unwrap();
+
+        let signer = Arc::new(LocalSigner { jwk });
+        let iss = RemoteIssuer::new(did, "controller", signer.clone(), Some(opts))
+            .await
+            .unwrap();
+        let (subj, _) = test_did_keypair().await.unwrap();
+        let statement = BasicPostAttestationStatement {
+            subject: subj,
+            reply_to: None,
+            title: "Hello".to_string(),
+            body: "World".to_string(),
+        };
+        let s = statement.generate_statement().unwrap();
+        let proof = BasicPostAttestationProof {
+            statement,
+            signature: "unchecked".to_string(),
+        };
+        proof
+            .to_content(&s, "unchecked")
+            .unwrap()
+            .credential(iss)
+            .await
+            .unwrap();
+
+        // Without the static document the DID can't be resolved.
+        if RemoteIssuer::new(did, "controller", signer, None)
+            .await
+            .is_ok()
+        {
+            panic!("Resolved a DID without its static document");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_credential_issuance() {
+        let (_, iss) = test_remote_issuer().await.unwrap();
+        let (subj, _) = test_did_keypair().await.unwrap();
+        let statement = BasicPostAttestationStatement {
+            subject: subj,
+            reply_to: None,
+            title: "Hello".to_string(),
+            body: "World".to_string(),
+        };
+
+        let s = statement.generate_statement().unwrap();
+        let proof = BasicPostAttestationProof {
+            statement,
+            signature: "unchecked".to_string(),
+        };
+        let content = proof.to_content(&s, "unchecked").unwrap();
+
+        let vc = content.credential(iss.clone()).await.unwrap();
+        let vc_iss = vc.issuer.as_ref().unwrap().get_id();
+        let vm = get_verification_method(&vc_iss, &make_resolver(&None))
+            .await
+            .unwrap();
+
+        let res = vc
+            .verify(
+                Some(LinkedDataProofOptions {
+                    verification_method: Some(URI::String(vm.clone())),
+                    ..Default::default()
+                }),
+                &make_resolver(&None),
+                &mut context_loader().unwrap(),
+            )
+            .await;
+
+        if !res.errors.is_empty() {
+            panic!("{}", res.errors.join(", "));
+        };
+
+        let jwt = content.jwt(iss).await.unwrap();
+        let res = Credential::verify_jwt(
+            &jwt,
+            Some(LinkedDataProofOptions {
+                verification_method: Some(URI::String(vm)),
+                ..Default::default()
+            }),
+            &make_resolver(&None),
+            &mut context_loader().unwrap(),
+        )
+        .await;
+
+        if !res.errors.is_empty() {
+            panic!("{}", res.errors.join(", "));
+        };
+    }
+}
@@ -0,0 +1,457 @@
+use crate::{
+    context::context_loader::context_loader,
+    types::{
+        defs::{make_resolver, DIDKey, DIDMethod, Holder, Issuer, ResolverOpts, Source, Subject},
+        error::{IssuerError, SubjectError},
+        presentation::PresentationOptions,
+    },
+};
+use async_trait::async_trait;
+use hex::FromHex;
+use k256::ecdsa::{
+    recoverable::{Id, Signature as RecoverableSig},
+    signature::{Signature as _, Signer},
+    Signature as Sig, SigningKey, VerifyingKey,
+};
+use serde::{Deserialize, Serialize};
+use ssi::{
+    jwk::{eip155::hash_public_key, Algorithm, Params, JWK},
+    ldp::{Proof, ProofSuiteType},
+    one_or_many::OneOrMany,
+    vc::{Credential, LinkedDataProofOptions, Presentation, URI},
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Secp256k1Jwk {
+    pub did: String,
+    pub jwk: JWK,
+    pub key_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_opts: Option<ResolverOpts>,
+}
+
+// NOTE: This Issuer supports "did:pkh:eip155" and "did:key" variants.
+// did:pkh:eip155 issuers produce EthereumEip712Signature2021 proofs and ES256K-R JWTs, since the
+// DID document only carries the account address and the key has to be recovered from the
+// signature. did:key issuers produce EcdsaSecp256k1Signature2019 proofs and ES256K JWTs.
+// ES256K-R JWTs have to be checked with defs::verify_jwt, which recovers the key from the
+// signature, as Credential::verify_jwt expects the key in the resolved verification method.
+impl Secp256k1Jwk {
+    pub fn new(did: &str, jwk_str: &str, key_name: &str) -> Result<Self, IssuerError> {
+        let mut jwk: JWK = serde_json::from_str(jwk_str)
+            .map_err(|e| IssuerError::Internal(format!("deserialization error: {}", e)))?;
+
+        match &jwk.params {
+            Params::EC(ec) if ec.curve.as_deref() == Some("secp256k1") => {}
+            _ => {
+                return Err(IssuerError::Internal(
+                    "expected a secp256k1 EC jwk".to_string(),
+                ))
+            }
+        }
+
+        if did.starts_with("did:pkh:eip155:") {
+            let address = did.rsplit(':').next().unwrap_or_default();
+            let expected = hash_public_key(&jwk)
+                .map_err(|e| IssuerError::Internal(format!("could not hash public key: {}", e)))?;
+            if address.to_lowercase() != expected {
+                return Err(IssuerError::Internal(format!(
+                    "jwk address {} does not match did {}",
+                    expected, did
+                )));
+            }
+            jwk.algorithm = Some(Algorithm::ES256KR);
+        } else if did.starts_with("did:key:") {
+            let expected = DIDKey {}.generate(&Source::Key(&jwk.to_public()));
+            if expected.as_deref() != Some(did) {
+                return Err(IssuerError::Internal(format!(
+                    "jwk did {} does not match did {}",
+                    expected.unwrap_or_default(),
+                    did
+                )));
+            }
+            jwk.algorithm = Some(Algorithm::ES256K);
+        } else {
+            return Err(IssuerError::Internal(format!(
+                "Currently only supports secp256k1 keys as did:pkh:eip155 or did:key, got: {}",
+                did
+            )));
+        }
+
+        Ok(Secp256k1Jwk {
+            did: did.to_owned(),
+            jwk,
+            key_name: key_name.to_owned(),
+            resolver_opts: None,
+        })
+    }
+
+    // with_resolver_opts has the issuer resolve its own DID using opts when making proofs.
+    pub fn with_resolver_opts(mut self, opts: Option<ResolverOpts>) -> Self {
+        self.resolver_opts = opts;
+        self
+    }
+
+    pub fn signing_key(&self) -> Result<SigningKey, SubjectError> {
+        match &self.jwk.params {
+            Params::EC(ec) => match &ec.ecc_private_key {
+                Some(key) => SigningKey::from_bytes(&key.0).map_err(|e| {
+                    SubjectError::Validation(format!("failed to generate secret key: {}", e))
+                }),
+                _ => Err(SubjectError::Validation(
+                    "could not recover private key from jwk".to_string(),
+                )),
+            },
+            _ => Err(SubjectError::Validation(
+                "could not recover private key from jwk".to_string(),
+            )),
+        }
+    }
+
+    pub fn public_key(&self) -> Result<VerifyingKey, SubjectError> {
+        match &self.jwk.params {
+            Params::EC(ec) => match (&ec.x_coordinate, &ec.y_coordinate) {
+                (Some(x), Some(y)) => {
+                    let mut point = vec![0x04];
+                    point.extend_from_slice(&x.0);
+                    point.extend_from_slice(&y.0);
+                    VerifyingKey::from_sec1_bytes(&point).map_err(|e| {
+                        SubjectError::Validation(format!("could not generate public key: {}", e))
+                    })
+                }
+                _ => Err(SubjectError::Validation(
+                    "could not recover public key from jwk".to_string(),
+                )),
+            },
+            _ => Err(SubjectError::Validation(
+                "could not recover public key from jwk".to_string(),
+            )),
+        }
+    }
+
+    fn is_pkh(&self) -> bool {
+        self.did.starts_with("did:pkh:eip155:")
+    }
+
+    fn proof_options(&self) -> Result<LinkedDataProofOptions, SubjectError> {
+        Ok(LinkedDataProofOptions {
+            verification_method: Some(URI::String(self.verification_method()?)),
+            ..Default::default()
+        })
+    }
+}
+
+// Statements are signed as EIP-191 personal messages, the same as Eip155 subjects sign them.
+fn eip191_message(statement: &str) -> Vec<u8> {
+    format!(
+        "\x19Ethereum Signed Message:\n{}{}",
+        statement.len(),
+        statement
+    )
+    .into()
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Subject for Secp256k1Jwk {
+    fn did(&self) -> Result<String, SubjectError> {
+        Ok(self.did.to_owned())
+    }
+
+    fn display_id(&self) -> Result<String, SubjectError> {
+        Ok(self.did.to_owned())
+    }
+
+    fn verification_method(&self) -> Result<String, SubjectError> {
+        Ok(format!("{}#{}", &self.did, &self.key_name))
+    }
+
+    async fn valid_signature(&self, statement: &str, signature: &str) -> Result<(), SubjectError> {
+        let signature = <[u8; 65]>::from_hex(signature.trim_start_matches("0x")).map_err(|e| {
+            SubjectError::Validation(format!("could not marshal signature to hex: {}", e))
+        })?;
+
+        let pk = RecoverableSig::new(
+            &Sig::from_bytes(&signature[..64]).map_err(|e| {
+                SubjectError::Validation(format!(
+                    "could not process signature to recover key: {}",
+                    e
+                ))
+            })?,
+            Id::new(signature[64] % 27).map_err(|e| {
+                SubjectError::Validation(format!(
+                    "could not process signature to recover key: {}",
+                    e
+                ))
+            })?,
+        )
+        .map_err(|e| SubjectError::Validation(format!("could not recover key: {}", e)))?
+        .recover_verifying_key(&eip191_message(statement))
+        .map_err(|e| {
+            SubjectError::Validation(format!("could not process statement to recover key: {}", e))
+        })?;
+
+        if pk != self.public_key()? {
+            Err(SubjectError::Validation("signature mismatch".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Issuer for Secp256k1Jwk {
+    // sign takes plain text and returns the corresponding signature
+    async fn sign(&self, plain_text: &str) -> Result<String, IssuerError> {
+        let sig: RecoverableSig = self
+            .signing_key()?
+            .try_sign(&eip191_message(plain_text))
+            .map_err(|e| IssuerError::Internal(format!("failed to sign: {}", e)))?;
+
+        let mut b = sig.as_ref().to_vec();
+        b[64] += 27;
+        Ok(format!("0x{}", hex::encode(b)))
+    }
+
+    // sign_vc takes a mutable reference to an incomplete VC and signs it.
+    async fn sign_vc(&self, credential: &mut Credential) -> Result<(), IssuerError> {
+        credential.proof = self.proof(credential).await?;
+        Ok(())
+    }
+
+    // generate_jwt takes a VC and returns it's formatted as a JWT:
+    async fn generate_jwt(&self, credential: &Credential) -> Result<String, IssuerError> {
+        credential
+            .generate_jwt(
+                Some(&self.jwk),
+                &LinkedDataProofOptions {
+                    checks: None,
+                    created: None,
+                    ..self.proof_options()?
+                },
+                &make_resolver(&self.resolver_opts),
+            )
+            .await
+            .map_err(|e| IssuerError::Jwt(format!("Failed to generate JWT: {}", e)))
+    }
+
+    // proof returns the linked data proof options for a given issuer type
+    async fn proof(
+        &self,
+        credential: &Credential,
+    ) -> Result<Option<OneOrMany<Proof>>, IssuerError> {
+        let mut lpdo = self.proof_options()?;
+        if self.is_pkh() {
+            lpdo.type_ = Some(ProofSuiteType::EthereumEip712Signature2021);
+        }
+
+        Ok(Some(OneOrMany::One(
+            credential
+                .generate_proof(
+                    &self.jwk,
+                    &lpdo,
+                    &make_resolver(&self.resolver_opts),
+                    &mut context_loader().map_err(|e| IssuerError::Vc(e.to_string()))?,
+                )
+                .await
+                .map_err(|e| IssuerError::Proof(format!("Failed to generate LDP proof: {}", e)))?,
+        )))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Holder for Secp256k1Jwk {
+    async fn sign_vp(
+        &self,
+        vp: &mut Presentation,
+        opts: &PresentationOptions,
+    ) -> Result<(), IssuerError> {
+        // EIP-712 can't type the credentials nested in a presentation, so did:pkh:eip155
+        // holders sign with EcdsaSecp256k1RecoverySignature2020 instead.
+        let proof = vp
+            .generate_proof(
+                &self.jwk,
+                &opts.proof_options(Some(self.verification_method()?)),
+                &make_resolver(&self.resolver_opts),
+                &mut context_loader().map_err(|e| IssuerError::Vc(e.to_string()))?,
+            )
+            .await
+            .map_err(|e| IssuerError::Proof(format!("Failed to generate LDP proof: {}", e)))?;
+        vp.add_proof(proof);
+        Ok(())
+    }
+
+    async fn generate_vp_jwt(
+        &self,
+        vp: &Presentation,
+        opts: &PresentationOptions,
+    ) -> Result<String, IssuerError> {
+        vp.generate_jwt(
+            Some(&self.jwk),
+            &LinkedDataProofOptions {
+                checks: None,
+                created: None,
+                ..opts.proof_options(Some(self.verification_method()?))
+            },
+            &make_resolver(&self.resolver_opts),
+        )
+        .await
+        .map_err(|e| IssuerError::Jwt(format!("Failed to generate JWT: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        proof::attestation::basic_post_attestation::BasicPostAttestationProof,
+        statement::attestation::basic_post_attestation::BasicPostAttestationStatement,
+        test_util::util::{test_secp256k1_did_key, test_secp256k1_keypair},
+        types::defs::{get_verification_method, verify_jwt, Content, Proof, Statement},
+    };
+    use chrono::{SecondsFormat, Utc};
+
+    #[tokio::test]
+    async fn test_secp256k1_issuer_and_subject() {
+        let (subj1, iss1) = test_secp256k1_keypair().unwrap();
+        let (subj2, iss2) = test_secp256k1_keypair().unwrap();
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let sig1 = iss1.sign(&now).await.unwrap();
+        let sig2 = iss2.sign(&now).await.unwrap();
+
+        iss1.valid_signature(&now, &sig1).await.unwrap();
+        iss2.valid_signature(&now, &sig2).await.unwrap();
+
+        if iss1.valid_signature(&now, &sig2).await.is_ok() {
+            panic!("Issuer 1 thought signature 2 was valid")
+        }
+
+        // Signatures should be accepted by the matching Eip155 subject.
+        subj1.valid_signature(&now, &sig1).await.unwrap();
+        subj2.valid_signature(&now, &sig2).await.unwrap();
+
+        if subj1.valid_signature(&now, &sig2).await.is_ok() {
+            panic!("Subject 1 thought signature 2 was valid")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_secp256k1_mismatched_did() {
+        let (_, iss1) = test_secp256k1_keypair().unwrap();
+        let (_, iss2) = test_secp256k1_keypair().unwrap();
+        let jwk = serde_json::to_string(&iss1.jwk).unwrap();
+        if Secp256k1Jwk::new(&iss2.did, &jwk, "blockchainAccountId").is_ok() {
+            panic!("Accepted a jwk that does not match the did")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_secp256k1_mismatched_did_key() {
+        let iss1 = test_secp256k1_did_key().await.unwrap();
+        let iss2 = test_secp256k1_did_key().await.unwrap();
+        let jwk = serde_json::to_string(&iss1.jwk).unwrap();
+        if Secp256k1Jwk::new(&iss2.did, &jwk, &iss2.key_name).is_ok() {
+            panic!("Accepted a jwk that does not match the did:key")
+        }
+    }
+
+    async fn issue_and_verify(iss: Secp256k1Jwk) {
+        let (subj, _) = test_secp256k1_keypair().unwrap();
+        let statement = BasicPostAttestationStatement {
+            subject: subj,
+            reply_to: None,
+            title: "Hello".to_string(),
+            body: "World".to_string(),
+        };
+
+        let s = statement.generate_statement().unwrap();
+        let proof = BasicPostAttestationProof {
+            statement,
+            signature: "unchecked".to_string(),
+        };
+        let content = proof.to_content(&s, "unchecked").unwrap();
+
+        let vc = content.credential(iss.clone()).await.unwrap();
+        let vc_iss = vc.issuer.as_ref().unwrap().get_id();
+        let vm = get_verification_method(&vc_iss, &make_resolver(&iss.resolver_opts))
+            .await
+            .unwrap();
+
+        let res = vc
+            .verify(
+                Some(LinkedDataProofOptions {
+                    verification_method: Some(URI::String(vm.clone())),
+                    ..Default::default()
+                }),
+                &make_resolver(&iss.resolver_opts),
+                &mut context_loader().unwrap(),
+            )
+            .await;
+
+        if !res.errors.is_empty() {
+            panic!("{}", res.errors.join(", "));
+        };
+
+        // Left unset, created is when the check is made rather than when the options are.
+        let ldpo = LinkedDataProofOptions {
+            verification_method: Some(URI::String(vm)),
+            created: None,
+            ..Default::default()
+        };
+        let jwt = content.jwt(iss.clone()).await.unwrap();
+        let res = verify_jwt(
+            &jwt,
+            Some(ldpo.clone()),
+            &make_resolver(&iss.resolver_opts),
+            &mut context_loader().unwrap(),
+        )
+        .await;
+
+        if !res.errors.is_empty() {
+            panic!("{}", res.errors.join(", "));
+        };
+
+        // Make sure it fails correctly, using the signature with another credential's claims:
+        let other = BasicPostAttestationProof {
+            statement: BasicPostAttestationStatement {
+                title: "Goodbye".to_string(),
+                ..proof.statement.clone()
+            },
+            signature: "unchecked".to_string(),
+        };
+        let other_jwt = other
+            .to_content(&other.generate_statement().unwrap(), "unchecked")
+            .unwrap()
+            .jwt(iss.clone())
+            .await
+            .unwrap();
+        let parts: Vec<&str> = jwt.split('.').collect();
+        let other_parts: Vec<&str> = other_jwt.split('.').collect();
+        let forged = format!("{}.{}.{}", parts[0], other_parts[1], parts[2]);
+        let res = verify_jwt(
+            &forged,
+            Some(ldpo),
+            &make_resolver(&iss.resolver_opts),
+            &mut context_loader().unwrap(),
+        )
+        .await;
+
+        if res.errors.is_empty() {
+            panic!("Approved JWT with claims the signature is not over");
+        };
+    }
+
+    #[tokio::test]
+    async fn test_secp256k1_pkh_credential_issuance() {
+        let (_, iss) = test_secp256k1_keypair().unwrap();
+        issue_and_verify(iss).await;
+    }
+
+    #[tokio::test]
+    async fn test_secp256k1_did_key_credential_issuance() {
+        let iss = test_secp256k1_did_key().await.unwrap();
+        issue_and_verify(iss).await;
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     content::attestation::basic_profile_attestation::BasicProfileAttestationContent,
     statement::attestation::basic_profile_attestation::BasicProfileAttestationStatement,
     types::{
-        defs::{Proof, Statement, Subject},
+        defs::{Proof, Statement, Subject, TypedData},
         error::StatementError,
     },
 };
@@ -22,6 +22,10 @@ impl Statement for BasicProfileAttestationProof {
     fn generate_statement(&self) -> Result<String, StatementError> {
         self.statement.generate_statement()
     }
+
+    fn generate_typed_data(&self) -> Result<TypedData, StatementError> {
+        self.statement.generate_typed_data()
+    }
 }
 
 impl Proof<BasicProfileAttestationContent> for BasicProfileAttestationProof {
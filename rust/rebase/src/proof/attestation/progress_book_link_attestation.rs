@@ -2,7 +2,7 @@ use crate::{
     content::attestation::progress_book_link_attestation::ProgressBookLinkAttestationContent,
     statement::attestation::progress_book_link_attestation::ProgressBookLinkAttestationStatement,
     types::{
-        defs::{Proof, Statement, Subject},
+        defs::{Proof, Statement, Subject, TypedData},
         error::{ProofError, StatementError},
     },
 };
@@ -22,6 +22,10 @@ impl Statement for ProgressBookLinkAttestationProof {
     fn generate_statement(&self) -> Result<String, StatementError> {
         self.statement.generate_statement()
     }
+
+    fn generate_typed_data(&self) -> Result<TypedData, StatementError> {
+        self.statement.generate_typed_data()
+    }
 }
 
 impl Proof<ProgressBookLinkAttestationContent> for ProgressBookLinkAttestationProof {
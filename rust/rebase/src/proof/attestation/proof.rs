@@ -6,7 +6,7 @@ use crate::{
         follow_attestation::*, like_attestation::*, progress_book_link_attestation::*,
     },
     types::{
-        defs::{Proof, Statement},
+        defs::{Proof, Statement, TypedData},
         enums::subject::Subjects,
         error::{ProofError, StatementError},
     },
@@ -74,6 +74,20 @@ impl Statement for AttestationProof {
             AttestationProof::ProgressBookLinkAttestation(x) => x.generate_statement(),
         }
     }
+
+    fn generate_typed_data(&self) -> Result<TypedData, StatementError> {
+        match self {
+            AttestationProof::BasicImageAttestation(x) => x.generate_typed_data(),
+            AttestationProof::BasicPostAttestation(x) => x.generate_typed_data(),
+            AttestationProof::BasicProfileAttestation(x) => x.generate_typed_data(),
+            AttestationProof::BasicTagAttestation(x) => x.generate_typed_data(),
+            AttestationProof::BookReviewAttestation(x) => x.generate_typed_data(),
+            AttestationProof::DappPreferencesAttestation(x) => x.generate_typed_data(),
+            AttestationProof::FollowAttestation(x) => x.generate_typed_data(),
+            AttestationProof::LikeAttestation(x) => x.generate_typed_data(),
+            AttestationProof::ProgressBookLinkAttestation(x) => x.generate_typed_data(),
+        }
+    }
 }
 
 impl Proof<AttestationContent> for AttestationProof {
@@ -0,0 +1,41 @@
+use crate::{
+    content::bluesky_verification::BlueskyVerificationContent as Ctnt,
+    statement::bluesky_verification::BlueskyVerificationStatement as Stmt,
+    types::{
+        defs::{Proof, Statement},
+        error::{ProofError, StatementError},
+    },
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct BlueskyVerificationProof {
+    pub statement: Stmt,
+    // The post the statement and signature were posted in, e.g.
+    // https://bsky.app/profile/alice.bsky.social/post/3k2a4xyz. If unset, they are looked for in
+    // the account's profile description instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_url: Option<String>,
+}
+
+impl Statement for BlueskyVerificationProof {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        self.statement.generate_statement()
+    }
+}
+
+impl Proof<Ctnt> for BlueskyVerificationProof {
+    fn to_content(&self, statement: &str, signature: &str) -> Result<Ctnt, ProofError> {
+        Ok(Ctnt {
+            handle: self.statement.handle.clone(),
+            subject: self.statement.subject.clone(),
+            post_url: self.post_url.clone(),
+            statement: statement.to_owned(),
+            signature: signature.to_owned(),
+        })
+    }
+}
@@ -12,24 +12,29 @@ use crate::{
         delegated_progress_book_link_attestation::DelegatedProgressBookLinkAttestationContent,
     },
     statement::attestation::statement::AttestationStatement,
-    subject::ethereum::Eip155,
+    subject::{ed25519::Ed25519Jwk, ethereum::Eip155, solana::Solana},
     types::{
-        capability::recap::from_action_string,
-        defs::{Proof, Statement, Subject},
+        capability::{
+            caip122::SignInMessage,
+            recap::{from_action_string, recap_cid, Caveats, DelegatedCapability},
+            revocation::{revocation_key, RevocationStore, RevocationTarget},
+        },
+        defs::{to_action, EthRpcOpts, Proof, ResolverOpts, Statement, Subject},
         enums::{
             attestation::AttestationTypes,
-            subject::{Pkh, Subjects},
+            subject::{Key, Pkh, Subjects},
         },
         error::{ProofError, StatementError},
     },
 };
-use base64::engine::general_purpose::STANDARD_NO_PAD as BASE64;
+use base64::engine::general_purpose::{STANDARD_NO_PAD as BASE64, URL_SAFE_NO_PAD as BASE64_URL};
 use base64::engine::Engine as _;
+use libipld::cid::Cid;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use siwe::{eip55, Message};
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
 use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
@@ -41,6 +46,16 @@ pub struct DelegatedAttestationProof {
     pub service_key: String,
     pub siwe_message: String,
     pub siwe_signature: String,
+    // The ReCaps siwe_message was delegated from, keyed by CID, when re-delegated.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parents: BTreeMap<String, SignedReCap>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SignedReCap {
+    pub message: String,
+    pub signature: String,
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
@@ -52,40 +67,117 @@ pub struct ParsedReCap {
     pub subject: Subjects,
     // the approved credential types to issue
     pub types: Vec<AttestationTypes>,
+    // the approved credential types with any caveats the delegator placed on them
+    #[serde(default)]
+    pub capabilities: Vec<DelegatedCapability>,
+    // the CIDs of the ReCaps this was delegated from
+    #[serde(default)]
+    pub parents: Vec<String>,
+    // the CID of the ReCap the delegation chain starts from, this ReCap unless verified
+    // with its parents by verify_siwe_recap
+    pub root: String,
 }
 
 pub const RECAP_PREFIX: &str = "urn:recap:";
 
+// The longest chain of re-delegations accepted.
+pub const MAX_RECAP_CHAIN: usize = 8;
+
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 struct HexRecap {
     pub att: Map<String, Value>,
-    // NOTE: Currently ignored.
-    // #[ts(type = "Array<any>")]
-    // pub prf: Vec<Value>,
+    #[serde(default)]
+    pub prf: Vec<String>,
+}
+
+impl ParsedReCap {
+    pub fn caveats(&self, attestation_type: &AttestationTypes) -> Caveats {
+        self.capabilities
+            .iter()
+            .find(|c| &c.attestation_type == attestation_type)
+            .map(|c| c.caveats.clone())
+            .unwrap_or_default()
+    }
+
+    // check_subject ensures the given subject is the delegator who signed the ReCap.
+    pub fn check_subject(&self, subject: &Subjects) -> Result<(), ProofError> {
+        let matches = match (&self.subject, subject) {
+            (Subjects::Pkh(Pkh::Eip155(x)), Subjects::Pkh(Pkh::Eip155(y))) => {
+                x.address == y.address
+            }
+            (x, y) => x.did()? == y.did()?,
+        };
+
+        if !matches {
+            return Err(ProofError::ContentGeneration(format!(
+                "Attestation subject is {} but ReCap signer is {}",
+                subject.did()?,
+                self.subject.did()?
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+// Builds the delegator from the account line of the sign in message. Ethereum accounts
+// are parsed by SIWE itself to keep its stricter checks, Solana accounts are base58
+// addresses and did:key accounts are ed25519 keys identified by their DID.
+fn recap_subject(siwe_recap: &str, m: &SignInMessage) -> Result<Subjects, ProofError> {
+    if m.address.starts_with("did:key:") {
+        let key_name = m.address.trim_start_matches("did:key:");
+        // z6Mk is the multibase prefix of ed25519 public keys.
+        if !key_name.starts_with("z6Mk") {
+            return Err(ProofError::ContentGeneration(format!(
+                "Only ed25519 did:key delegators are supported, got: {}",
+                m.address
+            )));
+        }
+        return Ok(Subjects::Key(Key::Ed25519(Ed25519Jwk::new(
+            &m.address, key_name,
+        )?)));
+    }
+
+    match m.namespace.as_str() {
+        "Ethereum" => {
+            let m = Message::from_str(siwe_recap).map_err(|e| {
+                ProofError::ContentGeneration(format!("Failed to parse ReCap into Message: {}", e))
+            })?;
+            Ok(Subjects::Pkh(Pkh::Eip155(Eip155 {
+                address: eip55(&m.address),
+                chain_id: format!("{}", m.chain_id),
+            })))
+        }
+        "Solana" => Ok(Subjects::Pkh(Pkh::Solana(Solana {
+            address: m.address.clone(),
+        }))),
+        n => Err(ProofError::ContentGeneration(format!(
+            "Unsupported ReCap account type: {}",
+            n
+        ))),
+    }
 }
 
 pub fn parse_siwe_recap(siwe_recap: &str, service_key: &str) -> Result<ParsedReCap, ProofError> {
-    let m = Message::from_str(siwe_recap).map_err(|e| {
+    let m = SignInMessage::from_str(siwe_recap).map_err(|e| {
         ProofError::ContentGeneration(format!("Failed to parse ReCap into Message: {}", e))
     })?;
 
-    let delegate = m.uri.to_string();
-
-    let address = eip55(&m.address);
-    let subject = Subjects::Pkh(Pkh::Eip155(Eip155 {
-        address,
-        chain_id: format!("{}", m.chain_id),
-    }));
+    let delegate = m.uri.clone();
+    let subject = recap_subject(siwe_recap, &m)?;
 
     let mut r: Option<HexRecap> = None;
     let mut counter = 0;
 
     for s in m.resources {
-        let s = s.to_string();
         if s.starts_with(RECAP_PREFIX) {
             let s = s.trim_start_matches(RECAP_PREFIX);
-            if let Ok(s) = BASE64.decode(s.as_bytes()) {
+            // ReCaps are URL safe base64, but older messages used the standard alphabet.
+            let decoded = BASE64_URL
+                .decode(s.as_bytes())
+                .or_else(|_| BASE64.decode(s.as_bytes()));
+            if let Ok(s) = decoded {
                 if let Ok(s) = String::from_utf8(s) {
                     if let Ok(parsed) = serde_json::from_str::<HexRecap>(&s) {
                         for (k, _) in parsed.att.clone() {
@@ -116,10 +208,23 @@ pub fn parse_siwe_recap(siwe_recap: &str, service_key: &str) -> Result<ParsedReC
                         ))
                     })?;
 
-                // TODO: Use _ to check for caveats, if those are implemented.
-                for (k, _) in attestation_map {
+                let mut capabilities: Vec<DelegatedCapability> = Vec::new();
+                for (k, nb) in attestation_map {
                     if let Some(t) = from_action_string(&k) {
-                        types.push(t);
+                        let caveats = Caveats::from_nota_bene(&nb).map_err(|e| {
+                            ProofError::ContentGeneration(format!(
+                                "Invalid caveats for {}: {}",
+                                k, e
+                            ))
+                        })?;
+                        caveats
+                            .validate_for(&t)
+                            .map_err(|e| ProofError::ContentGeneration(e.to_string()))?;
+                        types.push(t.clone());
+                        capabilities.push(DelegatedCapability {
+                            attestation_type: t,
+                            caveats,
+                        });
                     }
                 }
 
@@ -132,6 +237,9 @@ pub fn parse_siwe_recap(siwe_recap: &str, service_key: &str) -> Result<ParsedReC
                         delegate,
                         subject,
                         types,
+                        capabilities,
+                        parents: hex_recap.prf,
+                        root: recap_cid(siwe_recap).to_string(),
                     })
                 }
             } else {
@@ -147,6 +255,174 @@ pub fn parse_siwe_recap(siwe_recap: &str, service_key: &str) -> Result<ParsedReC
     }
 }
 
+fn recap_err(m: &str) -> ProofError {
+    ProofError::ContentGeneration(m.to_string())
+}
+
+// verify_recap checks a single ReCap's signature, validity period and revocation status.
+async fn verify_recap(
+    recap: &ParsedReCap,
+    message: &str,
+    signature: &str,
+    resolver_opts: &Option<ResolverOpts>,
+    eth_rpc: &Option<EthRpcOpts>,
+    revocations: Option<&dyn RevocationStore>,
+) -> Result<SignInMessage, ProofError> {
+    // did:key delegators are resolved the same way as the delegate.
+    recap
+        .subject
+        .valid_signature_with_opts(message, signature, resolver_opts, eth_rpc)
+        .await?;
+
+    let m = SignInMessage::from_str(message)
+        .map_err(|e| recap_err(&format!("Failed to parse ReCap into Message: {}", e)))?;
+
+    // Use the same timing checks as SIWE to determine if the ReCap is valid
+    if !m.valid_now().map_err(|e| recap_err(&e.to_string()))? {
+        return Err(recap_err("Capability is not valid at this time"));
+    }
+
+    if let Some(store) = revocations {
+        let mut targets = vec![RevocationTarget::Delegate(recap.delegate.clone())];
+        if let Some(n) = &m.nonce {
+            targets.push(RevocationTarget::Nonce(n.clone()));
+        }
+
+        for t in targets {
+            let key = revocation_key(&recap.subject, &t).map_err(|e| recap_err(&e.to_string()))?;
+            if store
+                .is_revoked(&key)
+                .await
+                .map_err(|e| recap_err(&e.to_string()))?
+            {
+                return Err(recap_err("Capability has been revoked"));
+            }
+        }
+    }
+
+    Ok(m)
+}
+
+// verify_siwe_recap parses the ReCap and checks its signature and validity period. If it was
+// re-delegated, each parent in the chain is found in parents by CID and verified in turn: the
+// child must be signed by the parent's delegate, may only narrow the parent's actions and
+// caveats, and may not outlive it. Every ReCap in the chain is checked against revocations
+// when given. The result's subject is the delegator at the root of the chain, on whose behalf
+// credentials are issued.
+pub async fn verify_siwe_recap(
+    siwe_recap: &str,
+    signature: &str,
+    service_key: &str,
+    parents: &BTreeMap<String, SignedReCap>,
+    resolver_opts: &Option<ResolverOpts>,
+    eth_rpc: &Option<EthRpcOpts>,
+    revocations: Option<&dyn RevocationStore>,
+) -> Result<ParsedReCap, ProofError> {
+    let mut bundle: BTreeMap<Cid, &SignedReCap> = BTreeMap::new();
+    for (k, v) in parents {
+        let cid =
+            Cid::from_str(k).map_err(|e| recap_err(&format!("Invalid parent CID {}: {}", k, e)))?;
+        if cid != recap_cid(&v.message) {
+            return Err(recap_err(&format!(
+                "Parent ReCap does not match its CID {}",
+                k
+            )));
+        }
+        bundle.insert(cid, v);
+    }
+
+    let leaf = parse_siwe_recap(siwe_recap, service_key)?;
+    let mut child_message = verify_recap(
+        &leaf,
+        siwe_recap,
+        signature,
+        resolver_opts,
+        eth_rpc,
+        revocations,
+    )
+    .await?;
+    let mut child = leaf.clone();
+    let mut depth = 0;
+
+    while !child.parents.is_empty() {
+        if child.parents.len() > 1 {
+            return Err(recap_err("Only one parent per ReCap is supported"));
+        }
+
+        depth += 1;
+        if depth > MAX_RECAP_CHAIN {
+            return Err(recap_err(&format!(
+                "ReCap chain is longer than {} delegations",
+                MAX_RECAP_CHAIN
+            )));
+        }
+
+        let cid = Cid::from_str(&child.parents[0])
+            .map_err(|e| recap_err(&format!("Invalid parent CID {}: {}", child.parents[0], e)))?;
+        let signed = bundle
+            .get(&cid)
+            .ok_or_else(|| recap_err(&format!("Missing parent ReCap {}", child.parents[0])))?;
+
+        let parent = parse_siwe_recap(&signed.message, service_key)?;
+        let parent_message = verify_recap(
+            &parent,
+            &signed.message,
+            &signed.signature,
+            resolver_opts,
+            eth_rpc,
+            revocations,
+        )
+        .await?;
+
+        // The child must be signed by the key the parent delegated to.
+        let parent_delegate = parent.delegate.split('#').next().unwrap_or_default();
+        if child.subject.did()? != parent_delegate {
+            return Err(recap_err(&format!(
+                "ReCap signer {} is not the delegate {} of its parent",
+                child.subject.did()?,
+                parent_delegate
+            )));
+        }
+
+        // The child may only narrow what the parent granted.
+        for c in &child.capabilities {
+            let p = parent
+                .capabilities
+                .iter()
+                .find(|p| p.attestation_type == c.attestation_type)
+                .ok_or_else(|| {
+                    recap_err(&format!(
+                        "ReCap grants {} which its parent does not",
+                        to_action(&c.attestation_type)
+                    ))
+                })?;
+
+            if !c.caveats.attenuates(&p.caveats) {
+                return Err(recap_err(&format!(
+                    "ReCap loosens its parent's caveats on {}",
+                    to_action(&c.attestation_type)
+                )));
+            }
+        }
+
+        if !child_message
+            .expires_within(&parent_message)
+            .map_err(|e| recap_err(&e.to_string()))?
+        {
+            return Err(recap_err("ReCap expires after its parent"));
+        }
+
+        child = parent;
+        child_message = parent_message;
+    }
+
+    Ok(ParsedReCap {
+        subject: child.subject,
+        root: child.root,
+        ..leaf
+    })
+}
+
 impl Statement for DelegatedAttestationProof {
     fn generate_statement(&self) -> Result<String, StatementError> {
         self.attestation.generate_statement()
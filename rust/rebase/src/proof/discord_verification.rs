@@ -0,0 +1,43 @@
+use crate::{
+    content::discord_verification::DiscordVerificationContent as Ctnt,
+    statement::discord_verification::DiscordVerificationStatement as Stmt,
+    types::{
+        defs::{Proof, Statement},
+        error::{ProofError, StatementError},
+    },
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct DiscordVerificationProof {
+    pub statement: Stmt,
+    // The channel the statement and signature were posted in, which must be one the witness
+    // watches, and the ID of the message.
+    pub channel_id: String,
+    pub message_id: String,
+}
+
+impl Statement for DiscordVerificationProof {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        self.statement.generate_statement()
+    }
+}
+
+impl Proof<Ctnt> for DiscordVerificationProof {
+    fn to_content(&self, statement: &str, signature: &str) -> Result<Ctnt, ProofError> {
+        Ok(Ctnt {
+            username: self.statement.username.clone(),
+            user_id: self.statement.user_id.clone(),
+            subject: self.statement.subject.clone(),
+            channel_id: self.channel_id.clone(),
+            message_id: self.message_id.clone(),
+            timestamp: None,
+            statement: statement.to_owned(),
+            signature: signature.to_owned(),
+        })
+    }
+}
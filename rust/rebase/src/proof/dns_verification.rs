@@ -8,6 +8,7 @@ impl Proof<Ctnt> for Stmt {
     fn to_content(&self, _statement: &str, _signature: &str) -> Result<Ctnt, ProofError> {
         Ok(Ctnt {
             domain: self.domain.clone(),
+            prefix: self.prefix.clone(),
             subject: self.subject.clone(),
         })
     }
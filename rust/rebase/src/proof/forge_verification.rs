@@ -0,0 +1,40 @@
+use crate::{
+    content::forge_verification::ForgeVerificationContent as Ctnt,
+    statement::forge_verification::ForgeVerificationStatement as Stmt,
+    types::{
+        defs::{Proof, Statement},
+        error::{ProofError, StatementError},
+    },
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ForgeVerificationProof {
+    // On GitLab, the ID of the snippet. On Gitea and Forgejo, which don't have snippets, the
+    // path of a file in one of the account's repositories, e.g. rebase/proof.txt
+    pub snippet_id: String,
+    pub statement: Stmt,
+}
+
+impl Statement for ForgeVerificationProof {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        self.statement.generate_statement()
+    }
+}
+
+impl Proof<Ctnt> for ForgeVerificationProof {
+    fn to_content(&self, statement: &str, signature: &str) -> Result<Ctnt, ProofError> {
+        Ok(Ctnt {
+            snippet_id: self.snippet_id.clone(),
+            handle: self.statement.handle.clone(),
+            domain: self.statement.domain.clone(),
+            subject: self.statement.subject.clone(),
+            statement: statement.to_owned(),
+            signature: signature.to_owned(),
+        })
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{
+    content::mastodon_verification::MastodonVerificationContent as Ctnt,
+    statement::mastodon_verification::MastodonVerificationStatement as Stmt,
+    types::{
+        defs::{Proof, Statement},
+        error::{ProofError, StatementError},
+    },
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MastodonVerificationProof {
+    pub statement: Stmt,
+    // The status the statement and signature were posted in. If unset, they are looked for in
+    // the account's bio and profile fields instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_url: Option<String>,
+}
+
+impl Statement for MastodonVerificationProof {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        self.statement.generate_statement()
+    }
+}
+
+impl Proof<Ctnt> for MastodonVerificationProof {
+    fn to_content(&self, statement: &str, signature: &str) -> Result<Ctnt, ProofError> {
+        Ok(Ctnt {
+            handle: self.statement.handle.clone(),
+            domain: self.statement.domain.clone(),
+            subject: self.statement.subject.clone(),
+            status_url: self.status_url.clone(),
+            statement: statement.to_owned(),
+            signature: signature.to_owned(),
+        })
+    }
+}
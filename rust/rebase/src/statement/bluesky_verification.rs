@@ -0,0 +1,28 @@
+use crate::types::{
+    defs::{Statement, Subject},
+    enums::subject::Subjects,
+    error::StatementError,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct BlueskyVerificationStatement {
+    // The account's handle without the @, e.g. alice.bsky.social
+    pub handle: String,
+    pub subject: Subjects,
+}
+
+impl Statement for BlueskyVerificationStatement {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        Ok(format!(
+            "I am attesting that this Bluesky handle {} is linked to the {} {}",
+            self.handle,
+            self.subject.statement_title()?,
+            self.subject.display_id()?
+        ))
+    }
+}
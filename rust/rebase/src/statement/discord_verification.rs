@@ -0,0 +1,31 @@
+use crate::types::{
+    defs::{Statement, Subject},
+    enums::subject::Subjects,
+    error::StatementError,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct DiscordVerificationStatement {
+    // The account's username, or username#discriminator for accounts that still have one.
+    pub username: String,
+    // If set, the message must also have been posted by the account with this user ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub subject: Subjects,
+}
+
+impl Statement for DiscordVerificationStatement {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        Ok(format!(
+            "I am attesting that this Discord account {} is linked to the {} {}",
+            self.username,
+            self.subject.statement_title()?,
+            self.subject.display_id()?
+        ))
+    }
+}
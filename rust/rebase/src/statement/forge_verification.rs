@@ -0,0 +1,30 @@
+use crate::types::{
+    defs::{Statement, Subject},
+    enums::subject::Subjects,
+    error::StatementError,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ForgeVerificationStatement {
+    pub handle: String,
+    // The domain of the forge the account is on, e.g. gitlab.com or codeberg.org
+    pub domain: String,
+    pub subject: Subjects,
+}
+
+impl Statement for ForgeVerificationStatement {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        Ok(format!(
+            "I am attesting that this {} handle {} is linked to the {} {}",
+            self.domain,
+            self.handle,
+            self.subject.statement_title()?,
+            self.subject.display_id()?
+        ))
+    }
+}
@@ -0,0 +1,38 @@
+use crate::types::{
+    defs::{Statement, Subject},
+    enums::subject::Subjects,
+    error::StatementError,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MastodonVerificationStatement {
+    // The account's username, e.g. alice for alice@mastodon.social
+    pub handle: String,
+    // The domain of the account's instance, e.g. mastodon.social
+    pub domain: String,
+    pub subject: Subjects,
+}
+
+impl MastodonVerificationStatement {
+    // acct is the account's WebFinger address, e.g. alice@mastodon.social
+    pub fn acct(&self) -> String {
+        format!("{}@{}", self.handle, self.domain)
+    }
+}
+
+impl Statement for MastodonVerificationStatement {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        // The handle isn't prefixed with @ so instances don't render it as a mention.
+        Ok(format!(
+            "I am attesting that this Mastodon account {} is linked to the {} {}",
+            self.acct(),
+            self.subject.statement_title()?,
+            self.subject.display_id()?
+        ))
+    }
+}
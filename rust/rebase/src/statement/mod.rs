@@ -1,7 +1,11 @@
 pub mod attestation;
+pub mod bluesky_verification;
+pub mod discord_verification;
 pub mod dns_verification;
 pub mod email_verification;
+pub mod forge_verification;
 pub mod github_verification;
+pub mod mastodon_verification;
 pub mod nft_ownership_verification;
 pub mod poap_ownership_verification;
 pub mod reddit_verification;
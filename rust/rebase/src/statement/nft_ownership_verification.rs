@@ -28,7 +28,7 @@ impl Statement for NftOwnershipVerificationStatement {
             self.subject.statement_title()?,
             self.subject.display_id()?,
             self.contract_address,
-            self.network.to_string(),
+            self.network,
             self.issued_at,
         ))
     }
@@ -0,0 +1,536 @@
+use crate::types::{defs::*, error::SubjectError};
+use async_trait::async_trait;
+use base58::ToBase58;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use bech32::{FromBase32, Variant};
+use k256::{
+    ecdsa::{
+        recoverable::{Id, Signature as RecoverableSig},
+        signature::{hazmat::PrehashVerifier, Signature as _},
+        Signature, VerifyingKey,
+    },
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use ripemd::Ripemd160;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+// The first 32 hex characters of the genesis block hash, as used by CAIP-2 for bip122 chains.
+pub const BITCOIN_MAINNET: &str = "000000000019d6689c085ae165831e93";
+pub const BITCOIN_TESTNET: &str = "000000000933ea01ad0ee984209779ba";
+
+const MESSAGE_PREFIX: &[u8] = b"\x18Bitcoin Signed Message:\n";
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+
+struct Network {
+    chain_id: &'static str,
+    p2pkh: u8,
+    p2sh: u8,
+    hrp: &'static str,
+}
+
+const MAINNET: Network = Network {
+    chain_id: BITCOIN_MAINNET,
+    p2pkh: 0x00,
+    p2sh: 0x05,
+    hrp: "bc",
+};
+
+const TESTNET: Network = Network {
+    chain_id: BITCOIN_TESTNET,
+    p2pkh: 0x6f,
+    p2sh: 0xc4,
+    hrp: "tb",
+};
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Bip122 {
+    // A P2PKH (1... / m... / n...), P2SH-P2WPKH (3... / 2...) or P2WPKH (bc1q... / tb1q...)
+    // address. The chain is determined by the address.
+    pub address: String,
+}
+
+impl Bip122 {
+    fn network(&self) -> Result<&'static Network, SubjectError> {
+        let a = self.address.to_lowercase();
+        if a.starts_with("bc1") || self.address.starts_with('1') || self.address.starts_with('3') {
+            Ok(&MAINNET)
+        } else if a.starts_with("tb1")
+            || self.address.starts_with('m')
+            || self.address.starts_with('n')
+            || self.address.starts_with('2')
+        {
+            Ok(&TESTNET)
+        } else {
+            Err(SubjectError::Validation(format!(
+                "unrecognized bitcoin address: {}",
+                self.address
+            )))
+        }
+    }
+
+    // Returns true if the given public key controls the address under any of the
+    // single-key address types.
+    fn matches_key(&self, key: &VerifyingKey, compressed: bool) -> Result<bool, SubjectError> {
+        let network = self.network()?;
+        let encoded = key.to_encoded_point(compressed);
+        let pkh = hash160(encoded.as_bytes());
+
+        if base58check(network.p2pkh, &pkh) == self.address {
+            return Ok(true);
+        }
+
+        // Segwit outputs require compressed keys.
+        if !compressed {
+            return Ok(false);
+        }
+
+        let mut redeem_script = vec![0x00, 0x14];
+        redeem_script.extend_from_slice(&pkh);
+        if base58check(network.p2sh, &hash160(&redeem_script)) == self.address {
+            return Ok(true);
+        }
+
+        Ok(self.witness_program()? == Some(pkh.to_vec()))
+    }
+
+    // Returns the version 0 witness program for bech32 addresses.
+    fn witness_program(&self) -> Result<Option<Vec<u8>>, SubjectError> {
+        let network = self.network()?;
+        if !self.address.to_lowercase().starts_with(network.hrp) {
+            return Ok(None);
+        }
+
+        let (hrp, data, variant) = bech32::decode(&self.address)
+            .map_err(|e| SubjectError::Validation(format!("invalid bech32 address: {}", e)))?;
+
+        if hrp != network.hrp || data.is_empty() {
+            return Err(SubjectError::Validation(format!(
+                "invalid bech32 address: {}",
+                self.address
+            )));
+        }
+
+        if data[0].to_u8() != 0 || variant != Variant::Bech32 {
+            return Err(SubjectError::Validation(
+                "only version 0 witness programs are supported".to_string(),
+            ));
+        }
+
+        Ok(Some(Vec::<u8>::from_base32(&data[1..]).map_err(|e| {
+            SubjectError::Validation(format!("invalid bech32 address: {}", e))
+        })?))
+    }
+
+    // BIP-137 (and the legacy Bitcoin Core format it extends): a 65 byte compact signature
+    // whose header encodes the recovery id and the address type. Wallets disagree on the
+    // header used for segwit addresses, so every address type derivable from the recovered
+    // key is accepted.
+    fn valid_bip137(&self, statement: &str, sig: &[u8]) -> Result<(), SubjectError> {
+        let header = sig[0];
+        if !(27..=42).contains(&header) {
+            return Err(SubjectError::Validation(format!(
+                "invalid signature header: {}",
+                header
+            )));
+        }
+
+        let compressed = header >= 31;
+        let id = Id::new((header - 27) % 4)
+            .map_err(|e| SubjectError::Validation(format!("invalid recovery id: {}", e)))?;
+
+        let key = RecoverableSig::new(
+            &Signature::from_bytes(&sig[1..])
+                .map_err(|e| SubjectError::Validation(format!("invalid signature: {}", e)))?,
+            id,
+        )
+        .map_err(|e| SubjectError::Validation(format!("invalid signature: {}", e)))?
+        .recover_verifying_key_from_digest_bytes(&signed_message_hash(statement).into())
+        .map_err(|e| SubjectError::Validation(format!("could not recover key: {}", e)))?;
+
+        if !self.matches_key(&key, compressed)? {
+            return Err(SubjectError::Validation(
+                "signature was not made by the address".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // BIP-322 "simple": the signature is the consensus encoded witness stack of the
+    // virtual to_sign transaction. Only P2WPKH addresses are supported.
+    fn valid_bip322(&self, statement: &str, witness: &[u8]) -> Result<(), SubjectError> {
+        let program = self.witness_program()?.ok_or_else(|| {
+            SubjectError::Validation(
+                "BIP-322 signatures are only supported for P2WPKH addresses".to_string(),
+            )
+        })?;
+
+        if program.len() != 20 {
+            return Err(SubjectError::Validation(
+                "BIP-322 signatures are only supported for P2WPKH addresses".to_string(),
+            ));
+        }
+
+        let stack = decode_witness(witness)?;
+        if stack.len() != 2 {
+            return Err(SubjectError::Validation(
+                "expected a witness of a signature and a public key".to_string(),
+            ));
+        }
+
+        let (der, sighash_type) = match stack[0].split_last() {
+            Some((t, der)) => (der, *t),
+            None => {
+                return Err(SubjectError::Validation(
+                    "witness signature was empty".to_string(),
+                ))
+            }
+        };
+
+        if sighash_type != 0x01 {
+            return Err(SubjectError::Validation(
+                "only SIGHASH_ALL signatures are supported".to_string(),
+            ));
+        }
+
+        if hash160(&stack[1]).to_vec() != program {
+            return Err(SubjectError::Validation(
+                "witness public key does not match the address".to_string(),
+            ));
+        }
+
+        let key = VerifyingKey::from_sec1_bytes(&stack[1])
+            .map_err(|e| SubjectError::Validation(format!("invalid public key: {}", e)))?;
+
+        let sig = Signature::from_der(der)
+            .map_err(|e| SubjectError::Validation(format!("invalid signature: {}", e)))?;
+
+        key.verify_prehash(&bip322_sighash(statement, &program), &sig)
+            .map_err(|e| SubjectError::Validation(e.to_string()))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Subject for Bip122 {
+    fn did(&self) -> Result<String, SubjectError> {
+        Ok(format!(
+            "did:pkh:bip122:{}:{}",
+            self.network()?.chain_id,
+            self.address
+        ))
+    }
+
+    fn display_id(&self) -> Result<String, SubjectError> {
+        Ok(self.address.clone())
+    }
+
+    fn verification_method(&self) -> Result<String, SubjectError> {
+        Ok(format!("{}#blockchainAccountId", self.did()?))
+    }
+
+    // Signatures are base64 encoded, as returned by wallets' message signing.
+    async fn valid_signature(&self, statement: &str, signature: &str) -> Result<(), SubjectError> {
+        let sig = BASE64
+            .decode(signature)
+            .map_err(|e| SubjectError::Validation(format!("failed to decode base64: {}", e)))?;
+
+        if sig.len() == 65 {
+            self.valid_bip137(statement, &sig)
+        } else {
+            self.valid_bip322(statement, &sig)
+        }
+    }
+}
+
+fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+fn base58check(version: u8, payload: &[u8]) -> String {
+    let mut b = vec![version];
+    b.extend_from_slice(payload);
+    let checksum = sha256d(&b);
+    b.extend_from_slice(&checksum[..4]);
+    b.to_base58()
+}
+
+fn write_varint(buf: &mut Vec<u8>, n: usize) {
+    match n {
+        0..=0xfc => buf.push(n as u8),
+        0xfd..=0xffff => {
+            buf.push(0xfd);
+            buf.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        _ => {
+            buf.push(0xfe);
+            buf.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<usize, SubjectError> {
+    let err = || SubjectError::Validation("malformed witness".to_string());
+    let first = *data.get(*pos).ok_or_else(err)?;
+    *pos += 1;
+    let width = match first {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        n => return Ok(n as usize),
+    };
+
+    let end = pos.checked_add(width).ok_or_else(err)?;
+    let bytes = data.get(*pos..end).ok_or_else(err)?;
+    *pos = end;
+    let mut n = [0u8; 8];
+    n[..width].copy_from_slice(bytes);
+    usize::try_from(u64::from_le_bytes(n)).map_err(|_| err())
+}
+
+fn decode_witness(data: &[u8]) -> Result<Vec<Vec<u8>>, SubjectError> {
+    let err = || SubjectError::Validation("malformed witness".to_string());
+    let mut pos = 0;
+    let count = read_varint(data, &mut pos)?;
+    let mut stack = Vec::new();
+    for _ in 0..count {
+        let len = read_varint(data, &mut pos)?;
+        let end = pos.checked_add(len).ok_or_else(err)?;
+        stack.push(data.get(pos..end).ok_or_else(err)?.to_vec());
+        pos = end;
+    }
+
+    if pos != data.len() {
+        return Err(err());
+    }
+
+    Ok(stack)
+}
+
+fn signed_message_hash(statement: &str) -> [u8; 32] {
+    let mut b = MESSAGE_PREFIX.to_vec();
+    write_varint(&mut b, statement.len());
+    b.extend_from_slice(statement.as_bytes());
+    sha256d(&b)
+}
+
+// Computes the BIP-143 SIGHASH_ALL digest of the BIP-322 to_sign transaction for a P2WPKH
+// address with the given 20 byte witness program.
+fn bip322_sighash(statement: &str, program: &[u8]) -> [u8; 32] {
+    let tag = Sha256::digest(BIP322_TAG);
+    let message_hash = Sha256::new()
+        .chain_update(tag)
+        .chain_update(tag)
+        .chain_update(statement.as_bytes())
+        .finalize();
+
+    let mut script_pubkey = vec![0x00, 0x14];
+    script_pubkey.extend_from_slice(program);
+
+    // to_spend, serialized without witness to produce its txid.
+    let mut to_spend = Vec::new();
+    to_spend.extend_from_slice(&0u32.to_le_bytes());
+    to_spend.push(1);
+    to_spend.extend_from_slice(&[0u8; 32]);
+    to_spend.extend_from_slice(&0xffffffffu32.to_le_bytes());
+    to_spend.push(34);
+    to_spend.extend_from_slice(&[0x00, 0x20]);
+    to_spend.extend_from_slice(&message_hash);
+    to_spend.extend_from_slice(&0u32.to_le_bytes());
+    to_spend.push(1);
+    to_spend.extend_from_slice(&0u64.to_le_bytes());
+    write_varint(&mut to_spend, script_pubkey.len());
+    to_spend.extend_from_slice(&script_pubkey);
+    to_spend.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut outpoint = sha256d(&to_spend).to_vec();
+    outpoint.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut script_code = vec![0x19, 0x76, 0xa9, 0x14];
+    script_code.extend_from_slice(program);
+    script_code.extend_from_slice(&[0x88, 0xac]);
+
+    // to_sign has a single OP_RETURN output of 0 value.
+    let mut output = 0u64.to_le_bytes().to_vec();
+    output.extend_from_slice(&[0x01, 0x6a]);
+
+    let mut preimage = Vec::new();
+    preimage.extend_from_slice(&0u32.to_le_bytes());
+    preimage.extend_from_slice(&sha256d(&outpoint));
+    preimage.extend_from_slice(&sha256d(&0u32.to_le_bytes()));
+    preimage.extend_from_slice(&outpoint);
+    preimage.extend_from_slice(&script_code);
+    preimage.extend_from_slice(&0u64.to_le_bytes());
+    preimage.extend_from_slice(&0u32.to_le_bytes());
+    preimage.extend_from_slice(&sha256d(&output));
+    preimage.extend_from_slice(&0u32.to_le_bytes());
+    preimage.extend_from_slice(&1u32.to_le_bytes());
+
+    sha256d(&preimage)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use k256::ecdsa::{signature::hazmat::PrehashSigner, SigningKey};
+
+    const STATEMENT: &str = "I am attesting that this bitcoin address is mine";
+
+    fn test_key(b: u8) -> SigningKey {
+        SigningKey::from_bytes(&[b; 32]).unwrap()
+    }
+
+    fn p2wpkh(key: &SigningKey) -> Bip122 {
+        let pkh = hash160(key.verifying_key().to_encoded_point(true).as_bytes());
+        let mut data = vec![bech32::u5::try_from_u8(0).unwrap()];
+        data.extend(bech32::ToBase32::to_base32(&pkh));
+        Bip122 {
+            address: bech32::encode("bc", data, Variant::Bech32).unwrap(),
+        }
+    }
+
+    fn bip137_sign(key: &SigningKey, statement: &str, header_base: u8) -> String {
+        let sig: RecoverableSig = key.sign_prehash(&signed_message_hash(statement)).unwrap();
+        let mut b = vec![header_base + u8::from(sig.recovery_id())];
+        b.extend_from_slice(&sig.as_ref()[..64]);
+        BASE64.encode(b)
+    }
+
+    fn bip322_sign(key: &SigningKey, statement: &str, program: &[u8]) -> String {
+        let sig: Signature = key
+            .sign_prehash(&bip322_sighash(statement, program))
+            .unwrap();
+        let mut der = sig.to_der().as_bytes().to_vec();
+        der.push(0x01);
+        let pk = key.verifying_key().to_encoded_point(true);
+
+        let mut w = vec![2];
+        write_varint(&mut w, der.len());
+        w.extend_from_slice(&der);
+        write_varint(&mut w, pk.len());
+        w.extend_from_slice(pk.as_bytes());
+        BASE64.encode(w)
+    }
+
+    #[tokio::test]
+    async fn test_bip322_vectors() {
+        let subject = Bip122 {
+            address: "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l".to_string(),
+        };
+        assert_eq!(
+            subject.did().unwrap(),
+            "did:pkh:bip122:000000000019d6689c085ae165831e93:bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l"
+        );
+
+        subject
+            .valid_signature("Hello World", "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=")
+            .await
+            .unwrap();
+        subject
+            .valid_signature("", "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=")
+            .await
+            .unwrap();
+
+        if subject
+            .valid_signature("Hello World", "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=")
+            .await
+            .is_ok()
+        {
+            panic!("Said invalid signature was valid");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bip322() {
+        let key = test_key(1);
+        let subject = p2wpkh(&key);
+        let program = subject.witness_program().unwrap().unwrap();
+
+        let sig = bip322_sign(&key, STATEMENT, &program);
+        subject.valid_signature(STATEMENT, &sig).await.unwrap();
+
+        let other = p2wpkh(&test_key(2));
+        if other.valid_signature(STATEMENT, &sig).await.is_ok() {
+            panic!("Invalid signature permitted");
+        }
+    }
+
+    #[test]
+    fn test_malformed_witness() {
+        // One stack item whose declared length overflows the read offset.
+        let mut data = vec![0x01, 0xff];
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(decode_witness(&data).is_err());
+
+        // A declared length past the end of the data.
+        assert!(decode_witness(&[0x01, 0x05, 0x00]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bip137() {
+        let key = test_key(1);
+        let encoded = key.verifying_key().to_encoded_point(true);
+        let pkh = hash160(encoded.as_bytes());
+        let mut redeem_script = vec![0x00, 0x14];
+        redeem_script.extend_from_slice(&pkh);
+
+        let subjects = [
+            (
+                Bip122 {
+                    address: base58check(0x00, &pkh),
+                },
+                31,
+            ),
+            (
+                Bip122 {
+                    address: base58check(0x05, &hash160(&redeem_script)),
+                },
+                35,
+            ),
+            (p2wpkh(&key), 39),
+            (
+                Bip122 {
+                    address: base58check(0x6f, &pkh),
+                },
+                31,
+            ),
+        ];
+
+        for (subject, header) in subjects {
+            let sig = bip137_sign(&key, STATEMENT, header);
+            subject.valid_signature(STATEMENT, &sig).await.unwrap();
+
+            if subject
+                .valid_signature("something else", &sig)
+                .await
+                .is_ok()
+            {
+                panic!("Said invalid signature was valid");
+            }
+
+            let sig = bip137_sign(&test_key(2), STATEMENT, header);
+            if subject.valid_signature(STATEMENT, &sig).await.is_ok() {
+                panic!("Invalid signature permitted");
+            }
+        }
+
+        // Uncompressed keys only control legacy addresses.
+        let uncompressed = Bip122 {
+            address: base58check(
+                0x00,
+                &hash160(key.verifying_key().to_encoded_point(false).as_bytes()),
+            ),
+        };
+        let sig = bip137_sign(&key, STATEMENT, 27);
+        uncompressed.valid_signature(STATEMENT, &sig).await.unwrap();
+    }
+}
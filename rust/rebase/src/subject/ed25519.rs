@@ -1,5 +1,5 @@
 use crate::types::{
-    defs::{resolve_key, DIDKey, DIDWeb, Subject, JWK},
+    defs::{make_resolver, resolve_key, DIDResolver, Subject, JWK},
     error::SubjectError,
 };
 use async_trait::async_trait;
@@ -8,6 +8,13 @@ use hex;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use ssi::jwk::Params;
+use ssi_dids::{
+    did_resolve::{
+        ERROR_INVALID_DID, ERROR_INVALID_DID_URL, ERROR_METHOD_NOT_SUPPORTED, ERROR_NOT_FOUND,
+        ERROR_REPRESENTATION_NOT_SUPPORTED,
+    },
+    Error as DIDError,
+};
 use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
@@ -27,30 +34,67 @@ impl Ed25519Jwk {
     }
 
     pub async fn jwk(&self) -> Result<JWK, SubjectError> {
-        if !self.did.starts_with("did:web:") && !self.did.starts_with("did:key:") {
-            return Err(SubjectError::Did(format!(
-                "Currently only supports ed25519 keys as did:web or did:key, got: {}",
-                self.did
-            )));
-        }
+        self.jwk_with_resolver(&make_resolver(&None)).await
+    }
 
+    pub async fn jwk_with_resolver(&self, resolver: &dyn DIDResolver) -> Result<JWK, SubjectError> {
         let full_did = format!("{}#{}", self.did, self.key_name);
-        if self.did.starts_with("did:key:") {
-            let resolver = DIDKey {};
-            resolve_key(&full_did, &resolver).await.map_err(|e| {
-                SubjectError::Validation(format!("Could not build JWK from DID: {}", e))
-            })
-        } else if self.did.starts_with("did:web:") {
-            let resolver = DIDWeb {};
-            resolve_key(&full_did, &resolver).await.map_err(|e| {
-                SubjectError::Validation(format!("Could not build JWK from DID: {}", e))
-            })
-        } else {
-            Err(SubjectError::Validation(format!(
-                "Delegate DID must be of did:web or did:key, got {}",
-                self.did
-            )))
+        resolve_key(&full_did, resolver).await.map_err(key_error)
+    }
+
+    // valid_signature_with_resolver is valid_signature with the DID resolved by the given
+    // resolver, e.g. one built by make_resolver from the witness's ResolverOpts.
+    pub async fn valid_signature_with_resolver(
+        &self,
+        statement: &str,
+        signature: &str,
+        resolver: &dyn DIDResolver,
+    ) -> Result<(), SubjectError> {
+        let jwk = self.jwk_with_resolver(resolver).await?;
+        let pk = match &jwk.params {
+            Params::OKP(o) => Ok(PublicKey::from_bytes(&o.public_key.0).map_err(|e| {
+                SubjectError::Validation(format!("could not generate public key: {}", e))
+            })?),
+            _ => Err(SubjectError::Validation(
+                "could not recover public key from jwk".to_string(),
+            )),
+        }?;
+
+        let statement = statement.as_bytes();
+        let signature = Signature::from_bytes(
+            &hex::decode(signature).map_err(|e| SubjectError::Validation(e.to_string()))?,
+        )
+        .map_err(|e| SubjectError::Validation(e.to_string()))?;
+
+        pk.verify(statement, &signature)
+            .map_err(|e| SubjectError::Validation(e.to_string()))
+    }
+}
+
+// key_error tells a resolver that couldn't be reached apart from a DID or key that doesn't
+// resolve, which is up to the caller to fix. Resolvers report outages in their own words,
+// so anything but the errors DID resolution defines for bad input is taken for one.
+fn key_error(e: DIDError) -> SubjectError {
+    let invalid = match &e {
+        DIDError::DIDURLDereference(m) => {
+            [
+                ERROR_INVALID_DID,
+                ERROR_INVALID_DID_URL,
+                ERROR_METHOD_NOT_SUPPORTED,
+                ERROR_NOT_FOUND,
+                ERROR_REPRESENTATION_NOT_SUPPORTED,
+            ]
+            .contains(&m.as_str())
+                // The document has no such key.
+                || m.starts_with("Unable to find object in DID document")
         }
+        _ => true,
+    };
+
+    if invalid {
+        SubjectError::Validation(format!("Could not build JWK from DID: {}", e))
+    } else {
+        SubjectError::Upstream(format!("Could not resolve DID: {}", e))
     }
 }
 
@@ -71,24 +115,8 @@ impl Subject for Ed25519Jwk {
     }
 
     async fn valid_signature(&self, statement: &str, signature: &str) -> Result<(), SubjectError> {
-        let jwk = self.jwk().await?;
-        let pk = match &jwk.params {
-            Params::OKP(o) => Ok(PublicKey::from_bytes(&o.public_key.0).map_err(|e| {
-                SubjectError::Validation(format!("could not generate public key: {}", e))
-            })?),
-            _ => Err(SubjectError::Validation(
-                "could not recover public key from jwk".to_string(),
-            )),
-        }?;
-
-        let statement = statement.as_bytes();
-        let signature = Signature::from_bytes(
-            &hex::decode(signature).map_err(|e| SubjectError::Validation(e.to_string()))?,
-        )
-        .map_err(|e| SubjectError::Validation(e.to_string()))?;
-
-        pk.verify(statement, &signature)
-            .map_err(|e| SubjectError::Validation(e.to_string()))
+        self.valid_signature_with_resolver(statement, signature, &make_resolver(&None))
+            .await
     }
 }
 
@@ -148,3 +176,102 @@ pub enum Context {
 // NOTE: Tests for this file can be found in issuer/ed25519
 // There the function test_did_kepair from test_util is tested as
 // both issuer and subject.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        issuer::ed25519::Ed25519Jwk as Issuer,
+        types::{
+            defs::{Issuer as _, ResolverOpts},
+            enums::subject::{Subjects, Web},
+        },
+    };
+    use serde_json::json;
+
+    fn static_did(did: &str, jwk: &JWK) -> ResolverOpts {
+        let doc = json!([{
+            "@context": "https://www.w3.org/ns/did/v1",
+            "id": did,
+            "verificationMethod": [{
+                "id": format!("{}#controller", did),
+                "type": "Ed25519VerificationKey2018",
+                "controller": did,
+                "publicKeyJwk": jwk.to_public(),
+            }],
+            "assertionMethod": [format!("{}#controller", did)],
+        }]);
+
+        let mut opts = ResolverOpts::default();
+        opts.add_static_documents(&doc.to_string()).unwrap();
+        opts
+    }
+
+    #[tokio::test]
+    async fn test_static_documents() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let did = "did:web:static.rebase.test";
+        let issuer = Issuer::new(did, &serde_json::to_string(&jwk).unwrap(), "controller").unwrap();
+        let subject = Ed25519Jwk::new(did, "controller").unwrap();
+        let sig = issuer.sign("hello world").await.unwrap();
+
+        let resolver = make_resolver(&Some(static_did(did, &jwk)));
+        subject
+            .valid_signature_with_resolver("hello world", &sig, &resolver)
+            .await
+            .unwrap();
+
+        if subject
+            .valid_signature_with_resolver("goodbye world", &sig, &resolver)
+            .await
+            .is_ok()
+        {
+            panic!("Approved bad signature");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_errors() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let did = "did:web:errors.rebase.test";
+        let resolver = make_resolver(&Some(static_did(did, &jwk)));
+
+        // A key the DID doesn't have, or a malformed DID, is the caller's mistake.
+        for (did, key_name) in [(did, "missing"), ("not-a-did", "controller")] {
+            match Ed25519Jwk::new(did, key_name)
+                .unwrap()
+                .jwk_with_resolver(&resolver)
+                .await
+            {
+                Err(SubjectError::Validation(_)) => {}
+                r => panic!("Expected a validation error for {}, got {:?}", did, r),
+            }
+        }
+
+        // Nothing listens here, so the DID can't be resolved.
+        match Ed25519Jwk::new("did:web:127.0.0.1%3A9", "controller")
+            .unwrap()
+            .jwk_with_resolver(&resolver)
+            .await
+        {
+            Err(SubjectError::Upstream(_)) => {}
+            r => panic!("Expected an upstream error, got {:?}", r),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signature_with_opts() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let did = "did:web:opts.rebase.test";
+        let issuer = Issuer::new(did, &serde_json::to_string(&jwk).unwrap(), "controller").unwrap();
+        let subject = Subjects::Web(Web::Ed25519(Ed25519Jwk::new(did, "controller").unwrap()));
+        let sig = issuer.sign("hello world").await.unwrap();
+
+        let mut opts = static_did(did, &jwk);
+        opts.cache_ttl_seconds = Some(60);
+        subject
+            .valid_signature_with_opts("hello world", &sig, &Some(opts), &None)
+            .await
+            .unwrap();
+    }
+}
@@ -1,4 +1,7 @@
-use crate::types::{defs::Subject, error::*};
+use crate::types::{
+    defs::{send_request, EthRpcOpts, Subject, TypedData},
+    error::*,
+};
 use async_trait::async_trait;
 use ed25519_dalek::ed25519::signature::Signature as Ed25519Sig;
 use hex::FromHex;
@@ -9,12 +12,22 @@ use k256::{
     },
     elliptic_curve::sec1::ToEncodedPoint,
 };
+use reqwest::Client;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use sha3::{Digest, Keccak256};
 use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
+// isValidSignature(bytes32,bytes)
+const EIP1271_SELECTOR: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+// Suffix marking an EIP-6492 wrapped signature.
+const EIP6492_SUFFIX: [u8; 32] = [
+    0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92,
+    0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92,
+];
+
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct Eip155 {
@@ -22,35 +35,100 @@ pub struct Eip155 {
     pub chain_id: String,
 }
 
-#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
-#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
-impl Subject for Eip155 {
-    fn did(&self) -> Result<String, SubjectError> {
-        Ok(format!("did:pkh:eip155:{}:{}", self.chain_id, self.address))
+impl Eip155 {
+    // valid_signature_with_rpc checks an EIP-191 signature, falling back to EIP-1271 and
+    // EIP-6492 contract wallet verification when an RPC endpoint is configured for the chain.
+    pub async fn valid_signature_with_rpc(
+        &self,
+        statement: &str,
+        signature: &str,
+        opts: &Option<EthRpcOpts>,
+    ) -> Result<(), SubjectError> {
+        self.valid_digests(&[eip191_hash(statement)], signature, opts)
+            .await
     }
 
-    fn display_id(&self) -> Result<String, SubjectError> {
-        Ok(self.address.clone())
-    }
+    // valid_typed_data_signature_with_rpc checks an eth_signTypedData_v4 signature, with the
+    // same contract wallet fallbacks as valid_signature_with_rpc. The typed data's domain
+    // must be bound to the subject's chain.
+    pub async fn valid_typed_data_signature_with_rpc(
+        &self,
+        typed_data: &TypedData,
+        signature: &str,
+        opts: &Option<EthRpcOpts>,
+    ) -> Result<(), SubjectError> {
+        if typed_data_chain_id(typed_data).as_deref() != Some(self.chain_id.as_str()) {
+            return Err(SubjectError::Validation(format!(
+                "typed data is not bound to chain {}",
+                self.chain_id
+            )));
+        }
 
-    fn verification_method(&self) -> Result<String, SubjectError> {
-        Ok(format!("{}#blockchainAccountId", self.did()?))
+        self.valid_digests(&[typed_data_hash(typed_data)?], signature, opts)
+            .await
     }
 
-    async fn valid_signature(&self, statement: &str, signature: &str) -> Result<(), SubjectError> {
-        // NOTE: THIS ASSUMES EIP191 SIGNING.
-        // TODO: Call this out in the type system?
-        let statement: Vec<u8> = format!(
-            "\x19Ethereum Signed Message:\n{}{}",
-            statement.as_bytes().len(),
-            statement
-        )
-        .into();
-
-        let signature = <[u8; 65]>::from_hex(signature.trim_start_matches("0x")).map_err(|e| {
+    // valid_digests succeeds if the signature is valid for any of the given message digests.
+    async fn valid_digests(
+        &self,
+        digests: &[[u8; 32]],
+        signature: &str,
+        opts: &Option<EthRpcOpts>,
+    ) -> Result<(), SubjectError> {
+        let sig = hex::decode(signature.trim_start_matches("0x")).map_err(|e| {
             SubjectError::Validation(format!("could not marshal signature to hex: {}", e))
         })?;
 
+        let rpc = opts
+            .as_ref()
+            .and_then(|o| o.rpc_urls.get(&self.chain_id).map(|u| (o, u.as_str())));
+
+        let mut err = SubjectError::Validation("signature mismatch".to_string());
+
+        if let Some(wrapped) = sig.strip_suffix(&EIP6492_SUFFIX) {
+            let (opts, url) = rpc.ok_or_else(|| {
+                SubjectError::Validation(format!(
+                    "no rpc url configured to verify EIP-6492 signatures on chain {}",
+                    self.chain_id
+                ))
+            })?;
+            for digest in digests {
+                match self.valid_eip6492(opts, url, digest, &sig, wrapped).await {
+                    Ok(()) => return Ok(()),
+                    Err(e) => err = e,
+                }
+            }
+            return Err(err);
+        }
+
+        for (i, digest) in digests.iter().enumerate() {
+            match self.ecrecover(digest, &sig) {
+                Ok(()) => return Ok(()),
+                // Report why the first, most likely, form failed.
+                Err(e) if i == 0 => err = e,
+                Err(_) => {}
+            }
+        }
+
+        if let Some((opts, url)) = rpc {
+            for digest in digests {
+                match self.valid_eip1271(opts, url, digest, &sig).await {
+                    Ok(()) => return Ok(()),
+                    Err(e) => err = e,
+                }
+            }
+        }
+
+        Err(err)
+    }
+
+    fn ecrecover(&self, digest: &[u8; 32], signature: &[u8]) -> Result<(), SubjectError> {
+        let signature = <[u8; 65]>::try_from(signature).map_err(|_| {
+            SubjectError::Validation(
+                "could not marshal signature to hex: Invalid string length".to_string(),
+            )
+        })?;
+
         let pk = EcdsaSig::new(
             &Sig::from_bytes(&signature[..64]).map_err(|e| {
                 SubjectError::Validation(format!(
@@ -66,7 +144,7 @@ impl Subject for Eip155 {
             })?,
         )
         .map_err(|e| SubjectError::Validation(format!("could not recover key: {}", e)))?
-        .recover_verifying_key(&statement)
+        .recover_verifying_key_from_digest_bytes(digest.into())
         .map_err(|e| {
             SubjectError::Validation(format!("could not process statement to recover key: {}", e))
         })?;
@@ -85,14 +163,492 @@ impl Subject for Eip155 {
             Ok(())
         }
     }
+
+    async fn valid_eip1271(
+        &self,
+        opts: &EthRpcOpts,
+        url: &str,
+        hash: &[u8; 32],
+        signature: &[u8],
+    ) -> Result<(), SubjectError> {
+        let mut data = EIP1271_SELECTOR.to_vec();
+        data.extend_from_slice(hash);
+        data.extend_from_slice(&abi_word(64));
+        data.extend_from_slice(&abi_bytes(signature));
+
+        let res = eth_call(opts, url, Some(&self.address), &data).await?;
+        if res.len() < 4 || res[..4] != EIP1271_SELECTOR {
+            return Err(SubjectError::Validation(
+                "contract wallet rejected the signature".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // EIP-6492 signatures are abi.encode(factory, factoryCalldata, signature) plus the magic
+    // suffix. Deployed wallets are checked with EIP-1271 against the inner signature, while
+    // undeployed wallets need the ValidateSigOffchain contract to simulate deployment.
+    async fn valid_eip6492(
+        &self,
+        opts: &EthRpcOpts,
+        url: &str,
+        hash: &[u8; 32],
+        signature: &[u8],
+        wrapped: &[u8],
+    ) -> Result<(), SubjectError> {
+        let inner = abi_decode_bytes(wrapped, 2)?;
+
+        let code = rpc(opts, url, "eth_getCode", json!([self.address, "latest"])).await?;
+        if code.as_str().map(|c| c != "0x").unwrap_or(false) {
+            return self.valid_eip1271(opts, url, hash, &inner).await;
+        }
+
+        let validator = opts.eip6492_validator.as_ref().ok_or_else(|| {
+            SubjectError::Validation(
+                "eip6492_validator must be configured to verify undeployed wallet signatures"
+                    .to_string(),
+            )
+        })?;
+
+        let mut data = hex::decode(validator.trim_start_matches("0x")).map_err(|e| {
+            SubjectError::Validation(format!("could not decode eip6492_validator: {}", e))
+        })?;
+        let address = <[u8; 20]>::from_hex(self.address.trim_start_matches("0x")).map_err(|e| {
+            SubjectError::Validation(format!("could not marshal address to hex: {}", e))
+        })?;
+        let mut addr_word = [0u8; 32];
+        addr_word[12..].copy_from_slice(&address);
+        data.extend_from_slice(&addr_word);
+        data.extend_from_slice(hash);
+        data.extend_from_slice(&abi_word(96));
+        data.extend_from_slice(&abi_bytes(signature));
+
+        let res = eth_call(opts, url, None, &data).await?;
+        match res.split_last() {
+            Some((1, rest)) if rest.iter().all(|b| *b == 0) => Ok(()),
+            _ => Err(SubjectError::Validation(
+                "contract wallet rejected the signature".to_string(),
+            )),
+        }
+    }
+}
+
+fn eip191_hash(statement: &str) -> [u8; 32] {
+    Keccak256::digest(
+        format!(
+            "\x19Ethereum Signed Message:\n{}{}",
+            statement.len(),
+            statement
+        )
+        .as_bytes(),
+    )
+    .into()
+}
+
+// typed_data_chain_id returns the chainId typed data's domain declares, if any.
+fn typed_data_chain_id(typed_data: &TypedData) -> Option<String> {
+    let v = serde_json::to_value(typed_data).ok()?;
+    v["types"]["EIP712Domain"]
+        .as_array()?
+        .iter()
+        .find(|m| m["name"] == "chainId")?;
+    match &v["domain"]["chainId"] {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn typed_data_hash(typed_data: &TypedData) -> Result<[u8; 32], SubjectError> {
+    typed_data
+        .hash()
+        .map_err(|e| SubjectError::Validation(format!("could not hash typed data: {}", e)))?
+        .try_into()
+        .map_err(|_| SubjectError::Validation("could not hash typed data".to_string()))
+}
+
+fn abi_word(n: usize) -> [u8; 32] {
+    let mut w = [0u8; 32];
+    w[24..].copy_from_slice(&(n as u64).to_be_bytes());
+    w
+}
+
+// abi_bytes encodes the tail of a dynamic bytes value: its length then the padded data.
+fn abi_bytes(b: &[u8]) -> Vec<u8> {
+    let mut v = abi_word(b.len()).to_vec();
+    v.extend_from_slice(b);
+    v.resize(32 + b.len().div_ceil(32) * 32, 0);
+    v
+}
+
+// abi_decode_bytes returns the dynamic bytes value at the given head position of an abi
+// encoded tuple.
+fn abi_decode_bytes(data: &[u8], index: usize) -> Result<Vec<u8>, SubjectError> {
+    let err = || SubjectError::Validation("malformed EIP-6492 signature".to_string());
+    let read_word = |at: usize| -> Result<usize, SubjectError> {
+        let w = data
+            .get(at..at.checked_add(32).ok_or_else(err)?)
+            .ok_or_else(err)?;
+        if w[..24].iter().any(|b| *b != 0) {
+            return Err(err());
+        }
+        usize::try_from(u64::from_be_bytes(w[24..].try_into().map_err(|_| err())?))
+            .map_err(|_| err())
+    };
+
+    let offset = read_word(index.checked_mul(32).ok_or_else(err)?)?;
+    let len = read_word(offset)?;
+    let start = offset.checked_add(32).ok_or_else(err)?;
+    let end = start.checked_add(len).ok_or_else(err)?;
+    Ok(data.get(start..end).ok_or_else(err)?.to_vec())
+}
+
+async fn rpc(
+    opts: &EthRpcOpts,
+    url: &str,
+    method: &str,
+    params: Value,
+) -> Result<Value, SubjectError> {
+    let req = Client::new().post(url).json(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    }));
+
+    let res: Value = send_request(&opts.http_opts, req)
+        .await
+        .map_err(|e| SubjectError::Upstream(format!("rpc request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| SubjectError::Upstream(format!("rpc response was not json: {}", e)))?;
+
+    if let Some(e) = res.get("error") {
+        // A reverted call is the contract refusing the signature, where other errors are the
+        // endpoint's.
+        if e.get("code").and_then(|c| c.as_i64()) == Some(3) {
+            return Err(SubjectError::Validation(format!(
+                "rpc call reverted: {}",
+                e
+            )));
+        }
+        return Err(SubjectError::Upstream(format!("rpc returned error: {}", e)));
+    }
+
+    res.get("result")
+        .cloned()
+        .ok_or_else(|| SubjectError::Upstream("rpc response had no result".to_string()))
+}
+
+async fn eth_call(
+    opts: &EthRpcOpts,
+    url: &str,
+    to: Option<&str>,
+    data: &[u8],
+) -> Result<Vec<u8>, SubjectError> {
+    let mut call = json!({ "data": format!("0x{}", hex::encode(data)) });
+    if let Some(to) = to {
+        call["to"] = json!(to);
+    }
+
+    let res = rpc(opts, url, "eth_call", json!([call, "latest"])).await?;
+    let res = res
+        .as_str()
+        .ok_or_else(|| SubjectError::Validation("eth_call result was not a string".to_string()))?;
+
+    hex::decode(res.trim_start_matches("0x"))
+        .map_err(|e| SubjectError::Validation(format!("could not decode eth_call result: {}", e)))
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Subject for Eip155 {
+    fn did(&self) -> Result<String, SubjectError> {
+        Ok(format!("did:pkh:eip155:{}:{}", self.chain_id, self.address))
+    }
+
+    fn display_id(&self) -> Result<String, SubjectError> {
+        Ok(self.address.clone())
+    }
+
+    fn verification_method(&self) -> Result<String, SubjectError> {
+        Ok(format!("{}#blockchainAccountId", self.did()?))
+    }
+
+    async fn valid_signature(&self, statement: &str, signature: &str) -> Result<(), SubjectError> {
+        self.valid_signature_with_rpc(statement, signature, &None)
+            .await
+    }
+
+    async fn valid_typed_data_signature(
+        &self,
+        typed_data: &TypedData,
+        signature: &str,
+    ) -> Result<(), SubjectError> {
+        self.valid_typed_data_signature_with_rpc(typed_data, signature, &None)
+            .await
+    }
 }
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::test_util::util::{
-        test_eth_did, test_eth_did_2, test_witness_signature, test_witness_statement, TestKey,
-        TestWitness,
+        test_eth_did, test_eth_did_2, test_secp256k1_keypair, test_sign_typed_data,
+        test_witness_signature, test_witness_statement, StubTransport, TestKey, TestWitness,
     };
+    use crate::types::{
+        defs::{statement_typed_data, with_chain_id, HttpOpts, HttpTransport},
+        enums::subject::{Pkh, Subjects},
+    };
+    use k256::ecdsa::{signature::hazmat::PrehashSigner, SigningKey, VerifyingKey};
+    use reqwest::{Request, Response};
+    use std::{collections::BTreeMap, sync::Arc};
+
+    const WALLET: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    const RPC: &str = "http://rpc.test/";
+    const VALIDATOR: &str = "0xdead";
+
+    // WalletTransport stands in for a JSON-RPC node with a single owner contract wallet
+    // deployed (or not) at WALLET.
+    #[derive(Debug)]
+    struct WalletTransport {
+        owner: VerifyingKey,
+        deployed: bool,
+    }
+
+    impl WalletTransport {
+        fn owner_signed(&self, hash: &[u8], sig: &[u8]) -> bool {
+            EcdsaSig::try_from(sig)
+                .and_then(|s| s.recover_verifying_key_from_digest_bytes(hash.into()))
+                .map(|k| k == self.owner)
+                .unwrap_or(false)
+        }
+
+        fn eth_call(&self, call: &Value) -> Result<Vec<u8>, SubjectError> {
+            let data =
+                hex::decode(call["data"].as_str().unwrap().trim_start_matches("0x")).unwrap();
+            if call.get("to").is_some() {
+                if !self.deployed || data[..4] != EIP1271_SELECTOR {
+                    return Ok(vec![]);
+                }
+                let sig = abi_decode_bytes(&data[4..], 1)?;
+                let mut res = [0u8; 32];
+                if self.owner_signed(&data[4..36], &sig) {
+                    res[..4].copy_from_slice(&EIP1271_SELECTOR);
+                }
+                return Ok(res.to_vec());
+            }
+
+            // Deployless ValidateSigOffchain call: creation code, then (signer, hash, signature).
+            let args = &data[hex::decode(&VALIDATOR[2..]).unwrap().len()..];
+            let sig = abi_decode_bytes(args, 2)?;
+            let inner = abi_decode_bytes(sig.strip_suffix(&EIP6492_SUFFIX).unwrap(), 2)?;
+            Ok(vec![u8::from(self.owner_signed(&args[32..64], &inner))])
+        }
+    }
+
+    #[async_trait]
+    impl HttpTransport for WalletTransport {
+        async fn execute(&self, request: Request) -> Result<Response, FlowError> {
+            let req: Value =
+                serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+            let result = match req["method"].as_str().unwrap() {
+                "eth_getCode" if self.deployed => json!("0x6080"),
+                "eth_getCode" => json!("0x"),
+                "eth_call" => json!(format!(
+                    "0x{}",
+                    hex::encode(
+                        self.eth_call(&req["params"][0])
+                            .map_err(|e| FlowError::BadLookup(e.to_string()))?
+                    )
+                )),
+                m => return Err(FlowError::BadLookup(format!("unexpected method {}", m))),
+            };
+
+            let res = http::Response::builder()
+                .status(200)
+                .body(json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string())
+                .map_err(|e| FlowError::BadLookup(e.to_string()))?;
+            Ok(Response::from(res))
+        }
+    }
+
+    fn wallet_opts(owner: &SigningKey, deployed: bool) -> Option<EthRpcOpts> {
+        Some(EthRpcOpts {
+            rpc_urls: BTreeMap::from([("1".to_string(), RPC.to_string())]),
+            eip6492_validator: Some(VALIDATOR.to_string()),
+            http_opts: Some(HttpOpts {
+                base_url: None,
+                transport: Some(Arc::new(WalletTransport {
+                    owner: owner.verifying_key(),
+                    deployed,
+                })),
+            }),
+        })
+    }
+
+    fn owner_sign(key: &SigningKey, statement: &str) -> Vec<u8> {
+        let sig: EcdsaSig = key.sign_prehash(&eip191_hash(statement)).unwrap();
+        sig.as_ref().to_vec()
+    }
+
+    fn wrap_eip6492(sig: &[u8]) -> String {
+        let factory_calldata = [0xab_u8; 36];
+        let mut b = [0u8; 32].to_vec();
+        b.extend_from_slice(&abi_word(96));
+        b.extend_from_slice(&abi_word(96 + abi_bytes(&factory_calldata).len()));
+        b.extend_from_slice(&abi_bytes(&factory_calldata));
+        b.extend_from_slice(&abi_bytes(sig));
+        b.extend_from_slice(&EIP6492_SUFFIX);
+        format!("0x{}", hex::encode(b))
+    }
+
+    #[tokio::test]
+    async fn test_typed_data() {
+        let (subject, issuer) = test_secp256k1_keypair().unwrap();
+        let (other, _) = test_secp256k1_keypair().unwrap();
+        let statement = "I am attesting that this ethereum address is mine";
+
+        let unbound = statement_typed_data(statement).unwrap();
+        let bound = with_chain_id(unbound.clone(), "1").unwrap();
+        let sig = test_sign_typed_data(&issuer.jwk, &bound).unwrap();
+        subject
+            .valid_typed_data_signature(&bound, &sig)
+            .await
+            .unwrap();
+
+        // A typed data signature is not a signature of the plain-text statement.
+        if subject.valid_signature(statement, &sig).await.is_ok() {
+            panic!("Typed data signature accepted for the plain-text statement");
+        }
+
+        if other.valid_typed_data_signature(&bound, &sig).await.is_ok() {
+            panic!("Invalid signature permitted");
+        }
+
+        // Typed data must be bound to the subject's chain.
+        let sig = test_sign_typed_data(&issuer.jwk, &unbound).unwrap();
+        if subject
+            .valid_typed_data_signature(&unbound, &sig)
+            .await
+            .is_ok()
+        {
+            panic!("Typed data without a chain id accepted");
+        }
+
+        let other_chain = with_chain_id(unbound, "5").unwrap();
+        let sig = test_sign_typed_data(&issuer.jwk, &other_chain).unwrap();
+        if subject
+            .valid_typed_data_signature(&other_chain, &sig)
+            .await
+            .is_ok()
+        {
+            panic!("Typed data for another chain accepted");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_eip1271() {
+        let owner = SigningKey::from_bytes(&[1; 32]).unwrap();
+        let other = SigningKey::from_bytes(&[2; 32]).unwrap();
+        let subject = Eip155 {
+            address: WALLET.to_string(),
+            chain_id: "1".to_string(),
+        };
+        let statement = "I control this wallet";
+        let sig = hex::encode(owner_sign(&owner, statement));
+
+        subject
+            .valid_signature_with_rpc(statement, &sig, &wallet_opts(&owner, true))
+            .await
+            .unwrap();
+
+        if subject
+            .valid_signature_with_rpc(statement, &sig, &wallet_opts(&other, true))
+            .await
+            .is_ok()
+        {
+            panic!("Contract wallet accepted another owner's signature");
+        }
+
+        if subject.valid_signature(statement, &sig).await.is_ok() {
+            panic!("Contract wallet signature accepted without an rpc url");
+        }
+
+        // An endpoint that can't be reached doesn't make the signature invalid.
+        let unreachable = Some(EthRpcOpts {
+            http_opts: Some(HttpOpts {
+                base_url: None,
+                transport: Some(Arc::new(StubTransport { routes: vec![] })),
+            }),
+            ..wallet_opts(&owner, true).unwrap()
+        });
+        match subject
+            .valid_signature_with_rpc(statement, &sig, &unreachable)
+            .await
+        {
+            Err(SubjectError::Upstream(_)) => {}
+            res => panic!("Expected an upstream error, got {:?}", res),
+        }
+
+        // Flows pass their own options through Subjects.
+        Subjects::Pkh(Pkh::Eip155(subject))
+            .valid_signature_with_opts(statement, &sig, &None, &wallet_opts(&owner, true))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_eip6492() {
+        let owner = SigningKey::from_bytes(&[1; 32]).unwrap();
+        let other = SigningKey::from_bytes(&[2; 32]).unwrap();
+        let subject = Eip155 {
+            address: WALLET.to_string(),
+            chain_id: "1".to_string(),
+        };
+        let statement = "I control this wallet";
+        let sig = wrap_eip6492(&owner_sign(&owner, statement));
+
+        for deployed in [true, false] {
+            subject
+                .valid_signature_with_rpc(statement, &sig, &wallet_opts(&owner, deployed))
+                .await
+                .unwrap();
+
+            if subject
+                .valid_signature_with_rpc(statement, &sig, &wallet_opts(&other, deployed))
+                .await
+                .is_ok()
+            {
+                panic!("Contract wallet accepted another owner's signature");
+            }
+        }
+
+        let mut opts = wallet_opts(&owner, false);
+        if let Some(o) = opts.as_mut() {
+            o.eip6492_validator = None;
+        }
+        if subject
+            .valid_signature_with_rpc(statement, &sig, &opts)
+            .await
+            .is_ok()
+        {
+            panic!("Undeployed wallet signature accepted without a validator");
+        }
+    }
+
+    #[test]
+    fn test_malformed_eip6492() {
+        // An offset pointing past the end of the address space.
+        let data = abi_word(usize::MAX);
+        assert!(abi_decode_bytes(&data, 0).is_err());
+
+        // A length running past the end of the address space.
+        let mut data = abi_word(32).to_vec();
+        data.extend_from_slice(&abi_word(usize::MAX - 16));
+        assert!(abi_decode_bytes(&data, 0).is_err());
+    }
 
     #[tokio::test]
     async fn test_eth() {
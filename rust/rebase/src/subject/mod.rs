@@ -1,3 +1,5 @@
+pub mod bip122;
 pub mod ed25519;
 pub mod ethereum;
 pub mod solana;
+pub mod tezos;
@@ -0,0 +1,153 @@
+use crate::types::{defs::*, error::SubjectError};
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssi::{
+    jwk::{blakesig::hash_public_key, Algorithm},
+    jws::verify_bytes,
+    tzkey::{decode_tzsig, encode_tezos_signed_message, jwk_from_tezos_key},
+};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+// Tezos addresses are hashes of the public key, so the public key (edpk..., sppk... or p2pk...)
+// must be supplied alongside the address (tz1..., tz2... or tz3...) to check signatures.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Tezos {
+    pub address: String,
+    pub public_key: String,
+}
+
+impl Tezos {
+    fn jwk(&self) -> Result<JWK, SubjectError> {
+        let jwk = jwk_from_tezos_key(&self.public_key)
+            .map_err(|e| SubjectError::Validation(format!("failed to decode public key: {}", e)))?;
+
+        let address = hash_public_key(&jwk)
+            .map_err(|e| SubjectError::Validation(format!("failed to hash public key: {}", e)))?;
+
+        if address != self.address {
+            return Err(SubjectError::Validation(format!(
+                "public key belongs to {} not {}",
+                address, self.address
+            )));
+        }
+
+        Ok(jwk)
+    }
+
+    // Accepts either a base58 Tezos signature (edsig..., spsig1..., p2sig...) or the raw
+    // signature bytes as hex.
+    fn decode_signature(
+        &self,
+        jwk: &JWK,
+        signature: &str,
+    ) -> Result<(Algorithm, Vec<u8>), SubjectError> {
+        if signature.starts_with("edsig")
+            || signature.starts_with("spsig")
+            || signature.starts_with("p2sig")
+        {
+            return decode_tzsig(signature).map_err(|e| SubjectError::Validation(e.to_string()));
+        }
+
+        let alg = jwk.get_algorithm().ok_or_else(|| {
+            SubjectError::Validation("could not determine key algorithm".to_string())
+        })?;
+        let sig = hex::decode(signature.trim_start_matches("0x"))
+            .map_err(|e| SubjectError::Validation(e.to_string()))?;
+
+        Ok((alg, sig))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Subject for Tezos {
+    fn did(&self) -> Result<String, SubjectError> {
+        Ok(format!("did:pkh:tz:{}", self.address))
+    }
+
+    fn display_id(&self) -> Result<String, SubjectError> {
+        Ok(self.address.clone())
+    }
+
+    fn verification_method(&self) -> Result<String, SubjectError> {
+        Ok(format!("{}#blockchainAccountId", self.did()?))
+    }
+
+    async fn valid_signature(&self, statement: &str, signature: &str) -> Result<(), SubjectError> {
+        let jwk = self.jwk()?;
+        let (alg, sig) = self.decode_signature(&jwk, signature)?;
+
+        // Wallets either sign the Micheline packed "Tezos Signed Message: " payload or the
+        // statement bytes as given, so both are accepted.
+        let packed = encode_tezos_signed_message(statement)
+            .map_err(|e| SubjectError::Validation(e.to_string()))?;
+
+        if verify_bytes(alg, &packed, &jwk, &sig).is_ok() {
+            return Ok(());
+        }
+
+        verify_bytes(alg, statement.as_bytes(), &jwk, &sig)
+            .map_err(|e| SubjectError::Validation(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::util::test_tezos_keypair;
+    use ssi::tzkey::sign_tezos;
+
+    const STATEMENT: &str = "I am attesting that this tezos address is mine";
+
+    #[tokio::test]
+    async fn test_tezos() {
+        for alg in [
+            Algorithm::EdBlake2b,
+            Algorithm::ESBlake2bK,
+            Algorithm::ESBlake2b,
+        ] {
+            let (subject, jwk) = test_tezos_keypair(alg).unwrap();
+
+            let packed = encode_tezos_signed_message(STATEMENT).unwrap();
+            let sig = sign_tezos(&packed, alg, &jwk).unwrap();
+            subject.valid_signature(STATEMENT, &sig).await.unwrap();
+
+            let sig = sign_tezos(STATEMENT.as_bytes(), alg, &jwk).unwrap();
+            subject.valid_signature(STATEMENT, &sig).await.unwrap();
+
+            let (_, raw) = decode_tzsig(&sig).unwrap();
+            subject
+                .valid_signature(STATEMENT, &hex::encode(raw))
+                .await
+                .unwrap();
+
+            if subject
+                .valid_signature("something else", &sig)
+                .await
+                .is_ok()
+            {
+                panic!("Said invalid signature was valid");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tezos_bad_key() {
+        let (subject, _) = test_tezos_keypair(Algorithm::EdBlake2b).unwrap();
+        let (_, other) = test_tezos_keypair(Algorithm::EdBlake2b).unwrap();
+
+        let sig = sign_tezos(STATEMENT.as_bytes(), Algorithm::EdBlake2b, &other).unwrap();
+        if subject.valid_signature(STATEMENT, &sig).await.is_ok() {
+            panic!("Invalid signature permitted");
+        }
+
+        let mut mismatched = subject.clone();
+        mismatched.address = hash_public_key(&other).unwrap();
+        if mismatched.valid_signature(STATEMENT, &sig).await.is_ok() {
+            panic!("Public key for another address permitted");
+        }
+    }
+}
@@ -1,20 +1,42 @@
 use crate::{
-    issuer::ed25519::Ed25519Jwk,
-    subject::{ed25519::Ed25519Jwk as JwkSubj, ethereum::Eip155, solana::Solana},
+    issuer::{
+        ed25519::Ed25519Jwk,
+        remote::{LocalSigner, RemoteIssuer},
+        secp256k1::Secp256k1Jwk,
+    },
+    subject::{ed25519::Ed25519Jwk as JwkSubj, ethereum::Eip155, solana::Solana, tezos::Tezos},
     types::{
-        defs::{get_verification_method, DIDKey, DIDMethod, Issuer, Source, Subject, JWK},
+        defs::{
+            get_verification_method, DIDKey, DIDMethod, HttpTransport, Issuer, ResolverOpts,
+            Source, Subject, TypedData, JWK,
+        },
         enums::subject::{Key, Pkh, Subjects, Web},
         error::{FlowError, IssuerError, SubjectError},
     },
 };
 use async_trait::async_trait;
-use ssi::{ldp::Proof as LDProof, one_or_many::OneOrMany, vc::Credential};
+use base58::ToBase58;
+use reqwest::{Request, Response};
+use ssi::{
+    jwk::{blakesig::hash_public_key, eip155::hash_public_key_eip55, Algorithm, Params},
+    jws::sign_bytes,
+    ldp::Proof as LDProof,
+    one_or_many::OneOrMany,
+    tzkey::jwk_to_tezos_key,
+    vc::Credential,
+};
+use std::sync::Arc;
 
 pub const TEST_2KEY_ETH_SIG_1: &str = "0x56e48e0dbca9eebd31b23a69d56be84e8fa359d27e70e62c3999fbe2f43659845cee0d976ff83ed576e556cd8fbc377eeb4a0cb38f6949f9ac8ff6f8794b869f1b";
 pub const TEST_2KEY_ETH_SIG_2: &str = "0x4f5448421f13e597f20ccfbe31ba62ab16bacc6ec93654a1131f126005ffd4cc7688c9c74b492e91cb5c795f53351ee87a05dbe32b9e11dde9d6cf3771506a101c";
 
-pub const TEST_2KEY_ED25519_SIG_1: &str = "2660633d366856fa1da5032ddfab4e3b27e6958c99ce59b7d92dd5fbbd6fac46c916193de00cfb138ff0ba08f86669becab87d828f4673575e865ff98b165102";
-pub const TEST_2KEY_ED25519_SIG_2: &str = "dcfd87b8abeadfc896056ea51ee99fe728c9b518a305c37bee7fcf3948357ef34f1b2566a2fb31c37959873a0bf076bf30dd3c45457a67b9118047580febb908";
+pub const TEST_2KEY_ED25519_SIG_1: &str = "1772adb066bd7f398d7f2941a272eac1616d9ce0f5ef8cd2c070f5b6a63b37b13d974fd54e5b52abb6543eb3033a60db46454b34a9b974bd6cef8df08de0e50a";
+pub const TEST_2KEY_ED25519_SIG_2: &str = "e00bb3e7a38f35ec1116916e7ea9ef9bae29f9ee8c648815d0021d662b77f7d6d7621cb3bd2e6ad5b8e39896e81b83d99a1a1037af809de5f1cadc63db452a03";
+
+// The keys behind test_ed25519_did and test_ed25519_did_2. test_resolver_opts serves their
+// DID documents, so tests don't resolve them over the network.
+pub const TEST_ED25519_JWK: &str = r#"{"kty":"OKP","crv":"Ed25519","x":"0rXG0S91nbC7jktiqZXxMmsvNPD4sFAoFfy-n2hLdZc","d":"zFSHDrkv9Wi79jBNUlDJrATEDCIQUFxuHWcbVEZSMI0"}"#;
+pub const TEST_ED25519_JWK_2: &str = r#"{"kty":"OKP","crv":"Ed25519","x":"YAC-XU-wosNQs9wwNcqe25LCWv-Qu5HAyRakks4_pFI","d":"DY6vRv2lPLAnyxZ9m1j2GkR0Lp-UaN1qwjMnbCQSGlc"}"#;
 
 pub const TEST_2KEY_SOLANA_SIG_1: &str = "a9da65e22dd752af74b92729fa0de6ee3f9126c8c442fc4109ae614b07fa173d6edc4099666cde2c4f9413e07f5ddd3454fcfbce3ad97f4f784a7069e8137903";
 pub const TEST_2KEY_SOLANA_SIG_2: &str = "98406d0e97b8b9c7f9e4abf6069d1a11d20a6e4d1b71608862df915038ea6a60276a99aca5c306346936e101e7aef22205dc82486788578060946eac44ae6008";
@@ -73,6 +95,122 @@ pub async fn test_did_keypair() -> Result<(Subjects, Ed25519Jwk), String> {
     ))
 }
 
+// Signs typed data as eth_signTypedData_v4 would, returning the hex signature.
+pub fn test_sign_typed_data(jwk: &JWK, typed_data: &TypedData) -> Result<String, String> {
+    let bytes = typed_data.bytes().map_err(|e| e.to_string())?;
+    let mut sig = sign_bytes(Algorithm::ESKeccakKR, &bytes, jwk).map_err(|e| e.to_string())?;
+    sig[64] += 27;
+    Ok(format!("0x{}", hex::encode(sig)))
+}
+
+pub fn test_secp256k1_keypair() -> Result<(Subjects, Secp256k1Jwk), String> {
+    let jwk = JWK::generate_secp256k1().map_err(|e| e.to_string())?;
+    let address = hash_public_key_eip55(&jwk).map_err(|e| e.to_string())?;
+    let json_jwk =
+        serde_json::to_string(&jwk).map_err(|e| format!("Could not serialize JWK: {}", e))?;
+
+    Ok((
+        Subjects::Pkh(Pkh::Eip155(Eip155 {
+            address: address.clone(),
+            chain_id: "1".to_owned(),
+        })),
+        Secp256k1Jwk::new(
+            &format!("did:pkh:eip155:1:{}", address),
+            &json_jwk,
+            "blockchainAccountId",
+        )
+        .map_err(|e| e.to_string())?,
+    ))
+}
+
+pub async fn test_secp256k1_did_key() -> Result<Secp256k1Jwk, String> {
+    let jwk = JWK::generate_secp256k1().map_err(|e| e.to_string())?;
+    let dk = DIDKey {};
+
+    let d = dk
+        .generate(&Source::Key(&jwk))
+        .ok_or("DID Generation returned None".to_string())?;
+
+    let vm = get_verification_method(&d, &dk)
+        .await
+        .ok_or("Failed to generated verification method from DID".to_string())?;
+
+    let split_did: Vec<String> = vm.split('#').map(|s| s.to_string()).collect();
+    if split_did.len() != 2 {
+        return Err("DID was not in expected format".to_string());
+    }
+
+    let json_jwk =
+        serde_json::to_string(&jwk).map_err(|e| format!("Could not serialize JWK: {}", e))?;
+
+    Secp256k1Jwk::new(&split_did[0], &json_jwk, &split_did[1]).map_err(|e| e.to_string())
+}
+
+// Generates a Tezos subject and its private key, for EdBlake2b (tz1), ESBlake2bK (tz2) or
+// ESBlake2b (tz3).
+pub fn test_tezos_keypair(alg: Algorithm) -> Result<(Tezos, JWK), String> {
+    let mut jwk = match alg {
+        Algorithm::EdBlake2b => JWK::generate_ed25519(),
+        Algorithm::ESBlake2bK => JWK::generate_secp256k1(),
+        Algorithm::ESBlake2b => JWK::generate_p256(),
+        _ => return Err(format!("Unsupported Tezos algorithm: {:?}", alg)),
+    }
+    .map_err(|e| e.to_string())?;
+    jwk.algorithm = Some(alg);
+
+    Ok((
+        Tezos {
+            address: hash_public_key(&jwk).map_err(|e| e.to_string())?,
+            public_key: jwk_to_tezos_key(&jwk.to_public()).map_err(|e| e.to_string())?,
+        },
+        jwk,
+    ))
+}
+
+// Generates a Solana subject and its private key, sign statements with
+// sign_bytes(Algorithm::EdDSA, ..) and hex encode the result.
+pub fn test_solana_keypair() -> Result<(Subjects, JWK), String> {
+    let jwk = JWK::generate_ed25519().map_err(|e| e.to_string())?;
+    let address = match &jwk.params {
+        Params::OKP(o) => o.public_key.0.to_base58(),
+        _ => return Err("Expected an OKP key".to_string()),
+    };
+
+    Ok((Subjects::Pkh(Pkh::Solana(Solana { address })), jwk))
+}
+
+pub async fn test_remote_issuer() -> Result<(Subjects, RemoteIssuer), String> {
+    let jwk = JWK::generate_ed25519().map_err(|e| e.to_string())?;
+    let dk = DIDKey {};
+
+    let d = dk
+        .generate(&Source::Key(&jwk))
+        .ok_or("DID Generation returned None".to_string())?;
+
+    let vm = get_verification_method(&d, &dk)
+        .await
+        .ok_or("Failed to generated verification method from DID".to_string())?;
+
+    let split_did: Vec<String> = vm.split('#').map(|s| s.to_string()).collect();
+    if split_did.len() != 2 {
+        return Err("DID was not in expected format".to_string());
+    }
+
+    Ok((
+        Subjects::Key(Key::Ed25519(
+            JwkSubj::new(&split_did[0], &split_did[1]).map_err(|e| e.to_string())?,
+        )),
+        RemoteIssuer::new(
+            &split_did[0],
+            &split_did[1],
+            Arc::new(LocalSigner { jwk }),
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?,
+    ))
+}
+
 pub fn test_ed25519_did() -> Subjects {
     Subjects::Web(Web::Ed25519(JwkSubj {
         did: "did:web:rebasedemokey.pages.dev".to_string(),
@@ -80,6 +218,35 @@ pub fn test_ed25519_did() -> Subjects {
     }))
 }
 
+// test_resolver_opts resolves test_ed25519_did and test_ed25519_did_2 from static documents.
+pub fn test_resolver_opts() -> ResolverOpts {
+    let docs: Vec<serde_json::Value> = [
+        ("did:web:rebasedemokey.pages.dev", TEST_ED25519_JWK),
+        ("did:web:tzprofiles.com", TEST_ED25519_JWK_2),
+    ]
+    .iter()
+    .map(|(did, jwk)| {
+        let jwk: JWK = serde_json::from_str(jwk).unwrap();
+        serde_json::json!({
+            "@context": "https://www.w3.org/ns/did/v1",
+            "id": did,
+            "verificationMethod": [{
+                "id": format!("{}#controller", did),
+                "type": "Ed25519VerificationKey2018",
+                "controller": did,
+                "publicKeyJwk": jwk.to_public(),
+            }],
+            "assertionMethod": [format!("{}#controller", did)],
+        })
+    })
+    .collect();
+
+    let mut opts = ResolverOpts::default();
+    opts.add_static_documents(&serde_json::Value::Array(docs).to_string())
+        .unwrap();
+    opts
+}
+
 pub fn test_ed25519_did_2() -> Subjects {
     Subjects::Web(Web::Ed25519(JwkSubj {
         did: "did:web:tzprofiles.com".to_string(),
@@ -105,9 +272,9 @@ pub enum TestKey {
 
 pub fn test_witness_signature(witness: TestWitness, key: TestKey) -> Result<String, FlowError> {
     match (witness, key) {
-        (TestWitness::DNS, TestKey::Ed25519) => Ok("95cff42c8a40f2ee8e0a2c37afdb8e60b7ffaf52a8378ad9288797200181a879f89872ee5b5921fb39bfea50128badd79ef041f1ba40489709984af723a1550e".to_string()),
-        (TestWitness::GitHub, TestKey::Ed25519) => Ok("99d17a62134bcc7ae6e45d984c2d1472fcaa6c5fb9260d49a9107606a6a1059f4c0bc8d28b5162a1e4dad9dd9f0771968e66667c05bb5fad262d211d3a81f90a".to_string()),
-        (TestWitness::Twitter, TestKey::Ed25519) => Ok("10009637910e72222e3cade3a5d5b17112f91bf93e089007f2b9b8968865bf46f73b2c6864c7adc8b19bf31f75ad4e078754d08718f1dd80941126c69f137801".to_string()),
+        (TestWitness::DNS, TestKey::Ed25519) => Ok("d8752cedf26e9d27d584854d14ec260c67cd5c58855b284683232001234e740bc183b691c57e20405c63ddf5ec39b3f84282ff4a336cd0a3e3ed737bfdb4fc0e".to_string()),
+        (TestWitness::GitHub, TestKey::Ed25519) => Ok("934b99ae037d454798124783cdaf9dc5bdee24fa3b932c0c6e4db980704c4b6fc531e4b2faab65ff6902608b0df7e47efbb6fbdb0e475c1d41d9723866969304".to_string()),
+        (TestWitness::Twitter, TestKey::Ed25519) => Ok("e5627144656e090febd7d0735f35f06dca111ec983ffd26fd00638f87bd0a819262d96389a4ea519743121e226d2e53ab3048cd2059d5f4f5b1c8a1455e5e604".to_string()),
         (TestWitness::Reddit, TestKey::Ed25519) => Err(FlowError::Validation("Ed25519 + Reddit Test Not Supported".to_string())),
         (TestWitness::SoundCloud, TestKey::Ed25519) => Err(FlowError::Validation("Ed25519 + SoundCloud Test Not Supported".to_string())),
         (TestWitness::NftOwnership, TestKey::Ed25519) => Err(FlowError::Validation("Ed25519 + NFT Test Not Supported".to_string())),
@@ -217,3 +384,59 @@ impl Issuer for MockIssuer {
         Err(IssuerError::Internal("unimplemented".to_string()))
     }
 }
+
+// StubTransport answers flow lookups in-process, returning the body paired with
+// the first URL prefix that matches the outgoing request.
+#[derive(Clone, Debug)]
+pub struct StubTransport {
+    pub routes: Vec<(String, String)>,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl HttpTransport for StubTransport {
+    async fn execute(&self, request: Request) -> Result<Response, FlowError> {
+        let u = request.url().to_string();
+        for (prefix, body) in &self.routes {
+            if u.starts_with(prefix) {
+                let res = http::Response::builder()
+                    .status(200)
+                    .header("content-type", "application/json")
+                    .body(body.clone())
+                    .map_err(|e| FlowError::BadLookup(e.to_string()))?;
+                return Ok(Response::from(res));
+            }
+        }
+
+        Err(FlowError::BadLookup(format!(
+            "no stubbed response for {}",
+            u
+        )))
+    }
+}
+
+// HeaderStubTransport is a StubTransport that only answers requests carrying every
+// one of headers, for flows that authenticate to their upstream API.
+#[derive(Clone, Debug)]
+pub struct HeaderStubTransport {
+    pub headers: Vec<(String, String)>,
+    pub stub: StubTransport,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl HttpTransport for HeaderStubTransport {
+    async fn execute(&self, request: Request) -> Result<Response, FlowError> {
+        for (name, value) in &self.headers {
+            let got = request.headers().get(name).and_then(|v| v.to_str().ok());
+            if got != Some(value.as_str()) {
+                return Err(FlowError::BadLookup(format!(
+                    "expected header {}: {}, got {:?}",
+                    name, value, got
+                )));
+            }
+        }
+
+        self.stub.execute(request).await
+    }
+}
@@ -0,0 +1,272 @@
+use crate::types::error::CapabilityError;
+use chrono::{DateTime, Utc};
+use std::{fmt, str::FromStr};
+
+const PREAMBLE: &str = " wants you to sign in with your ";
+const PREAMBLE_END: &str = " account:";
+
+const URI_TAG: &str = "URI: ";
+const VERSION_TAG: &str = "Version: ";
+const CHAIN_ID_TAG: &str = "Chain ID: ";
+const NONCE_TAG: &str = "Nonce: ";
+const ISSUED_AT_TAG: &str = "Issued At: ";
+const EXPIRATION_TIME_TAG: &str = "Expiration Time: ";
+const NOT_BEFORE_TAG: &str = "Not Before: ";
+const REQUEST_ID_TAG: &str = "Request ID: ";
+const RESOURCES_TAG: &str = "Resources:";
+
+// SignInMessage is a chain agnostic (CAIP-122) sign in message, the generalization of SIWE
+// used by Sign-In With Solana and others. The account namespace is whatever follows
+// "sign in with your" in the preamble, e.g. "Ethereum" or "Solana".
+#[derive(Clone, Debug)]
+pub struct SignInMessage {
+    pub domain: String,
+    pub namespace: String,
+    pub address: String,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: Option<String>,
+    pub nonce: Option<String>,
+    pub issued_at: Option<String>,
+    pub expiration_time: Option<String>,
+    pub not_before: Option<String>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+fn parse_time(tag: &str, s: &str) -> Result<DateTime<Utc>, CapabilityError> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| CapabilityError::ReCapError(format!("invalid {}{}: {}", tag, s, e)))
+}
+
+impl SignInMessage {
+    // valid_at mirrors SIWE's timing checks: the message must not be expired and its
+    // not before time, if any, must have passed.
+    pub fn valid_at(&self, t: &DateTime<Utc>) -> Result<bool, CapabilityError> {
+        if let Some(exp) = &self.expiration_time {
+            if *t >= parse_time(EXPIRATION_TIME_TAG, exp)? {
+                return Ok(false);
+            }
+        }
+
+        if let Some(nbf) = &self.not_before {
+            if *t < parse_time(NOT_BEFORE_TAG, nbf)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub fn valid_now(&self) -> Result<bool, CapabilityError> {
+        self.valid_at(&Utc::now())
+    }
+
+    // expires_within is true if this message expires no later than parent, as required of
+    // a ReCap delegated from parent.
+    pub fn expires_within(&self, parent: &SignInMessage) -> Result<bool, CapabilityError> {
+        match (&self.expiration_time, &parent.expiration_time) {
+            (_, None) => Ok(true),
+            (None, Some(_)) => Ok(false),
+            (Some(exp), Some(parent_exp)) => Ok(parse_time(EXPIRATION_TIME_TAG, exp)?
+                <= parse_time(EXPIRATION_TIME_TAG, parent_exp)?),
+        }
+    }
+}
+
+impl fmt::Display for SignInMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}\n{}\n\n",
+            self.domain, PREAMBLE, self.namespace, PREAMBLE_END, self.address
+        )?;
+        if let Some(statement) = &self.statement {
+            write!(f, "{}\n\n", statement)?;
+        }
+        write!(
+            f,
+            "{}{}\n{}{}",
+            URI_TAG, self.uri, VERSION_TAG, self.version
+        )?;
+
+        for (t, v) in [
+            (CHAIN_ID_TAG, &self.chain_id),
+            (NONCE_TAG, &self.nonce),
+            (ISSUED_AT_TAG, &self.issued_at),
+            (EXPIRATION_TIME_TAG, &self.expiration_time),
+            (NOT_BEFORE_TAG, &self.not_before),
+            (REQUEST_ID_TAG, &self.request_id),
+        ] {
+            if let Some(v) = v {
+                write!(f, "\n{}{}", t, v)?;
+            }
+        }
+
+        if !self.resources.is_empty() {
+            write!(f, "\n{}", RESOURCES_TAG)?;
+            for r in &self.resources {
+                write!(f, "\n- {}", r)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for SignInMessage {
+    type Err = CapabilityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err =
+            |m: &str| CapabilityError::ReCapError(format!("malformed sign in message: {}", m));
+        let mut lines = s.split('\n').peekable();
+
+        let (domain, namespace) = lines
+            .next()
+            .and_then(|l| l.strip_suffix(PREAMBLE_END))
+            .and_then(|l| l.split_once(PREAMBLE))
+            .ok_or_else(|| err("missing preamble"))?;
+
+        let address = lines
+            .next()
+            .filter(|l| !l.is_empty())
+            .ok_or_else(|| err("missing address"))?;
+
+        // Everything between the address and the URI tag is the optional statement,
+        // surrounded by blank lines.
+        let mut statement_lines: Vec<&str> = Vec::new();
+        while let Some(l) = lines.peek() {
+            if l.starts_with(URI_TAG) {
+                break;
+            }
+            statement_lines.push(l);
+            lines.next();
+        }
+        let statement = statement_lines.join("\n").trim_matches('\n').to_string();
+
+        let mut tag = |t: &str, required: bool| -> Result<Option<String>, CapabilityError> {
+            match lines.peek().and_then(|l| l.strip_prefix(t)) {
+                Some(v) => {
+                    let v = v.to_string();
+                    lines.next();
+                    Ok(Some(v))
+                }
+                None if required => Err(err(&format!("missing {}", t.trim_end()))),
+                None => Ok(None),
+            }
+        };
+
+        let uri = tag(URI_TAG, true)?.unwrap_or_default();
+        let version = tag(VERSION_TAG, true)?.unwrap_or_default();
+        let chain_id = tag(CHAIN_ID_TAG, false)?;
+        let nonce = tag(NONCE_TAG, false)?;
+        let issued_at = tag(ISSUED_AT_TAG, false)?;
+        let expiration_time = tag(EXPIRATION_TIME_TAG, false)?;
+        let not_before = tag(NOT_BEFORE_TAG, false)?;
+        let request_id = tag(REQUEST_ID_TAG, false)?;
+
+        let mut resources = Vec::new();
+        if lines.peek() == Some(&RESOURCES_TAG) {
+            lines.next();
+            for l in lines.by_ref() {
+                match l.strip_prefix("- ") {
+                    Some(r) => resources.push(r.to_string()),
+                    None => return Err(err(&format!("unexpected resource line: {}", l))),
+                }
+            }
+        }
+
+        if let Some(l) = lines.find(|l| !l.is_empty()) {
+            return Err(err(&format!("unexpected line: {}", l)));
+        }
+
+        if version != "1" {
+            return Err(err(&format!("unsupported version: {}", version)));
+        }
+
+        for (t, v) in [
+            (ISSUED_AT_TAG, &issued_at),
+            (EXPIRATION_TIME_TAG, &expiration_time),
+            (NOT_BEFORE_TAG, &not_before),
+        ] {
+            if let Some(v) = v {
+                parse_time(t, v)?;
+            }
+        }
+
+        Ok(SignInMessage {
+            domain: domain.to_string(),
+            namespace: namespace.to_string(),
+            address: address.to_string(),
+            statement: if statement.is_empty() {
+                None
+            } else {
+                Some(statement)
+            },
+            uri,
+            version,
+            chain_id,
+            nonce,
+            issued_at,
+            expiration_time,
+            not_before,
+            request_id,
+            resources,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_solana_message() {
+        let m = SignInMessage::from_str("localhost:8080 wants you to sign in with your Solana account:\nGwAF45zjfyGzUbd3i3hXxzGeuchzEZXwpRYHZM5912F1\n\nSign in to issue credentials.\n\nURI: did:key:z6Mk#z6Mk\nVersion: 1\nNonce: 6JQhF2R1wBhfF6ONV\nIssued At: 2023-09-27T17:11:32.013Z\nExpiration Time: 2023-09-28T17:11:32.014Z\nResources:\n- urn:recap:abc\n- https://example.com").unwrap();
+
+        assert_eq!(m.domain, "localhost:8080");
+        assert_eq!(m.namespace, "Solana");
+        assert_eq!(m.address, "GwAF45zjfyGzUbd3i3hXxzGeuchzEZXwpRYHZM5912F1");
+        assert_eq!(
+            m.statement,
+            Some("Sign in to issue credentials.".to_string())
+        );
+        assert_eq!(m.uri, "did:key:z6Mk#z6Mk");
+        assert_eq!(m.chain_id, None);
+        assert_eq!(m.resources, vec!["urn:recap:abc", "https://example.com"]);
+        assert!(!m.valid_now().unwrap());
+        assert!(m
+            .valid_at(&parse_time(ISSUED_AT_TAG, "2023-09-28T00:00:00Z").unwrap())
+            .unwrap());
+
+        let reparsed = SignInMessage::from_str(&m.to_string()).unwrap();
+        assert_eq!(reparsed.to_string(), m.to_string());
+        assert_eq!(reparsed.statement, m.statement);
+        assert_eq!(reparsed.resources, m.resources);
+
+        let mut child = m.clone();
+        assert!(child.expires_within(&m).unwrap());
+        child.expiration_time = Some("2023-09-29T17:11:32.014Z".to_string());
+        assert!(!child.expires_within(&m).unwrap());
+        child.expiration_time = None;
+        assert!(!child.expires_within(&m).unwrap());
+    }
+
+    #[test]
+    fn test_parse_bad_message() {
+        for bad in [
+            "localhost wants you to sign in with your Solana account:\n\nURI: a\nVersion: 1",
+            "localhost wants you to sign in with your Solana account:\nabc\n\nVersion: 1",
+            "localhost wants you to sign in with your Solana account:\nabc\n\nURI: a\nVersion: 2",
+            "localhost wants you to sign in with your Solana account:\nabc\n\nURI: a\nVersion: 1\nNot Before: yesterday",
+            "localhost wants you to sign in:\nabc\n\nURI: a\nVersion: 1",
+        ] {
+            if SignInMessage::from_str(bad).is_ok() {
+                panic!("Parsed malformed message: {}", bad);
+            }
+        }
+    }
+}
@@ -1 +1,3 @@
+pub mod caip122;
 pub mod recap;
+pub mod revocation;
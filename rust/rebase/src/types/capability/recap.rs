@@ -1,6 +1,30 @@
-use crate::types::enums::attestation::AttestationTypes;
+use crate::{
+    statement::attestation::statement::AttestationStatement,
+    types::{
+        capability::caip122::SignInMessage, defs::MaybeSendSync,
+        enums::attestation::AttestationTypes, error::CapabilityError,
+    },
+};
+use async_trait::async_trait;
+use cacaos::siwe::generate_nonce;
+use chrono::{DateTime, SecondsFormat, Utc};
+use iri_string::types::UriString;
+use libipld::{
+    cid::Cid,
+    multihash::{Code, MultihashDigest},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 pub use siwe_recap::Capability as RecapCapability;
+use std::{collections::BTreeMap, str::FromStr, sync::Mutex};
 use strum::IntoEnumIterator;
+use tsify::Tsify;
+use url::Url;
+use wasm_bindgen::prelude::*;
+
+// The raw multicodec, SIWE messages are referenced in ReCap proofs by the CID of their bytes.
+const RAW_CODEC: u64 = 0x55;
 
 pub fn to_action(attestation_type: &AttestationTypes) -> String {
     match attestation_type {
@@ -23,3 +47,368 @@ pub fn to_action(attestation_type: &AttestationTypes) -> String {
 pub fn from_action_string(action_string: &str) -> Option<AttestationTypes> {
     AttestationTypes::iter().find(|t| to_action(t) == action_string)
 }
+
+// Caveats restrict what a delegate may issue under a single ReCap action. They are written
+// into the action's nota bene and every field must be understood by the witness, so unknown
+// caveats fail closed.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize, Tsify)]
+#[serde(deny_unknown_fields)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Caveats {
+    // Allowed targets of follow and like attestations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<Url>>,
+    // Highest rating allowed in book review attestations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rating: Option<i64>,
+    // Most credentials the delegate may issue for this action under the ReCap.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_issuances: Option<u64>,
+}
+
+impl Caveats {
+    pub fn is_empty(&self) -> bool {
+        self == &Caveats::default()
+    }
+
+    // Ensures the caveats make sense for the given attestation type.
+    pub fn validate_for(&self, attestation_type: &AttestationTypes) -> Result<(), CapabilityError> {
+        if self.targets.is_some()
+            && !matches!(
+                attestation_type,
+                AttestationTypes::FollowAttestation | AttestationTypes::LikeAttestation
+            )
+        {
+            return Err(CapabilityError::ReCapError(format!(
+                "targets caveat does not apply to {}",
+                to_action(attestation_type)
+            )));
+        }
+
+        if self.max_rating.is_some()
+            && !matches!(attestation_type, AttestationTypes::BookReviewAttestation)
+        {
+            return Err(CapabilityError::ReCapError(format!(
+                "max_rating caveat does not apply to {}",
+                to_action(attestation_type)
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Checks the statement's content against the caveats, max_issuances is enforced by the
+    // witness through its IssuanceStore.
+    pub fn check(&self, statement: &AttestationStatement) -> Result<(), CapabilityError> {
+        let target = match statement {
+            AttestationStatement::FollowAttestation(x) => Some(&x.target),
+            AttestationStatement::LikeAttestation(x) => Some(&x.target),
+            _ => None,
+        };
+
+        if let (Some(targets), Some(target)) = (&self.targets, target) {
+            if !targets.contains(target) {
+                return Err(CapabilityError::ReCapError(format!(
+                    "target {} is not permitted by the ReCap",
+                    target
+                )));
+            }
+        }
+
+        if let (Some(max), AttestationStatement::BookReviewAttestation(x)) =
+            (self.max_rating, statement)
+        {
+            if x.rating > max {
+                return Err(CapabilityError::ReCapError(format!(
+                    "rating {} is above the ReCap's maximum of {}",
+                    x.rating, max
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // to_nota_bene converts the caveats into the ReCap representation, an empty list when
+    // unrestricted or a single caveat object.
+    pub fn to_nota_bene(&self) -> Result<Vec<BTreeMap<String, Value>>, CapabilityError> {
+        if self.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let v = serde_json::to_value(self)
+            .and_then(serde_json::from_value)
+            .map_err(|e| CapabilityError::ReCapError(format!("failed to encode caveats: {}", e)))?;
+        Ok(vec![v])
+    }
+
+    // attenuates is true if these caveats are at least as strict as parent's, as required of
+    // a ReCap delegated from parent.
+    pub fn attenuates(&self, parent: &Caveats) -> bool {
+        fn at_most<T: PartialOrd>(child: &Option<T>, parent: &Option<T>) -> bool {
+            match (child, parent) {
+                (_, None) => true,
+                (None, Some(_)) => false,
+                (Some(c), Some(p)) => c <= p,
+            }
+        }
+
+        let targets = match (&self.targets, &parent.targets) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(c), Some(p)) => c.iter().all(|t| p.contains(t)),
+        };
+
+        targets
+            && at_most(&self.max_rating, &parent.max_rating)
+            && at_most(&self.max_issuances, &parent.max_issuances)
+    }
+
+    // from_nota_bene is the inverse of to_nota_bene, also accepting a single empty object.
+    pub fn from_nota_bene(nota_bene: &Value) -> Result<Self, CapabilityError> {
+        let mut nb: Vec<Caveats> = serde_json::from_value(nota_bene.clone())
+            .map_err(|e| CapabilityError::ReCapError(format!("unsupported caveats: {}", e)))?;
+
+        match nb.len() {
+            0 => Ok(Caveats::default()),
+            1 => Ok(nb.remove(0)),
+            _ => Err(CapabilityError::ReCapError(
+                "only one set of caveats per action is supported".to_string(),
+            )),
+        }
+    }
+}
+
+// DelegatedCapability is an attestation type a delegate may issue and the caveats on it.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct DelegatedCapability {
+    pub attestation_type: AttestationTypes,
+    #[serde(default)]
+    pub caveats: Caveats,
+}
+
+impl From<&AttestationTypes> for DelegatedCapability {
+    fn from(attestation_type: &AttestationTypes) -> Self {
+        DelegatedCapability {
+            attestation_type: attestation_type.clone(),
+            caveats: Caveats::default(),
+        }
+    }
+}
+
+// recap_cid is the CID a child ReCap uses to reference message as its parent.
+pub fn recap_cid(message: &str) -> Cid {
+    Cid::new_v1(RAW_CODEC, Code::Sha2_256.digest(message.as_bytes()))
+}
+
+// build_recap builds the ReCap granting delegated_capabilities on service_key, attenuated from
+// the parent delegations.
+pub fn build_recap(
+    service_key: &str,
+    delegated_capabilities: &[DelegatedCapability],
+    parents: &[Cid],
+) -> Result<RecapCapability<Value>, CapabilityError> {
+    let u: UriString = service_key.try_into().map_err(|_e| {
+        CapabilityError::ReCapError(format!(
+            "failed to parse witness into UriString, service_key: {}",
+            service_key
+        ))
+    })?;
+
+    let mut v: Vec<(String, Vec<BTreeMap<String, Value>>)> = Vec::new();
+    for c in delegated_capabilities {
+        c.caveats.validate_for(&c.attestation_type)?;
+        v.push((to_action(&c.attestation_type), c.caveats.to_nota_bene()?));
+    }
+
+    let mut cap = RecapCapability::<Value>::new();
+    cap.with_actions_convert(u, v).map_err(|e| {
+        CapabilityError::ReCapError(format!("failed to parse generate actions: {}", e))
+    })?;
+
+    for p in parents {
+        // siwe-recap uses a newer cid crate than libipld, so convert through the string form.
+        let parent = p.to_string().parse().map_err(|_e| {
+            CapabilityError::ReCapError(format!("failed to convert parent CID: {}", p))
+        })?;
+        cap = cap.with_proof(&parent);
+    }
+
+    Ok(cap)
+}
+
+// redelegation_message lets the delegate of parent, a session key, delegate a subset of its
+// capabilities to another key. The result must be signed by the parent's delegate and is
+// only valid alongside parent and its signature.
+pub fn redelegation_message(
+    parent: &str,
+    delegate: &str,
+    service_key: &str,
+    delegated_capabilities: &[DelegatedCapability],
+    expiration_time: Option<String>,
+) -> Result<String, CapabilityError> {
+    let p = SignInMessage::from_str(parent)?;
+    let delegator = p.uri.split('#').next().unwrap_or_default().to_string();
+
+    let cap = build_recap(service_key, delegated_capabilities, &[recap_cid(parent)])?;
+    let resource: UriString = (&cap)
+        .try_into()
+        .map_err(|e| CapabilityError::ReCapError(format!("failed to encode ReCap: {}", e)))?;
+
+    let m = SignInMessage {
+        domain: p.domain,
+        namespace: "did:key".to_string(),
+        address: delegator,
+        statement: Some(cap.to_statement()),
+        uri: delegate.to_string(),
+        version: "1".to_string(),
+        chain_id: None,
+        nonce: Some(generate_nonce()),
+        issued_at: Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        expiration_time: expiration_time.or(p.expiration_time),
+        not_before: p.not_before,
+        request_id: None,
+        resources: vec![resource.to_string()],
+    };
+
+    Ok(m.to_string())
+}
+
+// IssuanceStore counts the credentials issued per ReCap action to enforce max_issuances.
+// Every instance of the witness must share the store, or each would allow max issuances.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait IssuanceStore: std::fmt::Debug + MaybeSendSync {
+    // Increments the count for key if it is below max, returning whether it did. The count
+    // only has to be kept until expires, after which the ReCaps it counts are invalid.
+    async fn try_increment(
+        &self,
+        key: &str,
+        max: u64,
+        expires: DateTime<Utc>,
+    ) -> Result<bool, CapabilityError>;
+}
+
+// MemoryIssuanceStore is an in-process IssuanceStore for witnesses run as a single instance.
+#[derive(Debug, Default)]
+pub struct MemoryIssuanceStore {
+    // The count under each key and when it can be forgotten.
+    counts: Mutex<BTreeMap<String, (u64, DateTime<Utc>)>>,
+}
+
+impl MemoryIssuanceStore {
+    pub const fn new() -> Self {
+        MemoryIssuanceStore {
+            counts: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl IssuanceStore for MemoryIssuanceStore {
+    async fn try_increment(
+        &self,
+        key: &str,
+        max: u64,
+        expires: DateTime<Utc>,
+    ) -> Result<bool, CapabilityError> {
+        let mut counts = self
+            .counts
+            .lock()
+            .map_err(|e| CapabilityError::ReCapError(e.to_string()))?;
+        let now = Utc::now();
+        counts.retain(|_, (_, exp)| *exp > now);
+
+        let (count, exp) = counts.entry(key.to_string()).or_insert((0, expires));
+        if *count >= max {
+            return Ok(false);
+        }
+
+        *count += 1;
+        // ReCaps sharing a count may expire at different times.
+        *exp = (*exp).max(expires);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        statement::attestation::{
+            book_review_attestation::BookReviewAttestationStatement,
+            follow_attestation::FollowAttestationStatement,
+        },
+        test_util::util::test_eth_did,
+    };
+
+    fn follow(target: &str) -> AttestationStatement {
+        AttestationStatement::FollowAttestation(FollowAttestationStatement {
+            subject: test_eth_did(),
+            target: Url::parse(target).unwrap(),
+        })
+    }
+
+    #[test]
+    fn test_caveats() {
+        let c = Caveats {
+            targets: Some(vec![Url::parse("https://example.com/alice").unwrap()]),
+            ..Default::default()
+        };
+        c.validate_for(&AttestationTypes::FollowAttestation)
+            .unwrap();
+        c.check(&follow("https://example.com/alice")).unwrap();
+        if c.check(&follow("https://example.com/mallory")).is_ok() {
+            panic!("Target outside of the allowlist permitted");
+        }
+        if c.validate_for(&AttestationTypes::BasicPostAttestation)
+            .is_ok()
+        {
+            panic!("Targets caveat permitted on basic posts");
+        }
+
+        let c = Caveats {
+            max_rating: Some(3),
+            ..Default::default()
+        };
+        let review = |rating| {
+            AttestationStatement::BookReviewAttestation(BookReviewAttestationStatement {
+                subject: test_eth_did(),
+                link: Url::parse("https://example.com/book").unwrap(),
+                rating,
+                review: "Fine".to_string(),
+                title: "Book".to_string(),
+            })
+        };
+        c.check(&review(3)).unwrap();
+        if c.check(&review(5)).is_ok() {
+            panic!("Rating above the maximum permitted");
+        }
+
+        let nb = serde_json::to_value(c.to_nota_bene().unwrap()).unwrap();
+        assert_eq!(Caveats::from_nota_bene(&nb).unwrap(), c);
+        assert!(Caveats::from_nota_bene(&serde_json::json!([{}]))
+            .unwrap()
+            .is_empty());
+        if Caveats::from_nota_bene(&serde_json::json!([{ "max_likes": 1 }])).is_ok() {
+            panic!("Unknown caveat permitted");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_issuance_store() {
+        let store = MemoryIssuanceStore::default();
+        let later = Utc::now() + chrono::Duration::minutes(10);
+        assert!(store.try_increment("a", 2, later).await.unwrap());
+        assert!(store.try_increment("a", 2, later).await.unwrap());
+        assert!(!store.try_increment("a", 2, later).await.unwrap());
+        assert!(store.try_increment("b", 2, later).await.unwrap());
+
+        // Expired counts are forgotten.
+        let past = Utc::now() - chrono::Duration::minutes(1);
+        assert!(store.try_increment("c", 1, past).await.unwrap());
+        assert!(store.try_increment("c", 1, past).await.unwrap());
+    }
+}
@@ -0,0 +1,293 @@
+use crate::types::{
+    defs::{MaybeSendSync, Statement, Subject},
+    enums::subject::{Pkh, Subjects},
+    error::{CapabilityError, StatementError},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Mutex};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+// RevocationTarget is what a delegator revokes: the single ReCap signed with the given SIWE
+// nonce, or every ReCap they have signed for the given delegate DID.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum RevocationTarget {
+    Nonce(String),
+    Delegate(String),
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Revocation {
+    // The signer of the ReCaps being revoked.
+    pub delegator: Subjects,
+    pub service_key: String,
+    pub target: RevocationTarget,
+    // RFC 3339 time the revocation can be forgotten at, once the ReCaps it targets have expired.
+    pub expires: String,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SignedRevocation {
+    pub revocation: Revocation,
+    // The delegator's signature over the revocation's statement.
+    pub signature: String,
+}
+
+// Ethereum delegators are identified by address alone, matching how ReCap subjects are
+// compared, so a revocation signed under another chain id still applies.
+fn delegator_id(delegator: &Subjects) -> Result<String, CapabilityError> {
+    match delegator {
+        Subjects::Pkh(Pkh::Eip155(x)) => Ok(x.address.clone()),
+        x => x
+            .did()
+            .map_err(|e| CapabilityError::ReCapError(e.to_string())),
+    }
+}
+
+// revocation_key is the key a revoked ReCap is stored under.
+pub fn revocation_key(
+    delegator: &Subjects,
+    target: &RevocationTarget,
+) -> Result<String, CapabilityError> {
+    let id = delegator_id(delegator)?;
+    Ok(match target {
+        RevocationTarget::Nonce(n) => format!("{}/nonce/{}", id, n),
+        RevocationTarget::Delegate(d) => format!(
+            "{}/delegate/{}",
+            id,
+            d.split('#').next().unwrap_or_default()
+        ),
+    })
+}
+
+impl Revocation {
+    pub fn key(&self) -> Result<String, CapabilityError> {
+        revocation_key(&self.delegator, &self.target)
+    }
+
+    pub fn expires(&self) -> Result<DateTime<Utc>, CapabilityError> {
+        DateTime::parse_from_rfc3339(&self.expires)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| CapabilityError::ReCapError(format!("invalid revocation expiry: {}", e)))
+    }
+}
+
+impl Statement for Revocation {
+    fn generate_statement(&self) -> Result<String, StatementError> {
+        let target = match &self.target {
+            RevocationTarget::Nonce(n) => format!("the delegated session with nonce {}", n),
+            RevocationTarget::Delegate(d) => format!("all delegated sessions for {}", d),
+        };
+
+        Ok(format!(
+            "I am revoking {} authorized by {} on {} until {}",
+            target,
+            self.delegator.did()?,
+            self.service_key,
+            self.expires
+        ))
+    }
+}
+
+// RevocationStore records revoked ReCaps for DelegatedAttestationFlow to refuse. Witnesses
+// running more than one instance should back it with shared storage.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait RevocationStore: std::fmt::Debug + MaybeSendSync {
+    // revoke records key as revoked until expires, after which it can be forgotten.
+    async fn revoke(&self, key: &str, expires: DateTime<Utc>) -> Result<(), CapabilityError>;
+    async fn is_revoked(&self, key: &str) -> Result<bool, CapabilityError>;
+}
+
+// insert_revocation records key in revoked, dropping expired revocations. It returns false if
+// nothing changed.
+fn insert_revocation(
+    revoked: &mut BTreeMap<String, DateTime<Utc>>,
+    key: &str,
+    expires: DateTime<Utc>,
+) -> bool {
+    let now = Utc::now();
+    let before = revoked.len();
+    revoked.retain(|_, e| *e > now);
+    let pruned = revoked.len() != before;
+
+    match revoked.get(key) {
+        Some(e) if *e >= expires => pruned,
+        _ => {
+            revoked.insert(key.to_string(), expires);
+            true
+        }
+    }
+}
+
+fn revoked_at(revoked: &BTreeMap<String, DateTime<Utc>>, key: &str, now: DateTime<Utc>) -> bool {
+    revoked.get(key).is_some_and(|e| *e > now)
+}
+
+// MemoryRevocationStore is the in-process RevocationStore used when none is configured.
+#[derive(Debug, Default)]
+pub struct MemoryRevocationStore {
+    revoked: Mutex<BTreeMap<String, DateTime<Utc>>>,
+}
+
+impl MemoryRevocationStore {
+    pub const fn new() -> Self {
+        MemoryRevocationStore {
+            revoked: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl RevocationStore for MemoryRevocationStore {
+    async fn revoke(&self, key: &str, expires: DateTime<Utc>) -> Result<(), CapabilityError> {
+        let mut revoked = self
+            .revoked
+            .lock()
+            .map_err(|e| CapabilityError::ReCapError(e.to_string()))?;
+        insert_revocation(&mut revoked, key, expires);
+        Ok(())
+    }
+
+    async fn is_revoked(&self, key: &str) -> Result<bool, CapabilityError> {
+        Ok(revoked_at(
+            &*self
+                .revoked
+                .lock()
+                .map_err(|e| CapabilityError::ReCapError(e.to_string()))?,
+            key,
+            Utc::now(),
+        ))
+    }
+}
+
+// FileRevocationStore keeps revocations in a JSON object of keys to their expiries, so they
+// survive restarts of a single witness.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct FileRevocationStore {
+    path: PathBuf,
+    revoked: Mutex<BTreeMap<String, DateTime<Utc>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileRevocationStore {
+    // open loads the revocations at path, which is created on the first revocation.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, CapabilityError> {
+        let path = path.as_ref().to_path_buf();
+        let revoked = match std::fs::read_to_string(&path) {
+            Ok(s) => serde_json::from_str(&s).map_err(|e| {
+                CapabilityError::ReCapError(format!(
+                    "failed to parse revocations in {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(CapabilityError::ReCapError(format!(
+                    "failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        Ok(FileRevocationStore {
+            path,
+            revoked: Mutex::new(revoked),
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl RevocationStore for FileRevocationStore {
+    async fn revoke(&self, key: &str, expires: DateTime<Utc>) -> Result<(), CapabilityError> {
+        let mut revoked = self
+            .revoked
+            .lock()
+            .map_err(|e| CapabilityError::ReCapError(e.to_string()))?;
+        if !insert_revocation(&mut revoked, key, expires) {
+            return Ok(());
+        }
+
+        let s = serde_json::to_string(&*revoked)
+            .map_err(|e| CapabilityError::ReCapError(e.to_string()))?;
+        // Write then rename, so a crash never leaves a truncated file behind.
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, s)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| {
+                CapabilityError::ReCapError(format!(
+                    "failed to write {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            })
+    }
+
+    async fn is_revoked(&self, key: &str) -> Result<bool, CapabilityError> {
+        Ok(revoked_at(
+            &*self
+                .revoked
+                .lock()
+                .map_err(|e| CapabilityError::ReCapError(e.to_string()))?,
+            key,
+            Utc::now(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::util::test_eth_did;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_file_revocation_store() {
+        let path = std::env::temp_dir().join(format!("rebase-{}.json", uuid::Uuid::new_v4()));
+        let key = revocation_key(
+            &test_eth_did(),
+            &RevocationTarget::Delegate("did:key:z6Mk#z6Mk".to_string()),
+        )
+        .unwrap();
+
+        let store = FileRevocationStore::open(&path).unwrap();
+        assert!(!store.is_revoked(&key).await.unwrap());
+        store
+            .revoke(&key, Utc::now() + Duration::hours(1))
+            .await
+            .unwrap();
+        assert!(store.is_revoked(&key).await.unwrap());
+
+        // Expired revocations are pruned by the next one.
+        store
+            .revoke("expired", Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        store
+            .revoke("later", Utc::now() + Duration::hours(1))
+            .await
+            .unwrap();
+
+        let reopened = FileRevocationStore::open(&path).unwrap();
+        assert!(reopened.is_revoked(&key).await.unwrap());
+        assert!(!reopened.is_revoked("something else").await.unwrap());
+        assert!(!reopened.is_revoked("expired").await.unwrap());
+        assert_eq!(reopened.revoked.lock().unwrap().len(), 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,7 +1,12 @@
-pub use crate::types::{capability::recap::to_action, error::*};
+pub use crate::types::{
+    capability::recap::{build_recap, to_action, Caveats, DelegatedCapability},
+    error::*,
+    presentation::PresentationOptions,
+};
 use async_trait::async_trait;
 use cacaos::siwe::{generate_nonce, TimeStamp, Version as SIWEVersion};
-use chrono::{SecondsFormat, Utc};
+pub use chrono::{DateTime, Utc};
+use chrono::{Duration, SecondsFormat};
 use did_ethr::DIDEthr;
 use did_ion::DIDION;
 use did_jwk::DIDJWK;
@@ -15,6 +20,7 @@ use hex::FromHex;
 use http::uri::Authority;
 pub use iri_string::types::UriString;
 use libipld::cid::Cid;
+use reqwest::{Client as HttpClient, Request, RequestBuilder, Response, StatusCode};
 use schemars::schema::RootSchema;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -23,18 +29,29 @@ use serde_with::{serde_as, DisplayFromStr};
 pub use siwe::{eip55, Message};
 pub use siwe_recap::Capability;
 pub use ssi::{
-    did::{DIDMethod, Source, DIDURL},
-    did_resolve::{resolve_key, DIDResolver},
+    did::{DIDMethod, Document, PrimaryDIDURL, Source, DIDURL},
+    did_resolve::{
+        resolve_key, resolve_vm, Content as DIDContent, ContentMetadata, DIDResolver,
+        DereferencingInputMetadata, DereferencingMetadata, DocumentMetadata,
+        ResolutionInputMetadata, ResolutionMetadata,
+    },
     jsonld::ContextLoader,
-    jwk::JWK,
-    ldp::Proof as LDProof,
+    jwk::{Algorithm, JWK},
+    ldp::{eip712::TypedData, Proof as LDProof},
     one_or_many::OneOrMany,
     ucan::{Capability as UCanCapability, UcanResource, UcanScope},
-    vc::{get_verification_method, Credential, Evidence, LinkedDataProofOptions, URI},
+    vc::{
+        get_verification_method, Credential, CredentialOrJWT, Evidence, LinkedDataProofOptions,
+        Presentation, VerificationResult, URI,
+    },
 };
 pub use ssi_dids::DIDMethods;
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
 use tsify::Tsify;
+use url::Url;
 use uuid::Uuid;
 use wasm_bindgen::prelude::*;
 
@@ -76,6 +93,19 @@ impl SessionConfig {
         &mut self,
         service_key: &str,
         delegated_capabilities: &[AttestationTypes],
+    ) -> Result<String, RebaseError> {
+        let delegated_capabilities: Vec<DelegatedCapability> =
+            delegated_capabilities.iter().map(|t| t.into()).collect();
+        self.generate_message_with_caveats(service_key, &delegated_capabilities)
+            .await
+    }
+
+    // generate_message_with_caveats is generate_message with each action restricted by the
+    // given caveats.
+    pub async fn generate_message_with_caveats(
+        &mut self,
+        service_key: &str,
+        delegated_capabilities: &[DelegatedCapability],
     ) -> Result<String, RebaseError> {
         if self.jwk.is_none() {
             self.generate_jwk()?;
@@ -114,7 +144,7 @@ impl SessionConfig {
         &self,
         delegate: &str,
         service_key: &str,
-        delegated_capabilities: &[AttestationTypes],
+        delegated_capabilities: &[DelegatedCapability],
     ) -> Result<Message, RebaseError> {
         let d: UriString = delegate.try_into().map_err(|_e| {
             CapabilityError::ReCapError(format!(
@@ -123,24 +153,8 @@ impl SessionConfig {
             ))
         })?;
 
-        let u: UriString = service_key.try_into().map_err(|_e| {
-            CapabilityError::ReCapError(format!(
-                "failed to parse witness into UriString, service_key: {}",
-                service_key
-            ))
-        })?;
-
-        // NOTE: If using caveats, _ will need to be some sort of struct.
-        let v: Vec<(String, Vec<BTreeMap<String, _>>)> = delegated_capabilities
-            .iter()
-            .map(|c| (to_action(c), Vec::<BTreeMap<String, _>>::new()))
-            .collect();
-
-        let m = Capability::<String>::new()
-            .with_actions_convert(u, v)
-            .map_err(|e| {
-                CapabilityError::ReCapError(format!("failed to parse generate actions: {}", e))
-            })?
+        let parents = self.parents.clone().unwrap_or_default();
+        let m = build_recap(service_key, delegated_capabilities, &parents)?
             .build_message(Message {
                 address: address_from_string(self.address.clone())?,
                 chain_id: self.chain_id,
@@ -161,13 +175,45 @@ impl SessionConfig {
         Ok(m)
     }
 }
-#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct ResolverOpts {
-    did_onion_proxy_url: Option<String>,
-    did_ion_api_url: Option<String>,
+    pub did_onion_proxy_url: Option<String>,
+    pub did_ion_api_url: Option<String>,
+    // How long resolved DID documents are cached. Resolvers only share cached documents with
+    // resolvers made from the same options. Unset disables caching.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_ttl_seconds: Option<u64>,
+    // DID documents returned without resolving, keyed by DID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub static_documents: Option<BTreeMap<String, Document>>,
 }
 
+impl ResolverOpts {
+    // add_static_documents parses a JSON array of DID documents and serves them by their ids.
+    pub fn add_static_documents(&mut self, json: &str) -> Result<(), ResolverError> {
+        let docs: Vec<Document> =
+            serde_json::from_str(json).map_err(|e| ResolverError::Load(e.to_string()))?;
+        let m = self.static_documents.get_or_insert_with(BTreeMap::new);
+        for doc in docs {
+            m.insert(doc.id.clone(), doc);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_static_documents_file(&mut self, path: &str) -> Result<(), ResolverError> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| ResolverError::Load(format!("{}: {}", path, e)))?;
+        self.add_static_documents(&json)
+    }
+}
+
+// Cached documents are keyed by the resolver's options as well as the DID, so resolvers made
+// with different endpoints or TTLs never answer from each other's entries.
+type CacheKey = (String, String);
+static RESOLVER_CACHE: Mutex<BTreeMap<CacheKey, (i64, Document)>> = Mutex::new(BTreeMap::new());
+
 pub fn make_resolver(opts: &Option<ResolverOpts>) -> DIDMethods<'static> {
     let mut methods = DIDMethods::default();
     methods.insert(Box::new(DIDKey));
@@ -187,10 +233,231 @@ pub fn make_resolver(opts: &Option<ResolverOpts>) -> DIDMethods<'static> {
             did_onion.proxy_url = u;
             methods.insert(Box::new(did_onion));
         }
+
+        if o.cache_ttl_seconds.is_some() || o.static_documents.is_some() {
+            let documents = Arc::new(o.static_documents.clone().unwrap_or_default());
+            // Static documents are answered before the cache, so they don't need to be in the key.
+            let cache_key = Arc::new(
+                serde_json::to_string(&(
+                    &o.did_onion_proxy_url,
+                    &o.did_ion_api_url,
+                    o.cache_ttl_seconds,
+                ))
+                .unwrap_or_default(),
+            );
+            let mut cached = DIDMethods::default();
+            for (_, inner) in methods.methods.drain() {
+                cached.insert(Box::new(CachedMethod {
+                    inner,
+                    ttl_seconds: o.cache_ttl_seconds,
+                    documents: documents.clone(),
+                    cache_key: cache_key.clone(),
+                }));
+            }
+            return cached;
+        }
     }
     methods
 }
 
+// CachedMethod answers from the static documents or the cache entries of resolvers with the
+// same options before falling back to the wrapped DID method.
+struct CachedMethod {
+    inner: Box<dyn DIDMethod>,
+    ttl_seconds: Option<u64>,
+    documents: Arc<BTreeMap<String, Document>>,
+    cache_key: Arc<String>,
+}
+
+impl CachedMethod {
+    fn lookup(&self, did: &str) -> Option<Document> {
+        if let Some(doc) = self.documents.get(did) {
+            return Some(doc.clone());
+        }
+
+        let ttl = self.ttl_seconds? as i64;
+        let cache = RESOLVER_CACHE.lock().ok()?;
+        match cache.get(&(self.cache_key.to_string(), did.to_string())) {
+            Some((at, doc)) if Utc::now().timestamp() - at < ttl => Some(doc.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl DIDMethod for CachedMethod {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn generate(&self, source: &Source) -> Option<String> {
+        self.inner.generate(source)
+    }
+
+    fn to_resolver(&self) -> &dyn DIDResolver {
+        self
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for CachedMethod {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        if let Some(doc) = self.lookup(did) {
+            return (
+                ResolutionMetadata::default(),
+                Some(doc),
+                Some(DocumentMetadata::default()),
+            );
+        }
+
+        let (res_meta, doc, doc_meta) = self.inner.to_resolver().resolve(did, input_metadata).await;
+        if let (Some(d), None, Some(_)) = (&doc, &res_meta.error, self.ttl_seconds) {
+            if let Ok(mut cache) = RESOLVER_CACHE.lock() {
+                let now = Utc::now().timestamp();
+                let ttl = self.ttl_seconds.unwrap_or_default() as i64;
+                cache.retain(|(k, _), (at, _)| k != self.cache_key.as_str() || now - *at < ttl);
+                cache.insert(
+                    (self.cache_key.to_string(), did.to_string()),
+                    (now, d.clone()),
+                );
+            }
+        }
+        (res_meta, doc, doc_meta)
+    }
+
+    async fn dereference(
+        &self,
+        primary_did_url: &PrimaryDIDURL,
+        input_metadata: &DereferencingInputMetadata,
+    ) -> Option<(DereferencingMetadata, DIDContent, ContentMetadata)> {
+        // Returning None makes the caller dereference through resolve, which checks the cache.
+        if self.lookup(&primary_did_url.did).is_some() {
+            return None;
+        }
+        self.inner
+            .to_resolver()
+            .dereference(primary_did_url, input_metadata)
+            .await
+    }
+}
+
+// MaybeSendSync is Send + Sync, except on wasm32 where there is a single thread and stores
+// may hold JS handles that are neither.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
+
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSendSync {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSendSync for T {}
+
+// HttpTransport sends the requests flows make to their upstream APIs. Implement it to add
+// retries, share a tuned connection pool, or answer requests from an in-process stub.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait HttpTransport: std::fmt::Debug + Send + Sync {
+    async fn execute(&self, request: Request) -> Result<Response, FlowError>;
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl HttpTransport for HttpClient {
+    async fn execute(&self, request: Request) -> Result<Response, FlowError> {
+        HttpClient::execute(self, request)
+            .await
+            .map_err(|e| FlowError::Upstream(e.to_string()))
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct HttpOpts {
+    // Replaces the scheme and host of the flow's default API, e.g. http://127.0.0.1:8080
+    #[serde(default)]
+    pub base_url: Option<String>,
+    // Not serializable, so must be set in code after the flow is deserialized.
+    #[serde(skip)]
+    pub transport: Option<Arc<dyn HttpTransport>>,
+}
+
+// make_url joins the path to the configured base URL, or to default_base if none is set.
+pub fn make_url(opts: &Option<HttpOpts>, default_base: &str, path: &str) -> Result<Url, FlowError> {
+    let base = match opts {
+        Some(HttpOpts {
+            base_url: Some(u), ..
+        }) => u.as_str(),
+        _ => default_base,
+    };
+
+    let u = format!("{}{}", base.trim_end_matches('/'), path);
+    Url::parse(&u).map_err(|e| FlowError::BadLookup(format!("failed to parse url {}: {}", u, e)))
+}
+
+// send_request uses the configured transport if there is one, otherwise sends the request as built.
+// Responses the upstream API couldn't serve, or that found nothing, are returned as errors.
+pub async fn send_request(
+    opts: &Option<HttpOpts>,
+    req: RequestBuilder,
+) -> Result<Response, FlowError> {
+    let res = match opts {
+        Some(HttpOpts {
+            transport: Some(t), ..
+        }) => {
+            let r = req
+                .build()
+                .map_err(|e| FlowError::BadLookup(e.to_string()))?;
+            t.execute(r).await?
+        }
+        _ => req
+            .send()
+            .await
+            .map_err(|e| FlowError::Upstream(e.to_string()))?,
+    };
+
+    let status = res.status();
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(FlowError::Upstream(format!(
+            "{} responded with {}",
+            res.url().host_str().unwrap_or_default(),
+            status
+        )));
+    }
+    if status == StatusCode::NOT_FOUND {
+        return Err(FlowError::NotFound(format!(
+            "{} responded with {}",
+            res.url().host_str().unwrap_or_default(),
+            status
+        )));
+    }
+
+    Ok(res)
+}
+
+// EthRpcOpts lets Eip155 subjects verify smart contract wallet signatures (EIP-1271 and
+// EIP-6492) through a JSON-RPC endpoint when the signature does not recover to the address.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct EthRpcOpts {
+    // JSON-RPC endpoints keyed by EIP-155 chain id, e.g. "1" for mainnet.
+    pub rpc_urls: BTreeMap<String, String>,
+    // Hex creation code of the EIP-6492 ValidateSigOffchain contract. Needed to verify
+    // signatures from wallets that have not been deployed yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eip6492_validator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+}
+
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 pub trait Subject
@@ -204,6 +471,17 @@ where
     fn verification_method(&self) -> Result<String, SubjectError>;
 
     async fn valid_signature(&self, statement: &str, signature: &str) -> Result<(), SubjectError>;
+
+    // valid_typed_data_signature checks an EIP-712 (eth_signTypedData_v4) signature.
+    async fn valid_typed_data_signature(
+        &self,
+        _typed_data: &TypedData,
+        _signature: &str,
+    ) -> Result<(), SubjectError> {
+        Err(SubjectError::Validation(
+            "typed data signatures are not supported for this subject".to_string(),
+        ))
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -225,6 +503,26 @@ where
     ) -> Result<Option<OneOrMany<LDProof>>, IssuerError>;
 }
 
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Holder
+where
+    Self: Subject,
+{
+    // sign_vp takes a mutable reference to an unsigned VP and adds the holder's proof.
+    async fn sign_vp(
+        &self,
+        vp: &mut Presentation,
+        opts: &PresentationOptions,
+    ) -> Result<(), IssuerError>;
+    // generate_vp_jwt takes a VP and returns it formatted as a JWT signed by the holder.
+    async fn generate_vp_jwt(
+        &self,
+        vp: &Presentation,
+        opts: &PresentationOptions,
+    ) -> Result<String, IssuerError>;
+}
+
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 pub trait Content {
@@ -285,9 +583,184 @@ pub trait Content {
     fn types(&self) -> Result<Vec<String>, ContentError>;
 }
 
+// set_validity gives vc an expirationDate validity_minutes after its issuanceDate.
+pub fn set_validity(
+    vc: &mut Credential,
+    validity_minutes: Option<i64>,
+) -> Result<(), ContentError> {
+    let minutes = match validity_minutes {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+
+    // This is checked for a negative value or 0 and errs if one is found.
+    if minutes <= 0 {
+        return Err(ContentError::Invalid(
+            "Validity must be set to a number of minutes greater than 0".to_string(),
+        ));
+    }
+
+    let issued: DateTime<Utc> = match &vc.issuance_date {
+        Some(d) => d.clone().into(),
+        None => Utc::now(),
+    };
+    vc.expiration_date = Some((issued + Duration::minutes(minutes)).into());
+    Ok(())
+}
+
+// CLOCK_SKEW_SECONDS is how far ahead of the witness's clock a timestamp may be before it's
+// treated as not yet valid, so that a client or issuer with a slightly fast clock isn't refused.
+pub const CLOCK_SKEW_SECONDS: i64 = 60;
+
+// check_validity errs with the reason if vc is expired, or not yet valid.
+pub fn check_validity(vc: &Credential) -> Result<(), FlowError> {
+    let now = Utc::now();
+    if let Some(d) = &vc.expiration_date {
+        let exp: DateTime<Utc> = d.clone().into();
+        if exp <= now {
+            return Err(FlowError::Expired(format!(
+                "Credential expired at {}",
+                exp.to_rfc3339_opts(SecondsFormat::Secs, true)
+            )));
+        }
+    }
+
+    if let Some(d) = &vc.issuance_date {
+        let issued: DateTime<Utc> = d.clone().into();
+        if issued > now + Duration::seconds(CLOCK_SKEW_SECONDS) {
+            return Err(FlowError::NotYetValid(format!(
+                "Credential is not valid until {}",
+                issued.to_rfc3339_opts(SecondsFormat::Secs, true)
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// verify_jwt checks a JWT credential as Credential::verify_jwt does, except that ES256K-R JWTs
+// are checked by recovering the key from the signature, since the verification methods of the
+// did:pkh issuers that make them carry only an account, not the key.
+pub async fn verify_jwt(
+    jwt: &str,
+    options: Option<LinkedDataProofOptions>,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+) -> VerificationResult {
+    match ssi::jws::decode_unverified(jwt) {
+        Ok((header, _)) if header.algorithm == Algorithm::ES256KR => {}
+        _ => return Credential::verify_jwt(jwt, options, resolver, context_loader).await,
+    };
+
+    match verify_recoverable_jwt(jwt, options, resolver).await {
+        Ok(_) => VerificationResult::new(),
+        Err(e) => VerificationResult::error(&e),
+    }
+}
+
+async fn verify_recoverable_jwt(
+    jwt: &str,
+    options: Option<LinkedDataProofOptions>,
+    resolver: &dyn DIDResolver,
+) -> Result<(), String> {
+    let vc = Credential::from_jwt_unsigned(jwt).map_err(|e| format!("Invalid VC: {}", e))?;
+    check_validity(&vc).map_err(|e| e.to_string())?;
+    let (header_b64, payload_b64, signature_b64) =
+        ssi::jws::split_jws(jwt).map_err(|e| format!("Unable to split JWS: {}", e))?;
+    let decoded = ssi::jws::decode_jws_parts(header_b64, payload_b64.as_bytes(), signature_b64)
+        .map_err(|e| format!("Unable to decode JWS: {}", e))?;
+
+    let kid = decoded
+        .header
+        .key_id
+        .ok_or_else(|| "JWT header missing key id".to_string())?;
+    if let Some(URI::String(vm)) = options.and_then(|o| o.verification_method) {
+        if vm != kid {
+            return Err(format!("JWT key id {} is not {}", kid, vm));
+        }
+    }
+
+    // The key has to be one of the issuer's.
+    let issuer = vc.issuer.as_ref().map(|i| i.get_id()).unwrap_or_default();
+    if kid.split('#').next() != Some(issuer.as_str()) {
+        return Err(format!("JWT key id {} is not the issuer's", kid));
+    }
+
+    let key = ssi::jws::recover(
+        Algorithm::ES256KR,
+        &decoded.signing_input,
+        &decoded.signature,
+    )
+    .map_err(|e| format!("Unable to recover key from signature: {}", e))?;
+    resolve_vm(&kid, resolver)
+        .await
+        .map_err(|e| format!("Unable to resolve key for JWS: {}", e))?
+        .match_jwk(&key)
+        .map_err(|e| format!("Unable to verify signature: {}", e))
+}
+
 pub trait Statement {
     // From the an attestation structure, create an accurate statement for signing.
     fn generate_statement(&self) -> Result<String, StatementError>;
+
+    // The statement as EIP-712 typed data, for wallets that sign with eth_signTypedData_v4.
+    // Defaults to the plain-text statement in a single field. Only the attestation statements
+    // override it with structured fields, as only the attestation flow accepts typed data
+    // signatures; the verification flows check the plain-text statement alone.
+    fn generate_typed_data(&self) -> Result<TypedData, StatementError> {
+        statement_typed_data(&self.generate_statement()?)
+    }
+}
+
+// statement_typed_data wraps a plain-text statement as EIP-712 typed data.
+pub fn statement_typed_data(statement: &str) -> Result<TypedData, StatementError> {
+    typed_data(
+        "Statement",
+        &[("statement".to_string(), statement.to_string())],
+    )
+}
+
+// typed_data builds EIP-712 typed data of string fields under the Rebase domain.
+pub fn typed_data(
+    primary_type: &str,
+    fields: &[(String, String)],
+) -> Result<TypedData, StatementError> {
+    let types: Vec<serde_json::Value> = fields
+        .iter()
+        .map(|(k, _)| json!({"name": k, "type": "string"}))
+        .collect();
+    let message: serde_json::Map<String, serde_json::Value> =
+        fields.iter().map(|(k, v)| (k.clone(), json!(v))).collect();
+
+    serde_json::from_value(json!({
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+            ],
+            primary_type: types,
+        },
+        "primaryType": primary_type,
+        "domain": {"name": "Rebase", "version": "1"},
+        "message": message,
+    }))
+    .map_err(|e| StatementError::Statement(format!("failed to build typed data: {}", e)))
+}
+
+// with_chain_id binds typed data to an EIP-155 chain by adding chainId to its domain, so a
+// signature over it can't be replayed on another chain.
+pub fn with_chain_id(typed_data: TypedData, chain_id: &str) -> Result<TypedData, StatementError> {
+    let err = |e: String| StatementError::Statement(format!("failed to bind typed data: {}", e));
+    let chain_id: i64 = chain_id
+        .parse()
+        .map_err(|_| err(format!("bad chain id {}", chain_id)))?;
+    let mut v = serde_json::to_value(typed_data).map_err(|e| err(e.to_string()))?;
+    v["types"]["EIP712Domain"]
+        .as_array_mut()
+        .ok_or_else(|| err("no EIP712Domain type".to_string()))?
+        .push(json!({"name": "chainId", "type": "uint256"}));
+    v["domain"]["chainId"] = json!(chain_id);
+    serde_json::from_value(v).map_err(|e| err(e.to_string()))
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -315,6 +788,10 @@ pub struct Instructions {
 pub struct StatementResponse {
     pub statement: String,
     pub delimiter: Option<String>,
+    // EIP-712 typed data that may be signed in place of the statement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tsify(type = "any")]
+    pub typed_data: Option<TypedData>,
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -330,10 +807,11 @@ where
         proof: P,
         issuer: I,
     ) -> Result<Credential, FlowError> {
-        let f = self.validate_proof(proof.clone(), issuer.clone());
-        let content = f.await?;
-        let f = content.credential(issuer.clone());
-        Ok(f.await?)
+        let f = self.unsigned_credential(proof, issuer.clone(), issuer.clone());
+        let mut vc = f.await?;
+        let f = issuer.sign_vc(&mut vc);
+        f.await?;
+        Ok(vc)
     }
 
     fn instructions(&self) -> Result<Instructions, FlowError>;
@@ -343,9 +821,9 @@ where
         proof: P,
         issuer: I,
     ) -> Result<String, FlowError> {
-        let f = self.validate_proof(proof, issuer.clone());
-        let content = f.await?;
-        let f = content.jwt(issuer);
+        let f = self.unsigned_credential(proof, issuer.clone(), issuer.clone());
+        let vc = f.await?;
+        let f = issuer.generate_jwt(&vc);
         Ok(f.await?)
     }
 
@@ -364,7 +842,14 @@ where
         let f = self.validate_proof(proof, issuer);
         let content = f.await?;
         let f = content.unsigned_credential(subj);
-        Ok(f.await?)
+        let mut vc = f.await?;
+        set_validity(&mut vc, self.validity_minutes())?;
+        Ok(vc)
+    }
+
+    // How long the flow's credentials are valid for after issuance, or forever if None.
+    fn validity_minutes(&self) -> Option<i64> {
+        None
     }
 
     async fn validate_proof<I: Issuer + Send>(&self, proof: P, issuer: I) -> Result<C, FlowError>;
@@ -381,11 +866,11 @@ pub enum AlchemyNetworks {
     PolygonMainnet,
 }
 
-impl std::string::ToString for AlchemyNetworks {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for AlchemyNetworks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AlchemyNetworks::EthMainnet => "eth-mainnet".to_string(),
-            AlchemyNetworks::PolygonMainnet => "polygon-mainnet".to_string(),
+            AlchemyNetworks::EthMainnet => write!(f, "eth-mainnet"),
+            AlchemyNetworks::PolygonMainnet => write!(f, "polygon-mainnet"),
         }
     }
 }
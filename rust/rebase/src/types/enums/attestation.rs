@@ -1,4 +1,7 @@
-use crate::types::{defs::Statement, error::StatementError};
+use crate::types::{
+    defs::{typed_data, Statement, TypedData},
+    error::StatementError,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
@@ -41,6 +44,22 @@ impl<T: Attestation> Statement for T {
 
         Ok(v.join("\n"))
     }
+
+    // Each content entry becomes a string field, so wallets can show them individually.
+    fn generate_typed_data(&self) -> Result<TypedData, StatementError> {
+        let (t, content) = self.to_statement()?;
+        t.validate(&content)?;
+        let mut fields: Vec<(String, String)> = content
+            .into_iter()
+            .map(|(k, v)| match v {
+                Value::String(s) => (k, s),
+                v => (k, v.to_string()),
+            })
+            .collect();
+        fields.sort();
+
+        typed_data(&format!("{:?}", t), &fields)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, EnumIter, JsonSchema, Serialize, Tsify, PartialEq)]
@@ -126,7 +145,7 @@ impl AttestationTypes {
         }
 
         for expected_key in content.keys() {
-            if h.get(expected_key).is_none() {
+            if !h.contains_key(expected_key) {
                 return Err(StatementError::Statement(format!(
                     "Found unknown key in content: {}",
                     expected_key
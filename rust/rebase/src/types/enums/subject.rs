@@ -1,5 +1,10 @@
-use crate::subject::{ed25519::Ed25519Jwk as Ed25519, ethereum::Eip155, solana::Solana};
-use crate::types::{defs::Subject, error::SubjectError};
+use crate::subject::{
+    bip122::Bip122, ed25519::Ed25519Jwk as Ed25519, ethereum::Eip155, solana::Solana, tezos::Tezos,
+};
+use crate::types::{
+    defs::{make_resolver, EthRpcOpts, ResolverOpts, Subject, TypedData},
+    error::SubjectError,
+};
 
 use async_trait::async_trait;
 use schemars::JsonSchema;
@@ -16,6 +21,8 @@ pub enum Subjects {
     Web(Web),
     #[serde(rename = "key")]
     Key(Key),
+    #[serde(rename = "jwk")]
+    Jwk(Jwk),
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
@@ -25,6 +32,13 @@ pub enum Key {
     Ed25519(Ed25519),
 }
 
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum Jwk {
+    #[serde(rename = "ed25519")]
+    Ed25519(Ed25519),
+}
+
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum Pkh {
@@ -32,6 +46,10 @@ pub enum Pkh {
     Eip155(Eip155),
     #[serde(rename = "solana")]
     Solana(Solana),
+    #[serde(rename = "tz")]
+    Tezos(Tezos),
+    #[serde(rename = "bip122")]
+    Bip122(Bip122),
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
@@ -48,8 +66,11 @@ impl Subject for Subjects {
         match &self {
             Subjects::Pkh(Pkh::Eip155(x)) => x.did(),
             Subjects::Pkh(Pkh::Solana(x)) => x.did(),
+            Subjects::Pkh(Pkh::Tezos(x)) => x.did(),
+            Subjects::Pkh(Pkh::Bip122(x)) => x.did(),
             Subjects::Web(Web::Ed25519(x)) => x.did(),
             Subjects::Key(Key::Ed25519(x)) => x.did(),
+            Subjects::Jwk(Jwk::Ed25519(x)) => x.did(),
         }
     }
 
@@ -57,8 +78,11 @@ impl Subject for Subjects {
         match &self {
             Subjects::Pkh(Pkh::Eip155(x)) => x.display_id(),
             Subjects::Pkh(Pkh::Solana(x)) => x.display_id(),
+            Subjects::Pkh(Pkh::Tezos(x)) => x.display_id(),
+            Subjects::Pkh(Pkh::Bip122(x)) => x.display_id(),
             Subjects::Web(Web::Ed25519(x)) => x.display_id(),
             Subjects::Key(Key::Ed25519(x)) => x.display_id(),
+            Subjects::Jwk(Jwk::Ed25519(x)) => x.display_id(),
         }
     }
 
@@ -66,8 +90,11 @@ impl Subject for Subjects {
         match &self {
             Subjects::Pkh(Pkh::Eip155(x)) => x.verification_method(),
             Subjects::Pkh(Pkh::Solana(x)) => x.verification_method(),
+            Subjects::Pkh(Pkh::Tezos(x)) => x.verification_method(),
+            Subjects::Pkh(Pkh::Bip122(x)) => x.verification_method(),
             Subjects::Web(Web::Ed25519(x)) => x.verification_method(),
             Subjects::Key(Key::Ed25519(x)) => x.verification_method(),
+            Subjects::Jwk(Jwk::Ed25519(x)) => x.verification_method(),
         }
     }
 
@@ -75,8 +102,41 @@ impl Subject for Subjects {
         match &self {
             Subjects::Pkh(Pkh::Eip155(x)) => x.valid_signature(statement, signature).await,
             Subjects::Pkh(Pkh::Solana(x)) => x.valid_signature(statement, signature).await,
+            Subjects::Pkh(Pkh::Tezos(x)) => x.valid_signature(statement, signature).await,
+            Subjects::Pkh(Pkh::Bip122(x)) => x.valid_signature(statement, signature).await,
             Subjects::Web(Web::Ed25519(x)) => x.valid_signature(statement, signature).await,
             Subjects::Key(Key::Ed25519(x)) => x.valid_signature(statement, signature).await,
+            Subjects::Jwk(Jwk::Ed25519(x)) => x.valid_signature(statement, signature).await,
+        }
+    }
+
+    async fn valid_typed_data_signature(
+        &self,
+        typed_data: &TypedData,
+        signature: &str,
+    ) -> Result<(), SubjectError> {
+        match &self {
+            Subjects::Pkh(Pkh::Eip155(x)) => {
+                x.valid_typed_data_signature(typed_data, signature).await
+            }
+            Subjects::Pkh(Pkh::Solana(x)) => {
+                x.valid_typed_data_signature(typed_data, signature).await
+            }
+            Subjects::Pkh(Pkh::Tezos(x)) => {
+                x.valid_typed_data_signature(typed_data, signature).await
+            }
+            Subjects::Pkh(Pkh::Bip122(x)) => {
+                x.valid_typed_data_signature(typed_data, signature).await
+            }
+            Subjects::Web(Web::Ed25519(x)) => {
+                x.valid_typed_data_signature(typed_data, signature).await
+            }
+            Subjects::Key(Key::Ed25519(x)) => {
+                x.valid_typed_data_signature(typed_data, signature).await
+            }
+            Subjects::Jwk(Jwk::Ed25519(x)) => {
+                x.valid_typed_data_signature(typed_data, signature).await
+            }
         }
     }
 }
@@ -88,8 +148,113 @@ impl Subjects {
         match &self {
             Subjects::Pkh(Pkh::Eip155(_)) => Ok("Ethereum Address".to_string()),
             Subjects::Pkh(Pkh::Solana(_)) => Ok("Solana Address".to_string()),
+            Subjects::Pkh(Pkh::Tezos(_)) => Ok("Tezos Address".to_string()),
+            Subjects::Pkh(Pkh::Bip122(_)) => Ok("Bitcoin Address".to_string()),
             Subjects::Web(Web::Ed25519(_)) => Ok("Ed25519 Web Key".to_string()),
             Subjects::Key(Key::Ed25519(_)) => Ok("Ed25519 DID Key".to_string()),
+            Subjects::Jwk(Jwk::Ed25519(_)) => Ok("Ed25519 DID JWK".to_string()),
+        }
+    }
+
+    // valid_signature_with_opts is valid_signature with did:web and did:key subjects resolved
+    // using the witness's ResolverOpts, and Ethereum contract wallets checked through its
+    // EthRpcOpts.
+    pub async fn valid_signature_with_opts(
+        &self,
+        statement: &str,
+        signature: &str,
+        resolver_opts: &Option<ResolverOpts>,
+        eth_rpc: &Option<EthRpcOpts>,
+    ) -> Result<(), SubjectError> {
+        match &self {
+            Subjects::Pkh(Pkh::Eip155(x)) => {
+                x.valid_signature_with_rpc(statement, signature, eth_rpc)
+                    .await
+            }
+            Subjects::Web(Web::Ed25519(x))
+            | Subjects::Key(Key::Ed25519(x))
+            | Subjects::Jwk(Jwk::Ed25519(x)) => {
+                x.valid_signature_with_resolver(statement, signature, &make_resolver(resolver_opts))
+                    .await
+            }
+            x => x.valid_signature(statement, signature).await,
+        }
+    }
+
+    // valid_typed_data_signature_with_rpc is valid_typed_data_signature with Ethereum contract
+    // wallets checked through the witness's EthRpcOpts.
+    pub async fn valid_typed_data_signature_with_rpc(
+        &self,
+        typed_data: &TypedData,
+        signature: &str,
+        eth_rpc: &Option<EthRpcOpts>,
+    ) -> Result<(), SubjectError> {
+        match &self {
+            Subjects::Pkh(Pkh::Eip155(x)) => {
+                x.valid_typed_data_signature_with_rpc(typed_data, signature, eth_rpc)
+                    .await
+            }
+            x => x.valid_typed_data_signature(typed_data, signature).await,
+        }
+    }
+
+    // from_did recovers the subject a DID was generated from, where the DID holds everything
+    // needed to check its signatures. Tezos subjects need their public key and did:web
+    // subjects their key name, so those can't be recovered.
+    pub fn from_did(did: &str) -> Result<Self, SubjectError> {
+        let parts: Vec<&str> = did.split(':').collect();
+        let subject = match parts.as_slice() {
+            ["did", "pkh", "eip155", chain_id, address] => Subjects::Pkh(Pkh::Eip155(Eip155 {
+                address: address.to_string(),
+                chain_id: chain_id.to_string(),
+            })),
+            ["did", "pkh", "solana", _, address] => Subjects::Pkh(Pkh::Solana(Solana {
+                address: address.to_string(),
+            })),
+            ["did", "pkh", "bip122", _, address] => Subjects::Pkh(Pkh::Bip122(Bip122 {
+                address: address.to_string(),
+            })),
+            ["did", "key", id] => Subjects::Key(Key::Ed25519(Ed25519::new(did, id)?)),
+            // did:jwk documents name their only verification method #0.
+            ["did", "jwk", _] => Subjects::Jwk(Jwk::Ed25519(Ed25519::new(did, "0")?)),
+            _ => {
+                return Err(SubjectError::Did(format!(
+                    "cannot recover subject from {}",
+                    did
+                )))
+            }
+        };
+
+        // Round trip so a DID for another network than the subject's is refused.
+        if subject.did()? != did {
+            return Err(SubjectError::Did(format!(
+                "cannot recover subject from {}",
+                did
+            )));
+        }
+
+        Ok(subject)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::util::{test_did_keypair, test_eth_did, test_solana_did};
+
+    #[tokio::test]
+    async fn test_from_did() {
+        let (key, _) = test_did_keypair().await.unwrap();
+        for subject in [test_eth_did(), test_solana_did(), key] {
+            let did = subject.did().unwrap();
+            assert_eq!(Subjects::from_did(&did).unwrap().did().unwrap(), did);
+        }
+
+        if Subjects::from_did("did:web:example.com").is_ok() {
+            panic!("Recovered did:web subject without its key name");
+        }
+        if Subjects::from_did("did:pkh:solana:wrongnetwork:address").is_ok() {
+            panic!("Recovered subject on another network");
         }
     }
 }
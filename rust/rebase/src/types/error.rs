@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use serde_json::Error as SerializeError;
 use thiserror::Error;
 
@@ -17,6 +18,14 @@ pub enum RebaseError {
     Proof(#[from] ProofError),
     #[error("flow error: {0}")]
     Flow(#[from] FlowError),
+    #[error("resolver error: {0}")]
+    Resolver(#[from] ResolverError),
+    #[error("presentation error: {0}")]
+    Presentation(#[from] PresentationError),
+    #[error("status error: {0}")]
+    Status(#[from] StatusError),
+    #[error("policy error: {0}")]
+    Policy(#[from] PolicyError),
 }
 
 #[derive(Debug, Error)]
@@ -33,6 +42,10 @@ pub enum SubjectError {
     Validation(String),
     #[error("failed to generate did string: {0}")]
     Did(String),
+    // The signature couldn't be checked, e.g. because the DID resolver or a JSON-RPC endpoint
+    // failed, which says nothing about whether it is valid.
+    #[error("could not check signature: {0}")]
+    Upstream(String),
 }
 
 #[derive(Debug, Error)]
@@ -87,6 +100,20 @@ pub enum FlowError {
     BadLookup(String),
     #[error("failed to validate proof: {0}")]
     Validation(String),
+    #[error("upstream service unavailable: {0}")]
+    Upstream(String),
+    #[error("evidence not found: {0}")]
+    NotFound(String),
+    #[error("invalid signature: {0}")]
+    Signature(String),
+    #[error("outside of validity window: {0}")]
+    Expired(String),
+    #[error("not yet valid: {0}")]
+    NotYetValid(String),
+    #[error("unsupported subject: {0}")]
+    UnsupportedSubject(String),
+    #[error("flow is misconfigured: {0}")]
+    Misconfigured(String),
     #[error("{0}")]
     Content(#[from] ContentError),
     #[error("{0}")]
@@ -97,4 +124,254 @@ pub enum FlowError {
     Subject(#[from] SubjectError),
     #[error("{0}")]
     Issuer(#[from] IssuerError),
+    #[error("{0}")]
+    Presentation(#[from] PresentationError),
+    #[error("{0}")]
+    Status(#[from] StatusError),
+    #[error("{0}")]
+    Policy(#[from] PolicyError),
+}
+
+impl FlowError {
+    // subject_error returns the SubjectError the error was caused by, if any.
+    fn subject_error(&self) -> Option<&SubjectError> {
+        match self {
+            FlowError::Subject(e)
+            | FlowError::Proof(ProofError::Subject(e))
+            | FlowError::Statement(StatementError::Subject(e))
+            | FlowError::Content(ContentError::Subject(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    // code is a stable identifier for the kind of error, for callers to match on instead of
+    // the message.
+    pub fn code(&self) -> &'static str {
+        match self.subject_error() {
+            Some(SubjectError::Validation(_)) => return "signature_invalid",
+            Some(SubjectError::Upstream(_)) => return "upstream_unavailable",
+            _ => {}
+        }
+
+        match self {
+            FlowError::BadLookup(_) => "lookup_failed",
+            FlowError::Validation(_) => "validation_failed",
+            FlowError::Upstream(_) => "upstream_unavailable",
+            FlowError::NotFound(_) => "evidence_not_found",
+            FlowError::Signature(_) => "signature_invalid",
+            FlowError::Expired(_) => "window_expired",
+            FlowError::NotYetValid(_) => "not_yet_valid",
+            FlowError::UnsupportedSubject(_) => "unsupported_subject",
+            FlowError::Misconfigured(_) => "misconfigured_flow",
+            FlowError::Subject(_) => "unsupported_subject",
+            FlowError::Content(_) => "invalid_content",
+            FlowError::Proof(_) => "invalid_proof",
+            FlowError::Statement(_) => "invalid_statement",
+            FlowError::Issuer(_) => "issuer_failed",
+            FlowError::Presentation(_) => "invalid_presentation",
+            FlowError::Status(StatusError::Revoked(_)) => "credential_revoked",
+            FlowError::Status(_) => "status_unavailable",
+            FlowError::Policy(_) => "policy_violation",
+        }
+    }
+
+    // status is the HTTP status code a service should respond with for the error.
+    pub fn status(&self) -> u16 {
+        if let Some(SubjectError::Upstream(_)) = self.subject_error() {
+            return 502;
+        }
+
+        match self {
+            FlowError::Upstream(_) | FlowError::Status(StatusError::Load(_)) => 502,
+            FlowError::Misconfigured(_) | FlowError::Issuer(_) => 500,
+            FlowError::NotFound(_) => 404,
+            FlowError::Policy(_) => 403,
+            _ => 400,
+        }
+    }
+
+    // title summarizes the kind of error, the same way for every occurrence of it.
+    pub fn title(&self) -> &'static str {
+        match self.code() {
+            "lookup_failed" => "Evidence lookup failed",
+            "validation_failed" => "Proof is invalid",
+            "upstream_unavailable" => "Upstream service unavailable",
+            "evidence_not_found" => "Evidence not found",
+            "signature_invalid" => "Signature is invalid",
+            "window_expired" => "Outside of validity window",
+            "not_yet_valid" => "Not yet valid",
+            "unsupported_subject" => "Subject is not supported",
+            "misconfigured_flow" => "Witness is misconfigured",
+            "invalid_content" => "Credential content is invalid",
+            "invalid_proof" => "Proof is invalid",
+            "invalid_statement" => "Statement is invalid",
+            "issuer_failed" => "Issuer failed",
+            "invalid_presentation" => "Presentation is invalid",
+            "credential_revoked" => "Credential is revoked",
+            "status_unavailable" => "Credential status unavailable",
+            "policy_violation" => "Credential does not meet the verification policy",
+            _ => "Request failed",
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        Problem {
+            type_: format!("{}{}", PROBLEM_TYPE_PREFIX, self.code()),
+            title: self.title().to_string(),
+            code: self.code().to_string(),
+            status: self.status(),
+            detail: self.to_string(),
+        }
+    }
+}
+
+// Problem types are URNs named after the error code, since there are no pages describing them.
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:rebase:problem:";
+
+fn about_blank() -> String {
+    "about:blank".to_string()
+}
+
+// Problem is the body of an error response from a witness service, as an RFC 7807 problem
+// details object with the code as an extension member. The code is stable across releases
+// where the detail is not.
+#[derive(Clone, Debug, Deserialize, Error, PartialEq, Serialize)]
+#[error("{code}: {detail}")]
+pub struct Problem {
+    #[serde(rename = "type", default = "about_blank")]
+    pub type_: String,
+    #[serde(default)]
+    pub title: String,
+    pub code: String,
+    pub status: u16,
+    pub detail: String,
+}
+
+#[derive(Debug, Error)]
+pub enum PresentationError {
+    #[error("{0}")]
+    Subject(#[from] SubjectError),
+    #[error("{0}")]
+    Issuer(#[from] IssuerError),
+    #[error{"serialization error: {0}"}]
+    Serialize(#[from] SerializeError),
+    #[error("invalid credential in presentation: {0}")]
+    Credential(String),
+    #[error("credential is not bound to holder: {0}")]
+    HolderBinding(String),
+}
+
+#[derive(Debug, Error)]
+pub enum StatusError {
+    #[error("{0}")]
+    Issuer(#[from] IssuerError),
+    #[error{"serialization error: {0}"}]
+    Serialize(#[from] SerializeError),
+    #[error("failed to update status list: {0}")]
+    Store(String),
+    #[error("invalid status list: {0}")]
+    List(String),
+    #[error("failed to load status list: {0}")]
+    Load(String),
+    #[error("credential status is {0}")]
+    Revoked(String),
+}
+
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error("invalid verification policy: {0}")]
+    Invalid(String),
+    #[error("issuer is not trusted: {0}")]
+    Issuer(String),
+    #[error("credential type is not allowed: {0}")]
+    Type(String),
+    #[error("credential is too old: {0}")]
+    Age(String),
+    #[error("credential is missing evidence: {0}")]
+    Evidence(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ResolverError {
+    #[error("failed to load did documents: {0}")]
+    Load(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problem() {
+        let cases = [
+            (
+                FlowError::Upstream("x".to_string()),
+                "upstream_unavailable",
+                502,
+            ),
+            (
+                FlowError::NotFound("x".to_string()),
+                "evidence_not_found",
+                404,
+            ),
+            (FlowError::Expired("x".to_string()), "window_expired", 400),
+            (
+                FlowError::NotYetValid("x".to_string()),
+                "not_yet_valid",
+                400,
+            ),
+            (
+                FlowError::Misconfigured("x".to_string()),
+                "misconfigured_flow",
+                500,
+            ),
+            (
+                FlowError::Subject(SubjectError::Validation("x".to_string())),
+                "signature_invalid",
+                400,
+            ),
+            (
+                FlowError::Proof(ProofError::Subject(SubjectError::Validation(
+                    "x".to_string(),
+                ))),
+                "signature_invalid",
+                400,
+            ),
+            (
+                FlowError::Status(StatusError::Revoked("revoked".to_string())),
+                "credential_revoked",
+                400,
+            ),
+            (
+                FlowError::Policy(PolicyError::Issuer("did:key:x".to_string())),
+                "policy_violation",
+                403,
+            ),
+            (
+                FlowError::Subject(SubjectError::Upstream("x".to_string())),
+                "upstream_unavailable",
+                502,
+            ),
+            (
+                FlowError::Content(ContentError::Subject(SubjectError::Upstream(
+                    "x".to_string(),
+                ))),
+                "upstream_unavailable",
+                502,
+            ),
+        ];
+
+        for (e, code, status) in cases {
+            let problem = e.problem();
+            assert_eq!(problem.code, code);
+            assert_eq!(problem.status, status);
+            assert_eq!(problem.detail, e.to_string());
+            assert_eq!(problem.type_, format!("urn:rebase:problem:{}", code));
+            assert_eq!(problem.title, e.title());
+
+            let decoded: Problem =
+                serde_json::from_value(serde_json::to_value(&problem).unwrap()).unwrap();
+            assert_eq!(decoded, problem);
+        }
+    }
 }
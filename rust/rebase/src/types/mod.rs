@@ -2,3 +2,7 @@ pub mod capability;
 pub mod defs;
 pub mod enums;
 pub mod error;
+pub mod nonce;
+pub mod policy;
+pub mod presentation;
+pub mod status;
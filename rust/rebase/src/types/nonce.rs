@@ -0,0 +1,309 @@
+use crate::types::{defs::MaybeSendSync, error::FlowError};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+use tsify::Tsify;
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+// RateLimit caps how many requests a single subject may make to a flow.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RateLimit {
+    pub max_requests: u64,
+    // This is checked for a negative value or 0 and errs if one is found.
+    pub window_minutes: i64,
+}
+
+// NonceStore remembers the challenges and SIWE nonces flows have accepted, so they can't be
+// replayed, and counts requests for rate limiting. Witnesses running more than one instance
+// should back it with shared storage.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait NonceStore: std::fmt::Debug + MaybeSendSync {
+    // consume marks key as used until expires, returning false if it already was.
+    async fn consume(&self, key: &str, expires: DateTime<Utc>) -> Result<bool, FlowError>;
+    // bind ties key to value until expires, returning false if key is bound to another value.
+    async fn bind(&self, key: &str, value: &str, expires: DateTime<Utc>)
+        -> Result<bool, FlowError>;
+    // hit records a request under key, returning false if it goes over limit.
+    async fn hit(&self, key: &str, limit: &RateLimit) -> Result<bool, FlowError>;
+}
+
+#[derive(Debug)]
+struct UsedKey {
+    value: String,
+    // When the key can be forgotten.
+    expires: DateTime<Utc>,
+}
+
+// MemoryNonceStore is the in-process NonceStore used when none is configured.
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    used: Mutex<BTreeMap<String, UsedKey>>,
+    // When each recent request under a key falls out of its rate limit window.
+    hits: Mutex<BTreeMap<String, VecDeque<DateTime<Utc>>>>,
+}
+
+impl MemoryNonceStore {
+    pub const fn new() -> Self {
+        MemoryNonceStore {
+            used: Mutex::new(BTreeMap::new()),
+            hits: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn claim(
+        &self,
+        key: &str,
+        value: &str,
+        expires: DateTime<Utc>,
+        reusable: bool,
+    ) -> Result<bool, FlowError> {
+        let mut used = self
+            .used
+            .lock()
+            .map_err(|e| FlowError::BadLookup(e.to_string()))?;
+        let now = Utc::now();
+        used.retain(|_, u| u.expires > now);
+
+        match used.get(key) {
+            Some(u) => Ok(reusable && u.value == value),
+            None => {
+                used.insert(
+                    key.to_string(),
+                    UsedKey {
+                        value: value.to_string(),
+                        expires,
+                    },
+                );
+                Ok(true)
+            }
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl NonceStore for MemoryNonceStore {
+    async fn consume(&self, key: &str, expires: DateTime<Utc>) -> Result<bool, FlowError> {
+        self.claim(key, "", expires, false)
+    }
+
+    async fn bind(
+        &self,
+        key: &str,
+        value: &str,
+        expires: DateTime<Utc>,
+    ) -> Result<bool, FlowError> {
+        self.claim(key, value, expires, true)
+    }
+
+    async fn hit(&self, key: &str, limit: &RateLimit) -> Result<bool, FlowError> {
+        let mut hits = self
+            .hits
+            .lock()
+            .map_err(|e| FlowError::BadLookup(e.to_string()))?;
+        let now = Utc::now();
+        hits.retain(|_, v| v.back().is_some_and(|t| *t > now));
+
+        let v = hits.entry(key.to_string()).or_default();
+        while v.front().is_some_and(|t| *t <= now) {
+            v.pop_front();
+        }
+
+        if v.len() as u64 >= limit.max_requests {
+            return Ok(false);
+        }
+        v.push_back(now + Duration::minutes(limit.window_minutes));
+        Ok(true)
+    }
+}
+
+static DEFAULT_NONCE_STORE: MemoryNonceStore = MemoryNonceStore::new();
+
+// nonce_store returns the configured store, or the in-process default shared by all flows.
+pub fn nonce_store(store: &Option<Arc<dyn NonceStore>>) -> &dyn NonceStore {
+    match store {
+        Some(s) => s.as_ref(),
+        None => &DEFAULT_NONCE_STORE,
+    }
+}
+
+// check_rate_limit records a request by subject to a flow and errs if it is over limit.
+pub async fn check_rate_limit(
+    store: &dyn NonceStore,
+    limit: &Option<RateLimit>,
+    flow: &str,
+    subject: &str,
+) -> Result<(), FlowError> {
+    let limit = match limit {
+        Some(l) => l,
+        None => return Ok(()),
+    };
+
+    if limit.window_minutes <= 0 {
+        return Err(FlowError::Misconfigured(
+            "Rate limit window must be set to a number of minutes greater than 0".to_string(),
+        ));
+    }
+
+    if !store.hit(&format!("{}:{}", flow, subject), limit).await? {
+        return Err(FlowError::Validation(format!(
+            "Too many requests from {}, try again in {} minutes",
+            subject, limit.window_minutes
+        )));
+    }
+
+    Ok(())
+}
+
+// consume_challenge errs if a flow has already accepted the challenge.
+pub async fn consume_challenge(
+    store: &dyn NonceStore,
+    flow: &str,
+    challenge: &str,
+    expires: DateTime<Utc>,
+) -> Result<(), FlowError> {
+    if !store
+        .consume(&format!("{}:{}", flow, challenge), expires)
+        .await?
+    {
+        return Err(FlowError::Validation(
+            "Challenge has already been used".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+// issue_challenge returns a new challenge that redeem_challenge accepts once for scope, for the
+// next ttl_minutes.
+pub async fn issue_challenge(
+    store: &dyn NonceStore,
+    scope: &str,
+    ttl_minutes: i64,
+) -> Result<String, FlowError> {
+    let challenge = Uuid::new_v4().to_string();
+    if !store
+        .bind(
+            &format!("{}:{}", scope, challenge),
+            "issued",
+            Utc::now() + Duration::minutes(ttl_minutes),
+        )
+        .await?
+    {
+        return Err(FlowError::Validation(
+            "Challenge has already been issued".to_string(),
+        ));
+    }
+
+    Ok(challenge)
+}
+
+// redeem_challenge errs unless issue_challenge issued challenge for scope in the last
+// ttl_minutes, and it hasn't been redeemed since.
+pub async fn redeem_challenge(
+    store: &dyn NonceStore,
+    scope: &str,
+    challenge: &str,
+    ttl_minutes: i64,
+) -> Result<(), FlowError> {
+    let key = format!("{}:{}", scope, challenge);
+    let expires = Utc::now() + Duration::minutes(ttl_minutes);
+    // A NonceStore can't look keys up, but only an issued challenge is bound to another value.
+    // Binding one that wasn't issued also stops it from being used later.
+    if store.bind(&key, "redeemed", expires).await? {
+        return Err(FlowError::Validation(
+            "Challenge was not issued by this verifier or has expired".to_string(),
+        ));
+    }
+
+    consume_challenge(store, scope, &format!("{}:redeemed", challenge), expires).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_nonce_store() {
+        let store = MemoryNonceStore::new();
+        let later = Utc::now() + Duration::minutes(5);
+
+        assert!(store.consume("a", later).await.unwrap());
+        assert!(!store.consume("a", later).await.unwrap());
+        // Expired keys are forgotten.
+        assert!(store
+            .consume("b", Utc::now() - Duration::minutes(1))
+            .await
+            .unwrap());
+        assert!(store.consume("b", later).await.unwrap());
+
+        assert!(store.bind("c", "1", later).await.unwrap());
+        assert!(store.bind("c", "1", later).await.unwrap());
+        assert!(!store.bind("c", "2", later).await.unwrap());
+        assert!(!store.consume("c", later).await.unwrap());
+
+        let limit = Some(RateLimit {
+            max_requests: 2,
+            window_minutes: 10,
+        });
+        check_rate_limit(&store, &limit, "test", "alice")
+            .await
+            .unwrap();
+        check_rate_limit(&store, &limit, "test", "alice")
+            .await
+            .unwrap();
+        if check_rate_limit(&store, &limit, "test", "alice")
+            .await
+            .is_ok()
+        {
+            panic!("Allowed request over the rate limit");
+        }
+        check_rate_limit(&store, &limit, "test", "bob")
+            .await
+            .unwrap();
+        check_rate_limit(&store, &None, "test", "alice")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_challenges() {
+        let store = MemoryNonceStore::new();
+        let ttl = 5;
+
+        let challenge = issue_challenge(&store, "test", ttl).await.unwrap();
+        if redeem_challenge(&store, "other", &challenge, ttl)
+            .await
+            .is_ok()
+        {
+            panic!("Redeemed a challenge issued for another scope");
+        }
+        redeem_challenge(&store, "test", &challenge, ttl)
+            .await
+            .unwrap();
+        if redeem_challenge(&store, "test", &challenge, ttl)
+            .await
+            .is_ok()
+        {
+            panic!("Redeemed a challenge twice");
+        }
+
+        // Challenges the verifier didn't issue are refused, even if tried again.
+        for _ in 0..2 {
+            if redeem_challenge(&store, "test", "made-up", ttl)
+                .await
+                .is_ok()
+            {
+                panic!("Redeemed a challenge that wasn't issued");
+            }
+        }
+    }
+}
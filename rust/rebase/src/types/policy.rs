@@ -0,0 +1,166 @@
+use crate::types::{defs::Credential, error::PolicyError};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+// VerificationPolicy is what a verifier requires of a credential beyond a valid signature.
+// Without one, a credential self-issued by any DID would be accepted. Empty fields are not
+// checked.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct VerificationPolicy {
+    // The DIDs of the witnesses whose credentials are accepted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issuers: Vec<String>,
+    // The credential must have at least one of these types.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    // How long after its issuance date a credential is accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_minutes: Option<i64>,
+    // Fields that must be present in the credential's evidence.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_evidence: Vec<String>,
+}
+
+impl VerificationPolicy {
+    // or_issuer returns policy, trusting only issuer if it doesn't name the issuers it trusts.
+    pub fn or_issuer(policy: &Option<Self>, issuer: &str) -> Self {
+        let mut policy = policy.clone().unwrap_or_default();
+        if policy.issuers.is_empty() {
+            policy.issuers.push(issuer.to_string());
+        }
+        policy
+    }
+
+    // check errs with the first requirement of the policy that vc doesn't meet.
+    pub fn check(&self, vc: &Credential) -> Result<(), PolicyError> {
+        if !self.issuers.is_empty() {
+            let issuer = vc
+                .issuer
+                .as_ref()
+                .map(|i| i.get_id())
+                .ok_or_else(|| PolicyError::Issuer("no issuer found".to_string()))?;
+            if !self.issuers.contains(&issuer) {
+                return Err(PolicyError::Issuer(issuer));
+            }
+        }
+
+        if !self.types.is_empty()
+            && !vc
+                .type_
+                .clone()
+                .into_iter()
+                .any(|t| self.types.contains(&t))
+        {
+            let types: Vec<String> = vc.type_.clone().into_iter().collect();
+            return Err(PolicyError::Type(types.join(", ")));
+        }
+
+        if let Some(minutes) = self.max_age_minutes {
+            // This is checked for a negative value or 0 and errs if one is found.
+            if minutes <= 0 {
+                return Err(PolicyError::Invalid(
+                    "Max age must be set to a number of minutes greater than 0".to_string(),
+                ));
+            }
+
+            let issued: DateTime<Utc> = match &vc.issuance_date {
+                Some(d) => d.clone().into(),
+                None => return Err(PolicyError::Age("no issuance date found".to_string())),
+            };
+            if issued + Duration::minutes(minutes) < Utc::now() {
+                return Err(PolicyError::Age(format!(
+                    "issued at {}",
+                    issued.to_rfc3339_opts(SecondsFormat::Secs, true)
+                )));
+            }
+        }
+
+        let evidence: Vec<serde_json::Value> = vc
+            .evidence
+            .clone()
+            .into_iter()
+            .flatten()
+            .filter_map(|e| serde_json::to_value(e).ok())
+            .collect();
+        for field in &self.required_evidence {
+            if !evidence
+                .iter()
+                .any(|e| e.get(field).map(|v| !v.is_null()).unwrap_or(false))
+            {
+                return Err(PolicyError::Evidence(field.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::github_verification::GitHubVerificationContent,
+        test_util::util::test_did_keypair,
+        types::defs::{Content, Subject},
+    };
+
+    #[tokio::test]
+    async fn test_verification_policy() {
+        let (subject, witness) = test_did_keypair().await.unwrap();
+        let content = GitHubVerificationContent {
+            gist_id: "1".to_string(),
+            handle: "example".to_string(),
+            subject,
+            statement: "unimplemented".to_string(),
+            signature: "unimplemented".to_string(),
+        };
+
+        let vc = content.credential(witness.clone()).await.unwrap();
+        let policy = VerificationPolicy {
+            issuers: vec![witness.did().unwrap()],
+            types: vec!["GitHubVerification".to_string()],
+            max_age_minutes: Some(10),
+            required_evidence: vec!["handle".to_string(), "gistId".to_string()],
+        };
+        policy.check(&vc).unwrap();
+
+        // Self-issued by a key other than the witness.
+        let (_, attacker) = test_did_keypair().await.unwrap();
+        let self_issued = content.credential(attacker).await.unwrap();
+        if policy.check(&self_issued).is_ok() {
+            panic!("Accepted credential from untrusted issuer");
+        }
+
+        let default = VerificationPolicy::or_issuer(&None, &witness.did().unwrap());
+        default.check(&vc).unwrap();
+        if default.check(&self_issued).is_ok() {
+            panic!("Accepted credential from another issuer by default");
+        }
+
+        let wrong_type = VerificationPolicy {
+            types: vec!["TwitterVerification".to_string()],
+            ..policy.clone()
+        };
+        if wrong_type.check(&vc).is_ok() {
+            panic!("Accepted credential of disallowed type");
+        }
+
+        let missing_evidence = VerificationPolicy {
+            required_evidence: vec!["tweetUrl".to_string()],
+            ..policy.clone()
+        };
+        if missing_evidence.check(&vc).is_ok() {
+            panic!("Accepted credential missing evidence");
+        }
+
+        let mut old = vc.clone();
+        old.issuance_date = Some((Utc::now() - Duration::minutes(11)).into());
+        if policy.check(&old).is_ok() {
+            panic!("Accepted credential older than max age");
+        }
+    }
+}
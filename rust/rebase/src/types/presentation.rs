@@ -0,0 +1,340 @@
+use crate::types::{
+    defs::{
+        Credential, CredentialOrJWT, Holder, LinkedDataProofOptions, Presentation, Subject, URI,
+    },
+    error::PresentationError,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use ssi::vc::ProofPurpose;
+use std::collections::BTreeSet;
+use tsify::Tsify;
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+// PresentationOptions are chosen by the verifier a presentation is made for, so it can't be
+// replayed to another verifier or for another request.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PresentationOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+impl PresentationOptions {
+    // proof_options returns the linked data proof options a holder signs with, or a verifier
+    // checks for, when authenticating with these options.
+    pub fn proof_options(&self, verification_method: Option<String>) -> LinkedDataProofOptions {
+        LinkedDataProofOptions {
+            verification_method: verification_method.map(URI::String),
+            proof_purpose: Some(ProofPurpose::Authentication),
+            challenge: self.challenge.clone(),
+            domain: self.domain.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+fn did_of(id: &str) -> String {
+    id.split('#').next().unwrap_or_default().to_string()
+}
+
+fn is_same_controller(vc: &Credential) -> bool {
+    vc.type_.contains(&"SameControllerAssertion".to_string())
+}
+
+fn issuer_of(vc: &Credential) -> Option<String> {
+    vc.issuer.as_ref().map(|i| i.get_id())
+}
+
+// subject_dids returns the DIDs a Rebase credential is about: the id of its credentialSubject,
+// or both ids of a SameControllerAssertion.
+fn subject_dids(vc: &Credential) -> Vec<String> {
+    let mut dids = Vec::new();
+    for s in &vc.credential_subject {
+        if let Some(id) = &s.id {
+            dids.push(did_of(&id.to_string()));
+        }
+
+        if let Some(p) = &s.property_set {
+            for k in ["id1", "id2"] {
+                if let Some(Value::String(id)) = p.get(k) {
+                    dids.push(did_of(id));
+                }
+            }
+        }
+    }
+
+    dids
+}
+
+fn decode_credential(c: &CredentialOrJWT) -> Result<Credential, PresentationError> {
+    match c {
+        CredentialOrJWT::Credential(vc) => Ok(vc.clone()),
+        CredentialOrJWT::JWT(jwt) => Credential::from_jwt_unsigned(jwt)
+            .map_err(|e| PresentationError::Credential(e.to_string())),
+    }
+}
+
+// bound_dids returns the DIDs the holder is bound to by the presented credentials: their
+// own, and any that a presented SameControllerAssertion by one of issuers links to one
+// already bound. Anyone can self-issue a SameControllerAssertion, so others bind nothing.
+fn bound_dids(holder: &str, credentials: &[Credential], issuers: &[String]) -> BTreeSet<String> {
+    let mut bound = BTreeSet::from([did_of(holder)]);
+    loop {
+        let before = bound.len();
+        for vc in credentials.iter().filter(|vc| {
+            is_same_controller(vc) && issuer_of(vc).is_some_and(|i| issuers.contains(&i))
+        }) {
+            let dids = subject_dids(vc);
+            if dids.iter().any(|d| bound.contains(d)) {
+                bound.extend(dids);
+            }
+        }
+
+        if bound.len() == before {
+            return bound;
+        }
+    }
+}
+
+// credentials returns the credentials in vp, decoding any presented as JWTs. Their proofs
+// are not checked.
+pub fn credentials(vp: &Presentation) -> Result<Vec<Credential>, PresentationError> {
+    vp.verifiable_credential
+        .iter()
+        .flatten()
+        .map(decode_credential)
+        .collect()
+}
+
+// check_holder_binding errs unless every credential in vp is about its holder, or about a
+// DID bound to its holder by a SameControllerAssertion in vp issued by one of issuers.
+pub fn check_holder_binding(
+    vp: &Presentation,
+    issuers: &[String],
+) -> Result<(), PresentationError> {
+    let holder = vp
+        .holder
+        .as_ref()
+        .ok_or_else(|| PresentationError::HolderBinding("presentation has no holder".to_string()))?
+        .to_string();
+
+    let credentials = credentials(vp)?;
+    if credentials.is_empty() {
+        return Err(PresentationError::Credential(
+            "presentation has no credentials".to_string(),
+        ));
+    }
+
+    let bound = bound_dids(&holder, &credentials, issuers);
+    for vc in &credentials {
+        let dids = subject_dids(vc);
+        if !dids.iter().any(|d| bound.contains(d)) {
+            return Err(PresentationError::HolderBinding(format!(
+                "{} is about {} not {}",
+                vc.id
+                    .as_ref()
+                    .map(|id| String::from(id.clone()))
+                    .unwrap_or_else(|| "credential".to_string()),
+                dids.join(", "),
+                holder
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// unsigned_presentation bundles credentials issued about holder into a VP.
+pub fn unsigned_presentation<H: Subject>(
+    holder: &H,
+    credentials: Vec<CredentialOrJWT>,
+) -> Result<Presentation, PresentationError> {
+    let vp: Presentation = serde_json::from_value(json!({
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "id": format!("urn:uuid:{}", Uuid::new_v4()),
+        "type": ["VerifiablePresentation"],
+        "holder": holder.did()?,
+        "verifiableCredential": credentials,
+    }))?;
+
+    // The holder trusts the witnesses of its own credentials; verifiers check the binding
+    // against the issuers they trust.
+    let issuers: Vec<String> = self::credentials(&vp)?
+        .iter()
+        .filter_map(issuer_of)
+        .collect();
+    check_holder_binding(&vp, &issuers)?;
+    Ok(vp)
+}
+
+// presentation returns a VP of credentials with a linked data proof by holder.
+pub async fn presentation<H: Holder>(
+    holder: &H,
+    credentials: Vec<CredentialOrJWT>,
+    opts: &PresentationOptions,
+) -> Result<Presentation, PresentationError> {
+    let mut vp = unsigned_presentation(holder, credentials)?;
+    holder.sign_vp(&mut vp, opts).await?;
+    Ok(vp)
+}
+
+// presentation_jwt returns a VP of credentials as a JWT signed by holder.
+pub async fn presentation_jwt<H: Holder>(
+    holder: &H,
+    credentials: Vec<CredentialOrJWT>,
+    opts: &PresentationOptions,
+) -> Result<String, PresentationError> {
+    let vp = unsigned_presentation(holder, credentials)?;
+    Ok(holder.generate_vp_jwt(&vp, opts).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::{
+            github_verification::GitHubVerificationContent,
+            same_controller_assertion::SameControllerAssertionContent,
+        },
+        context::context_loader::context_loader,
+        test_util::util::{test_did_keypair, test_secp256k1_keypair},
+        types::{
+            defs::{make_resolver, Content},
+            enums::subject::Subjects,
+        },
+    };
+
+    fn github(subject: Subjects) -> GitHubVerificationContent {
+        GitHubVerificationContent {
+            gist_id: "1".to_string(),
+            handle: "example".to_string(),
+            subject,
+            statement: "unimplemented".to_string(),
+            signature: "unimplemented".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_presentation() {
+        let (witness_subj, witness) = test_did_keypair().await.unwrap();
+        let (subj1, holder) = test_did_keypair().await.unwrap();
+        let (subj2, _) = test_did_keypair().await.unwrap();
+        let (subj3, _) = test_did_keypair().await.unwrap();
+
+        let own = CredentialOrJWT::Credential(
+            github(subj1.clone())
+                .credential(witness.clone())
+                .await
+                .unwrap(),
+        );
+        let linked =
+            CredentialOrJWT::JWT(github(subj2.clone()).jwt(witness.clone()).await.unwrap());
+        let same_controller_content = SameControllerAssertionContent {
+            id1: subj1,
+            id2: subj2,
+            statement: "unimplemented".to_string(),
+            signature1: "unimplemented".to_string(),
+            signature2: "unimplemented".to_string(),
+        };
+        let same_controller = CredentialOrJWT::Credential(
+            same_controller_content
+                .credential(witness.clone())
+                .await
+                .unwrap(),
+        );
+        let self_issued = CredentialOrJWT::Credential(
+            same_controller_content
+                .credential(holder.clone())
+                .await
+                .unwrap(),
+        );
+        let unrelated =
+            CredentialOrJWT::Credential(github(subj3).credential(witness.clone()).await.unwrap());
+
+        let opts = PresentationOptions {
+            challenge: Some(Uuid::new_v4().to_string()),
+            domain: Some("example.com".to_string()),
+        };
+        let bundle = vec![own.clone(), linked.clone(), same_controller];
+
+        let vp = presentation(&holder, bundle.clone(), &opts).await.unwrap();
+        let trusted = vec![witness_subj.did().unwrap()];
+        check_holder_binding(&vp, &trusted).unwrap();
+        let res = vp
+            .verify(
+                Some(opts.proof_options(None)),
+                &make_resolver(&None),
+                &mut context_loader().unwrap(),
+            )
+            .await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+
+        let replayed = PresentationOptions {
+            challenge: Some(Uuid::new_v4().to_string()),
+            ..opts.clone()
+        };
+        let res = vp
+            .verify(
+                Some(replayed.proof_options(None)),
+                &make_resolver(&None),
+                &mut context_loader().unwrap(),
+            )
+            .await;
+        assert!(
+            !res.errors.is_empty(),
+            "Accepted presentation for another challenge"
+        );
+
+        let jwt = presentation_jwt(&holder, bundle, &opts).await.unwrap();
+        let res = Presentation::verify_jwt(
+            &jwt,
+            Some(opts.proof_options(None)),
+            &make_resolver(&None),
+            &mut context_loader().unwrap(),
+        )
+        .await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+
+        // Without the SameControllerAssertion, the linked credential isn't bound to the holder.
+        if unsigned_presentation(&holder, vec![own.clone(), linked.clone()]).is_ok() {
+            panic!("Presented credential about another subject");
+        }
+
+        if unsigned_presentation(&holder, vec![own.clone(), unrelated]).is_ok() {
+            panic!("Presented credential about an unrelated subject");
+        }
+
+        // A SameControllerAssertion the holder issued themselves binds nothing for a verifier
+        // trusting only the witness.
+        let vp = unsigned_presentation(&holder, vec![own, linked, self_issued]).unwrap();
+        if check_holder_binding(&vp, &trusted).is_ok() {
+            panic!("Bound a DID with a self-issued SameControllerAssertion");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_eth_presentation() {
+        let (_, witness) = test_did_keypair().await.unwrap();
+        let (subject, holder) = test_secp256k1_keypair().unwrap();
+        let own = CredentialOrJWT::Credential(github(subject).credential(witness).await.unwrap());
+
+        let opts = PresentationOptions {
+            challenge: Some(Uuid::new_v4().to_string()),
+            domain: None,
+        };
+        let vp = presentation(&holder, vec![own], &opts).await.unwrap();
+        let res = vp
+            .verify(
+                Some(opts.proof_options(None)),
+                &make_resolver(&None),
+                &mut context_loader().unwrap(),
+            )
+            .await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+    }
+}
@@ -0,0 +1,522 @@
+use crate::{
+    context::context_loader::context_loader,
+    types::{
+        defs::{
+            get_verification_method, make_resolver, send_request, Credential, HttpOpts, Issuer,
+            LinkedDataProofOptions, MaybeSendSync, ResolverOpts, URI,
+        },
+        error::StatusError,
+    },
+};
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use ssi::vc::{
+    revocation::{
+        List, StatusList2021, StatusList2021Credential, StatusList2021Entry, StatusList2021Subject,
+        MIN_BITSTRING_LENGTH,
+    },
+    Context, Contexts, Status,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+pub const STATUS_LIST_2021_V1_CONTEXT: &str = "https://w3id.org/vc/status-list/2021/v1";
+const STATUS_PURPOSE: &str = "revocation";
+
+// StatusListState is what a StatusListStore keeps for each list.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StatusListState {
+    pub next_index: usize,
+    pub revoked: BTreeSet<usize>,
+}
+
+// StatusListStore keeps the indices a witness has handed out in its status lists, and which
+// of them are revoked. Lists are keyed by the URL their credential is served from.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait StatusListStore: std::fmt::Debug + MaybeSendSync {
+    // allocate reserves the next unused index in list, or returns None once length are used.
+    async fn allocate(&self, list: &str, length: usize) -> Result<Option<usize>, StatusError>;
+    async fn set_revoked(&self, list: &str, index: usize, revoked: bool)
+        -> Result<(), StatusError>;
+    async fn state(&self, list: &str) -> Result<StatusListState, StatusError>;
+}
+
+fn allocate_in(
+    lists: &mut BTreeMap<String, StatusListState>,
+    list: &str,
+    length: usize,
+) -> Option<usize> {
+    let state = lists.entry(list.to_string()).or_default();
+    if state.next_index >= length {
+        return None;
+    }
+
+    state.next_index += 1;
+    Some(state.next_index - 1)
+}
+
+fn set_revoked_in(
+    lists: &mut BTreeMap<String, StatusListState>,
+    list: &str,
+    index: usize,
+    revoked: bool,
+) -> Result<bool, StatusError> {
+    let state = lists.entry(list.to_string()).or_default();
+    if index >= state.next_index {
+        return Err(StatusError::Store(format!(
+            "index {} has not been issued in {}",
+            index, list
+        )));
+    }
+
+    Ok(if revoked {
+        state.revoked.insert(index)
+    } else {
+        state.revoked.remove(&index)
+    })
+}
+
+// MemoryStatusListStore is the in-process StatusListStore used when none is configured.
+#[derive(Debug, Default)]
+pub struct MemoryStatusListStore {
+    lists: Mutex<BTreeMap<String, StatusListState>>,
+}
+
+impl MemoryStatusListStore {
+    pub const fn new() -> Self {
+        MemoryStatusListStore {
+            lists: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl StatusListStore for MemoryStatusListStore {
+    async fn allocate(&self, list: &str, length: usize) -> Result<Option<usize>, StatusError> {
+        let mut lists = self
+            .lists
+            .lock()
+            .map_err(|e| StatusError::Store(e.to_string()))?;
+        Ok(allocate_in(&mut lists, list, length))
+    }
+
+    async fn set_revoked(
+        &self,
+        list: &str,
+        index: usize,
+        revoked: bool,
+    ) -> Result<(), StatusError> {
+        let mut lists = self
+            .lists
+            .lock()
+            .map_err(|e| StatusError::Store(e.to_string()))?;
+        set_revoked_in(&mut lists, list, index, revoked)?;
+        Ok(())
+    }
+
+    async fn state(&self, list: &str) -> Result<StatusListState, StatusError> {
+        Ok(self
+            .lists
+            .lock()
+            .map_err(|e| StatusError::Store(e.to_string()))?
+            .get(list)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+// FileStatusListStore keeps status lists in a JSON object keyed by list URL, so indices are
+// never handed out twice across restarts of a single witness.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct FileStatusListStore {
+    path: PathBuf,
+    lists: Mutex<BTreeMap<String, StatusListState>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStatusListStore {
+    // open loads the status lists at path, which is created on the first allocation.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StatusError> {
+        let path = path.as_ref().to_path_buf();
+        let lists = match std::fs::read_to_string(&path) {
+            Ok(s) => serde_json::from_str(&s).map_err(|e| {
+                StatusError::Store(format!(
+                    "failed to parse status lists in {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(StatusError::Store(format!(
+                    "failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        Ok(FileStatusListStore {
+            path,
+            lists: Mutex::new(lists),
+        })
+    }
+
+    fn save(&self, lists: &BTreeMap<String, StatusListState>) -> Result<(), StatusError> {
+        let s = serde_json::to_string(lists)?;
+        // Write then rename, so a crash never leaves a truncated file behind.
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, s)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| {
+                StatusError::Store(format!("failed to write {}: {}", self.path.display(), e))
+            })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl StatusListStore for FileStatusListStore {
+    async fn allocate(&self, list: &str, length: usize) -> Result<Option<usize>, StatusError> {
+        let mut lists = self
+            .lists
+            .lock()
+            .map_err(|e| StatusError::Store(e.to_string()))?;
+        let index = allocate_in(&mut lists, list, length);
+        if index.is_some() {
+            self.save(&lists)?;
+        }
+        Ok(index)
+    }
+
+    async fn set_revoked(
+        &self,
+        list: &str,
+        index: usize,
+        revoked: bool,
+    ) -> Result<(), StatusError> {
+        let mut lists = self
+            .lists
+            .lock()
+            .map_err(|e| StatusError::Store(e.to_string()))?;
+        if set_revoked_in(&mut lists, list, index, revoked)? {
+            self.save(&lists)?;
+        }
+        Ok(())
+    }
+
+    async fn state(&self, list: &str) -> Result<StatusListState, StatusError> {
+        Ok(self
+            .lists
+            .lock()
+            .map_err(|e| StatusError::Store(e.to_string()))?
+            .get(list)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+static DEFAULT_STATUS_LIST_STORE: MemoryStatusListStore = MemoryStatusListStore::new();
+
+fn default_length() -> usize {
+    MIN_BITSTRING_LENGTH
+}
+
+// StatusListManager gives the credentials a witness issues a StatusList2021 entry, and signs
+// the list credential verifiers check them against.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StatusListManager {
+    // Where the witness serves the list credential, e.g. https://witness.example.com/status_list
+    pub list_url: String,
+    // How many credentials the list can track. Must be a multiple of 8, and at least 131072
+    // so a list doesn't give away which credential a verifier is checking.
+    #[serde(default = "default_length")]
+    pub length: usize,
+    // Not serializable, so must be set in code after the flow is deserialized.
+    #[serde(skip)]
+    pub store: Option<Arc<dyn StatusListStore>>,
+}
+
+impl StatusListManager {
+    pub fn new(list_url: &str) -> Self {
+        StatusListManager {
+            list_url: list_url.to_string(),
+            length: default_length(),
+            store: None,
+        }
+    }
+
+    fn store(&self) -> &dyn StatusListStore {
+        match &self.store {
+            Some(s) => s.as_ref(),
+            None => &DEFAULT_STATUS_LIST_STORE,
+        }
+    }
+
+    // add_status allocates an index for vc and embeds its credentialStatus, returning the
+    // index. It must be called before vc is signed.
+    pub async fn add_status(&self, vc: &mut Credential) -> Result<usize, StatusError> {
+        if self.length < MIN_BITSTRING_LENGTH || !self.length.is_multiple_of(8) {
+            return Err(StatusError::List(format!(
+                "length must be a multiple of 8 of at least {}",
+                MIN_BITSTRING_LENGTH
+            )));
+        }
+
+        let index = self
+            .store()
+            .allocate(&self.list_url, self.length)
+            .await?
+            .ok_or_else(|| StatusError::Store(format!("{} is full", self.list_url)))?;
+
+        let ctx = Context::URI(URI::String(STATUS_LIST_2021_V1_CONTEXT.to_string()));
+        if !vc.context.contains_uri(STATUS_LIST_2021_V1_CONTEXT) {
+            vc.context = match vc.context.clone() {
+                Contexts::One(c) => Contexts::Many(vec![c, ctx]),
+                Contexts::Many(mut v) => {
+                    v.push(ctx);
+                    Contexts::Many(v)
+                }
+            };
+        }
+
+        let props: HashMap<String, Value> = serde_json::from_value(json!({
+            "statusPurpose": STATUS_PURPOSE,
+            "statusListIndex": index.to_string(),
+            "statusListCredential": self.list_url,
+        }))?;
+        vc.credential_status = Some(Status {
+            id: URI::String(format!("{}#{}", self.list_url, index)),
+            type_: "StatusList2021Entry".to_string(),
+            property_set: Some(props),
+        });
+
+        Ok(index)
+    }
+
+    // set_revoked revokes, or reinstates, the credential issued with index.
+    pub async fn set_revoked(&self, index: usize, revoked: bool) -> Result<(), StatusError> {
+        self.store()
+            .set_revoked(&self.list_url, index, revoked)
+            .await
+    }
+
+    // list_credential returns the list as a StatusList2021Credential signed by issuer, which
+    // must be the issuer of the credentials in the list.
+    pub async fn list_credential<I: Issuer>(&self, issuer: &I) -> Result<Credential, StatusError> {
+        let mut list =
+            StatusList2021::new(self.length).map_err(|e| StatusError::List(e.to_string()))?;
+        for index in self.store().state(&self.list_url).await?.revoked {
+            list.set_status(index, true)
+                .map_err(|e| StatusError::List(e.to_string()))?;
+        }
+
+        let mut vc: Credential = serde_json::from_value(json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1", STATUS_LIST_2021_V1_CONTEXT],
+            "id": self.list_url,
+            "issuer": issuer.did().map_err(|e| StatusError::Issuer(e.into()))?,
+            "issuanceDate": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            "type": ["VerifiableCredential", "StatusList2021Credential"],
+            "credentialSubject": {
+                "id": format!("{}#list", self.list_url),
+                "type": "StatusList2021",
+                "statusPurpose": STATUS_PURPOSE,
+                "encodedList": list.encoded_list.0,
+            }
+        }))?;
+
+        issuer.sign_vc(&mut vc).await?;
+        Ok(vc)
+    }
+}
+
+// check_status errs if vc has a StatusList2021 entry that is set in its list credential, or
+// if the list can't be loaded and verified. Credentials without a status are left alone.
+pub async fn check_status(
+    vc: &Credential,
+    resolver_opts: &Option<ResolverOpts>,
+    http: &Option<HttpOpts>,
+) -> Result<(), StatusError> {
+    let status = match &vc.credential_status {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    if status.type_ != "StatusList2021Entry" {
+        return Err(StatusError::List(format!(
+            "unsupported credentialStatus type {}",
+            status.type_
+        )));
+    }
+
+    if !vc.context.contains_uri(STATUS_LIST_2021_V1_CONTEXT) {
+        return Err(StatusError::List(format!(
+            "credential is missing the {} context",
+            STATUS_LIST_2021_V1_CONTEXT
+        )));
+    }
+
+    let entry: StatusList2021Entry = serde_json::from_value(serde_json::to_value(status)?)?;
+    let index: usize = String::from(entry.status_list_index.clone())
+        .parse()
+        .map_err(|_| StatusError::List("invalid statusListIndex".to_string()))?;
+    let url = entry.status_list_credential.to_string();
+
+    let res = send_request(http, HttpClient::new().get(&url))
+        .await
+        .map_err(|e| StatusError::Load(e.to_string()))?;
+    if !res.status().is_success() {
+        return Err(StatusError::Load(format!(
+            "{} returned {}",
+            url,
+            res.status()
+        )));
+    }
+    let list_vc: Credential = res
+        .json()
+        .await
+        .map_err(|e| StatusError::Load(e.to_string()))?;
+
+    // Only the issuer of a credential may revoke it.
+    let issuer = match (&vc.issuer, &list_vc.issuer) {
+        (Some(a), Some(b)) if a.get_id() == b.get_id() => a.get_id(),
+        _ => {
+            return Err(StatusError::List(
+                "status list is not from the credential's issuer".to_string(),
+            ))
+        }
+    };
+
+    let resolver = make_resolver(resolver_opts);
+    let vm = get_verification_method(&issuer, &resolver)
+        .await
+        .ok_or_else(|| StatusError::List("could not find the issuer's key".to_string()))?;
+    let ldpo = LinkedDataProofOptions {
+        verification_method: Some(URI::String(vm)),
+        ..Default::default()
+    };
+    let mut loader = context_loader().map_err(|e| StatusError::List(e.to_string()))?;
+    let res = list_vc.verify(Some(ldpo), &resolver, &mut loader).await;
+    if !res.errors.is_empty() {
+        return Err(StatusError::List(res.errors.join(" ")));
+    }
+
+    let list_vc = StatusList2021Credential::try_from(list_vc)
+        .map_err(|e| StatusError::List(e.to_string()))?;
+    let StatusList2021Subject::StatusList2021(subject) = list_vc.credential_subject;
+    if subject.more_properties.get("statusPurpose") != Some(&json!(entry.status_purpose)) {
+        return Err(StatusError::List(format!(
+            "status list is not for {}",
+            entry.status_purpose
+        )));
+    }
+
+    let list =
+        List::try_from(&subject.encoded_list).map_err(|e| StatusError::List(e.to_string()))?;
+    if index >= list.0.len() * 8 {
+        return Err(StatusError::List(format!(
+            "statusListIndex {} is out of range",
+            index
+        )));
+    }
+
+    if list
+        .iter_revoked_indexes()
+        .map_err(|e| StatusError::List(e.to_string()))?
+        .any(|i| i == index)
+    {
+        return Err(StatusError::Revoked(entry.status_purpose));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::github_verification::GitHubVerificationContent,
+        test_util::util::{test_did_keypair, StubTransport},
+        types::defs::Content,
+    };
+
+    const LIST_URL: &str = "https://witness.example.com/status_list";
+
+    #[tokio::test]
+    async fn test_status_list() {
+        let (subject, witness) = test_did_keypair().await.unwrap();
+        let manager = StatusListManager {
+            store: Some(Arc::new(MemoryStatusListStore::new())),
+            ..StatusListManager::new(LIST_URL)
+        };
+
+        let content = GitHubVerificationContent {
+            gist_id: "1".to_string(),
+            handle: "example".to_string(),
+            subject,
+            statement: "unimplemented".to_string(),
+            signature: "unimplemented".to_string(),
+        };
+
+        let mut issued = Vec::new();
+        for _ in 0..2 {
+            let mut vc = content.unsigned_credential(witness.clone()).await.unwrap();
+            let index = manager.add_status(&mut vc).await.unwrap();
+            witness.sign_vc(&mut vc).await.unwrap();
+            issued.push((index, vc));
+        }
+        assert_eq!(issued[0].0, 0);
+        assert_eq!(issued[1].0, 1);
+
+        let http = |list: &Credential| {
+            Some(HttpOpts {
+                base_url: None,
+                transport: Some(Arc::new(StubTransport {
+                    routes: vec![(LIST_URL.to_string(), serde_json::to_string(list).unwrap())],
+                })),
+            })
+        };
+
+        let list = manager.list_credential(&witness).await.unwrap();
+        for (_, vc) in &issued {
+            check_status(vc, &None, &http(&list)).await.unwrap();
+        }
+
+        manager.set_revoked(issued[0].0, true).await.unwrap();
+        let list = manager.list_credential(&witness).await.unwrap();
+        if check_status(&issued[0].1, &None, &http(&list))
+            .await
+            .is_ok()
+        {
+            panic!("Accepted revoked credential");
+        }
+        check_status(&issued[1].1, &None, &http(&list))
+            .await
+            .unwrap();
+
+        // A list signed by anyone else can't revoke the witness's credentials.
+        let (_, other) = test_did_keypair().await.unwrap();
+        let forged = manager.list_credential(&other).await.unwrap();
+        if check_status(&issued[1].1, &None, &http(&forged))
+            .await
+            .is_ok()
+        {
+            panic!("Accepted status list from another issuer");
+        }
+
+        if manager.set_revoked(5, true).await.is_ok() {
+            panic!("Revoked an index that was never issued");
+        }
+    }
+}
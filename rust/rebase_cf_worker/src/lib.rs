@@ -1,11 +1,17 @@
+use async_trait::async_trait;
 use rebase_witness_sdk::types::{
-    handle_verify, issuer::ed25519::Ed25519Jwk, Alchemy, AttestationFlow, DelegatedAttestationFlow,
-    DnsVerificationFlow, EmailVerificationFlow, GitHubVerificationFlow, InstructionsReq,
+    handle_presentation_challenge, handle_verify_presentation, handle_verify_report,
+    issuer::ed25519::Ed25519Jwk, nonce_store, Alchemy, AttestationFlow, BlueskyVerificationFlow,
+    CapabilityError, DateTime, DelegatedAttestationFlow, DiscordVerificationFlow,
+    DnsVerificationFlow, EmailVerificationFlow, EthRpcOpts, FlowError, ForgeInstance, ForgeKind,
+    ForgeVerificationFlow, GitHubVerificationFlow, InstructionsReq, MastodonVerificationFlow,
     NftOwnershipVerificationFlow, PoapOwnershipVerificationFlow, Proofs, RedditVerificationFlow,
-    SameControllerAssertionFlow, SoundCloudVerificationFlow, Statements, TwitterVerificationFlow,
-    VCWrapper, WitnessFlow,
+    RefreshReq, RevocationStore, SameControllerAssertionFlow, SignedRevocation,
+    SoundCloudVerificationFlow, Statements, TwitterVerificationFlow, Utc, VCWrapper,
+    VerificationPolicy, VerifyPresentationReq, WitnessFlow,
 };
 use serde_json::json;
+use std::sync::Arc;
 use worker::*;
 
 mod utils;
@@ -20,24 +26,122 @@ fn log_request(req: &Request) {
     );
 }
 
+// KvRevocationStore keeps delegated session revocations in the REVOCATIONS KV namespace,
+// so they are shared by every instance of the worker.
+struct KvRevocationStore {
+    kv: kv::KvStore,
+}
+
+impl std::fmt::Debug for KvRevocationStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvRevocationStore")
+    }
+}
+
+#[async_trait(?Send)]
+impl RevocationStore for KvRevocationStore {
+    async fn revoke(
+        &self,
+        key: &str,
+        expires: DateTime<Utc>,
+    ) -> std::result::Result<(), CapabilityError> {
+        // KV forgets the key once the ReCaps it revokes have expired. Expirations have to be at
+        // least a minute away.
+        let expiration = expires.timestamp().max(Utc::now().timestamp() + 60);
+        self.kv
+            .put(key, "revoked")
+            .map_err(|e| CapabilityError::ReCapError(e.to_string()))?
+            .expiration(expiration as u64)
+            .execute()
+            .await
+            .map_err(|e| CapabilityError::ReCapError(e.to_string()))
+    }
+
+    async fn is_revoked(&self, key: &str) -> std::result::Result<bool, CapabilityError> {
+        Ok(self
+            .kv
+            .get(key)
+            .text()
+            .await
+            .map_err(|e| CapabilityError::ReCapError(e.to_string()))?
+            .is_some())
+    }
+}
+
 fn new_flow(env: &Env) -> WitnessFlow {
     let mut flow = WitnessFlow {
-        dns_verification: Some(DnsVerificationFlow {}),
+        bluesky_verification: Some(BlueskyVerificationFlow {
+            delimiter: "\n\n".to_string(),
+            http_opts: None,
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }),
+        discord_verification: None,
+        dns_verification: Some(DnsVerificationFlow {
+            http_opts: None,
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }),
         email_verification: None,
+        forge_verification: Some(ForgeVerificationFlow {
+            user_agent: "Spruce Systems".to_string(),
+            delimiter: "\n\n".to_string(),
+            instances: vec![
+                ForgeInstance {
+                    kind: ForgeKind::GitLab,
+                    url: "https://gitlab.com".to_string(),
+                },
+                ForgeInstance {
+                    kind: ForgeKind::Gitea,
+                    url: "https://codeberg.org".to_string(),
+                },
+            ],
+            http_opts: None,
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }),
         github_verification: Some(GitHubVerificationFlow {
             user_agent: "Spruce Systems".to_string(),
             delimiter: "\n\n".to_string(),
+            http_opts: None,
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }),
+        mastodon_verification: Some(MastodonVerificationFlow {
+            delimiter: "\n\n".to_string(),
+            http_opts: None,
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
         }),
         nft_ownership_verification: None,
         poap_ownership_verification: None,
         reddit_verification: Some(RedditVerificationFlow {
             user_agent: "rebase-server:rebase-witness-sdk:0.0.1 (by eval-apply-quote)".to_string(),
+            http_opts: None,
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        }),
+        same_controller_assertion: Some(SameControllerAssertionFlow {
+            resolver_opts: None,
+            eth_rpc: None,
         }),
-        same_controller_assertion: Some(SameControllerAssertionFlow {}),
         soundcloud_verification: None,
         twitter_verification: None,
-        attestation: Some(AttestationFlow {}),
+        attestation: Some(AttestationFlow {
+            resolver_opts: None,
+            eth_rpc: None,
+        }),
         delegated_attestation: None,
+        // KV can't allocate status list indices atomically across instances, so credentials
+        // are issued without a credentialStatus.
+        status_list: None,
+        validity_minutes: None,
     };
 
     match &env.secret("DID_WEB") {
@@ -45,10 +149,48 @@ fn new_flow(env: &Env) -> WitnessFlow {
         Ok(s) => {
             flow.delegated_attestation = Some(DelegatedAttestationFlow {
                 service_key: format!("rebase:{}", s.to_string()),
+                resolver_opts: None,
+                eth_rpc: None,
+                // KV can't count atomically across instances, so ReCaps with max_issuances
+                // caveats are refused.
+                issuance_store: None,
+                revocation_store: env
+                    .kv("REVOCATIONS")
+                    .ok()
+                    .map(|kv| Arc::new(KvRevocationStore { kv }) as Arc<dyn RevocationStore>),
+                expected_domain: siwe_domain(env, &s.to_string()),
+                rate_limit: None,
+                nonce_store: None,
             });
         }
     }
 
+    match env.secret("DISCORD_BOT_TOKEN") {
+        Err(_) => {}
+        Ok(s) => {
+            // A comma separated list of the channels statements may be posted in.
+            let channel_ids = env
+                .var("DISCORD_CHANNEL_IDS")
+                .map(|v| {
+                    v.to_string()
+                        .split(',')
+                        .map(|c| c.trim().to_string())
+                        .filter(|c| !c.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            flow.discord_verification = Some(DiscordVerificationFlow {
+                bot_token: s.to_string(),
+                delimiter: "\n\n".to_string(),
+                channel_ids,
+                http_opts: None,
+                resolver_opts: None,
+                eth_rpc: None,
+                validity_minutes: None,
+            })
+        }
+    }
+
     match env.secret("SENDGRID_BEARER_TOKEN") {
         Err(_) => {}
         Ok(s) => {
@@ -59,6 +201,12 @@ fn new_flow(env: &Env) -> WitnessFlow {
                 from_name: "Spruce".to_string(),
                 subject_name: "Rebase Credentialing".to_string(),
                 max_elapsed_minutes: 15,
+                http_opts: None,
+                resolver_opts: None,
+                eth_rpc: None,
+                rate_limit: None,
+                validity_minutes: None,
+                nonce_store: None,
             })
         }
     };
@@ -71,6 +219,11 @@ fn new_flow(env: &Env) -> WitnessFlow {
                     api_key: s.to_string(),
                     challenge_delimiter: "\n\n".to_string(),
                     max_elapsed_minutes: 15,
+                    http_opts: None,
+                    eth_rpc: None,
+                    rate_limit: None,
+                    validity_minutes: None,
+                    nonce_store: None,
                 }));
         }
     }
@@ -82,6 +235,11 @@ fn new_flow(env: &Env) -> WitnessFlow {
                 api_key: s.to_string(),
                 challenge_delimiter: "\n\n".to_string(),
                 max_elapsed_minutes: 15,
+                http_opts: None,
+                eth_rpc: None,
+                rate_limit: None,
+                validity_minutes: None,
+                nonce_store: None,
             })
         }
     }
@@ -93,6 +251,10 @@ fn new_flow(env: &Env) -> WitnessFlow {
                 client_id: s.to_string(),
                 limit: 100,
                 max_offset: 9000,
+                http_opts: None,
+                resolver_opts: None,
+                eth_rpc: None,
+                validity_minutes: None,
             })
         }
     }
@@ -103,6 +265,10 @@ fn new_flow(env: &Env) -> WitnessFlow {
             flow.twitter_verification = Some(TwitterVerificationFlow {
                 api_key: s.to_string(),
                 delimiter: "\n\n".to_string(),
+                http_opts: None,
+                resolver_opts: None,
+                eth_rpc: None,
+                validity_minutes: None,
             })
         }
     }
@@ -119,6 +285,48 @@ fn new_issuer(env: &Env) -> Result<Ed25519Jwk> {
     .map_err(|e| format!("failed to create issuer: {}", e).into())
 }
 
+// VERIFICATION_POLICY is the JSON of a VerificationPolicy applied by /verify and
+// /verify_presentation, such as {"issuers": ["did:web:example.com"]}. Unless it names the
+// issuers it trusts, only credentials issued by this witness are accepted.
+fn new_policy(env: &Env) -> Result<Option<VerificationPolicy>> {
+    match env.var("VERIFICATION_POLICY") {
+        Err(_) => Ok(None),
+        Ok(s) => serde_json::from_str(&s.to_string())
+            .map(Some)
+            .map_err(|e| format!("failed to parse verification policy: {}", e).into()),
+    }
+}
+
+// SIWE_DOMAIN is the domain delegations must be signed for, falling back to the host of the
+// did:web identifier, e.g. did:web:example.com%3A8443 gives example.com:8443.
+fn siwe_domain(env: &Env, did_web: &str) -> String {
+    match env.var("SIWE_DOMAIN") {
+        Ok(s) => s.to_string(),
+        Err(_) => did_web
+            .trim_start_matches("did:web:")
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .replace("%3A", ":"),
+    }
+}
+
+// PRESENTATION_DOMAIN is the domain holders sign presentations to /verify_presentation for.
+fn presentation_domain(env: &Env) -> Option<String> {
+    env.var("PRESENTATION_DOMAIN").ok().map(|s| s.to_string())
+}
+
+// ETH_RPC is the JSON of the EthRpcOpts flows use to check Ethereum contract wallet
+// signatures, such as {"rpc_urls": {"1": "https://eth.example.com"}}.
+fn new_eth_rpc(env: &Env) -> Result<Option<EthRpcOpts>> {
+    match env.var("ETH_RPC") {
+        Err(_) => Ok(None),
+        Ok(s) => serde_json::from_str(&s.to_string())
+            .map(Some)
+            .map_err(|e| format!("failed to parse eth rpc options: {}", e).into()),
+    }
+}
+
 fn preflight_response() -> Result<Response> {
     let mut headers = worker::Headers::new();
     headers.set("Access-Control-Allow-Methods", "GET,POST,OPTIONS")?;
@@ -141,6 +349,31 @@ fn post_resp_headers() -> Result<Headers> {
     Ok(headers)
 }
 
+// problem_response returns the error as an application/problem+json body, with the status code
+// the error calls for.
+fn problem_response(e: &FlowError) -> Result<Response> {
+    let problem = e.problem();
+    let mut headers = post_resp_headers()?;
+    headers.set("Content-Type", "application/problem+json")?;
+    Ok(Response::from_json(&problem)?
+        .with_status(problem.status)
+        .with_headers(headers))
+}
+
+fn bad_request(e: Error) -> Result<Response> {
+    problem_response(&FlowError::Validation(format!(
+        "invalid request body: {}",
+        e
+    )))
+}
+
+fn json_response(r: std::result::Result<serde_json::Value, FlowError>) -> Result<Response> {
+    match r {
+        Ok(v) => Ok(Response::from_json(&v)?.with_headers(post_resp_headers()?)),
+        Err(e) => problem_response(&e),
+    }
+}
+
 #[event(fetch)]
 pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Response> {
     log_request(&req);
@@ -148,72 +381,107 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
     // Optionally, get more helpful error messages written to the console in the case of a panic.
     utils::set_panic_hook();
 
-    let flow = new_flow(&env);
+    let mut flow = new_flow(&env);
+    flow.set_eth_rpc_opts(&new_eth_rpc(&env)?);
     let issuer = new_issuer(&env)?;
-    let router = Router::with_data((flow, issuer));
+    let policy = Some(VerificationPolicy::or_issuer(
+        &new_policy(&env)?,
+        &issuer.did,
+    ));
+    let router = Router::with_data((flow, issuer, policy, presentation_domain(&env)));
 
     router
         // TODO: Investigate if there is a wild card pattern instead of repetition
         .options("/instructions", |_req, _ctx| preflight_response())
         .post_async("/instructions", |mut req, ctx| async move {
-            let _ = &ctx;
-            if let Ok(b) = req.json::<InstructionsReq>().await {
-                if let Ok(r) = ctx.data.0.handle_instructions(&b).await {
-                    let res = Response::from_json(&r)?;
-                    return Ok(res.with_headers(post_resp_headers()?));
-                };
-            };
-            Response::error("Bad Request", 400)
+            match req.json::<InstructionsReq>().await {
+                Ok(b) => json_response(ctx.data.0.handle_instructions(&b).await),
+                Err(e) => bad_request(e),
+            }
         })
         // TODO: Investigate if there is a wild card pattern instead of repetition
         .options("/statement", |_req, _ctx| preflight_response())
         .post_async("/statement", |mut req, ctx| async move {
-            let _ = &ctx;
-            if let Ok(b) = req.json::<Statements>().await {
-                if let Ok(r) = ctx.data.0.handle_statement(b, ctx.data.1.clone()).await {
-                    let res = Response::from_json(&r)?;
-                    return Ok(res.with_headers(post_resp_headers()?));
-                };
-            };
-            Response::error("Bad Request", 400)
+            match req.json::<Statements>().await {
+                Ok(b) => json_response(ctx.data.0.handle_statement(b, ctx.data.1.clone()).await),
+                Err(e) => bad_request(e),
+            }
         })
         // TODO: Investigate if there is a wild card pattern instead of repetition
         .options("/witness_jwt", |_req, _ctx| preflight_response())
         .post_async("/witness_jwt", |mut req, ctx| async move {
-            let _ = &ctx;
-            if let Ok(b) = req.json::<Proofs>().await {
-                if let Ok(r) = ctx.data.0.handle_jwt(b, ctx.data.1.clone()).await {
-                    let res = Response::from_json(&r)?;
-                    return Ok(res.with_headers(post_resp_headers()?));
-                };
-            };
-            Response::error("Bad Request", 400)
+            match req.json::<Proofs>().await {
+                Ok(b) => json_response(ctx.data.0.handle_jwt(b, ctx.data.1.clone()).await),
+                Err(e) => bad_request(e),
+            }
         })
         // TODO: Investigate if there is a wild card pattern instead of repetition
         .options("/witness_ld", |_req, _ctx| preflight_response())
         .post_async("/witness_ld", |mut req, ctx| async move {
-            let _ = &ctx;
-            if let Ok(b) = req.json::<Proofs>().await {
-                if let Ok(r) = ctx.data.0.handle_ld(b, ctx.data.1.clone()).await {
-                    let res = Response::from_json(&r)?;
-                    return Ok(res.with_headers(post_resp_headers()?));
-                };
-            };
-            Response::error("Bad Request", 400)
+            match req.json::<Proofs>().await {
+                Ok(b) => json_response(ctx.data.0.handle_ld(b, ctx.data.1.clone()).await),
+                Err(e) => bad_request(e),
+            }
         })
         // TODO: Investigate if there is a wild card pattern instead of repetition
         .options("/verify", |_req, _ctx| preflight_response())
-        .post_async("/verify", |mut req, _ctx| async move {
-            if let Ok(t) = req.text().await {
-                if let Ok(b) = serde_json::from_str::<VCWrapper>(&t) {
-                    if handle_verify(&b, &None).await.is_ok() {
-                        return Ok(Response::from_json(&json!({"success": true}))?
-                            .with_headers(post_resp_headers()?));
-                    };
+        .post_async("/verify", |mut req, ctx| async move {
+            let b = match req.json::<VCWrapper>().await {
+                Ok(b) => b,
+                Err(e) => return bad_request(e),
+            };
+
+            // The report is returned either way, so callers can show what failed.
+            match handle_verify_report(&b, &None, &ctx.data.2).await {
+                Ok(r) => {
+                    let status = if r.success { 200 } else { 400 };
+                    Ok(Response::from_json(&r)?
+                        .with_status(status)
+                        .with_headers(post_resp_headers()?))
                 }
+                Err(e) => problem_response(&e),
+            }
+        })
+        // Like the flows' nonces, challenges are kept in the isolate's memory, so a holder's
+        // presentation is only accepted by the isolate that issued its challenge.
+        .options("/presentation_challenge", |_req, _ctx| preflight_response())
+        .post_async("/presentation_challenge", |_req, ctx| async move {
+            json_response(
+                handle_presentation_challenge(nonce_store(&None), &ctx.data.3)
+                    .await
+                    .map(|o| json!(o)),
+            )
+        })
+        .options("/verify_presentation", |_req, _ctx| preflight_response())
+        .post_async("/verify_presentation", |mut req, ctx| async move {
+            match req.json::<VerifyPresentationReq>().await {
+                Ok(b) => json_response(
+                    handle_verify_presentation(
+                        &b,
+                        &None,
+                        &ctx.data.2,
+                        nonce_store(&None),
+                        &ctx.data.3,
+                    )
+                    .await
+                    .map(|_| json!({"success": true})),
+                ),
+                Err(e) => bad_request(e),
+            }
+        })
+        .options("/revoke", |_req, _ctx| preflight_response())
+        .post_async("/revoke", |mut req, ctx| async move {
+            match req.json::<SignedRevocation>().await {
+                Ok(b) => json_response(ctx.data.0.handle_revoke(&b).await),
+                Err(e) => bad_request(e),
+            }
+        })
+        .options("/refresh", |_req, _ctx| preflight_response())
+        .post_async("/refresh", |mut req, ctx| async move {
+            match req.json::<RefreshReq>().await {
+                Ok(b) => json_response(ctx.data.0.handle_refresh(&b, ctx.data.1.clone()).await),
+                Err(e) => bad_request(e),
             }
-
-            Response::error("Bad Request", 400)
         })
         .run(req, env)
         .await
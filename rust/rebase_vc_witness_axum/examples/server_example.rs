@@ -6,7 +6,7 @@ async fn main() {
     let s = include_str!("../rebase.json");
     let config: rebase_vc_witness_axum::Config = from_str(s).unwrap();
 
-    let app = rebase_vc_witness_axum::service(config).await;
+    let app = rebase_vc_witness_axum::service(config).await.unwrap();
     axum::Server::bind(&"0.0.0.0:8787".parse().unwrap())
         .serve(app.into_make_service())
         .await
@@ -1,100 +1,244 @@
-// TODO: More robust err handling via anyhow err?
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
 use rebase_witness_sdk::types::{
-    handle_verify, issuer::ed25519::Ed25519Jwk, InstructionsReq, Proofs, Statements, VCWrapper,
-    WitnessFlow,
+    handle_presentation_challenge, handle_verify_presentation, handle_verify_report,
+    issuer::ed25519::Ed25519Jwk, nonce_store, EthRpcOpts, FileRevocationStore, FileStatusListStore,
+    FlowError, InstructionsReq, MemoryIssuanceStore, Problem, Proofs, RebaseError, RefreshReq,
+    ResolverOpts, SignedRevocation, Statements, VCWrapper, VerificationPolicy,
+    VerifyPresentationReq, WitnessFlow,
 };
 use serde::Deserialize;
 use serde_json::Value;
-use std::{ops::Deref, sync::Arc};
+use std::{ops::Deref, path::PathBuf, sync::Arc};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
     pub rebase: WitnessFlow,
     pub issuer: Ed25519Jwk,
+    // Used to resolve the DIDs of credentials and presentations being verified, and by the
+    // issuer and every flow that don't set their own.
+    #[serde(default)]
+    pub resolver_opts: Option<ResolverOpts>,
+    // JSON-RPC endpoints used to verify smart contract wallet signatures, for every flow that
+    // doesn't set its own.
+    #[serde(default)]
+    pub eth_rpc: Option<EthRpcOpts>,
+    // Persists delegated session revocations, otherwise they are kept in memory.
+    #[serde(default)]
+    pub revocation_file: Option<PathBuf>,
+    // Persists the status list's issued and revoked indices, otherwise they are kept in memory.
+    #[serde(default)]
+    pub status_list_file: Option<PathBuf>,
+    // What /verify and /verify_presentation require of credentials beyond a valid signature.
+    // Unless it names the issuers it trusts, only credentials issued by this witness are
+    // accepted.
+    #[serde(default)]
+    pub verification_policy: Option<VerificationPolicy>,
+    // The domain holders sign presentations to /verify_presentation for, e.g. "example.com".
+    #[serde(default)]
+    pub presentation_domain: Option<String>,
+}
+
+// ProblemResponse is a FlowError returned as an application/problem+json body, with the status
+// code the error calls for.
+pub struct ProblemResponse(pub Problem);
+
+impl From<FlowError> for ProblemResponse {
+    fn from(e: FlowError) -> Self {
+        ProblemResponse(e.problem())
+    }
+}
+
+impl IntoResponse for ProblemResponse {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.0.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (
+            status,
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            Json(self.0),
+        )
+            .into_response()
+    }
 }
 
 // TODO: Change the calls so the return value is well typed, here it would be: Json<Instructions> instead of Json<Value> (???)
-// TODO: Pass through err messages?
 pub async fn instructions_handler(
     State(state): State<Arc<Config>>,
     instructions_req: Json<InstructionsReq>,
-) -> Result<Json<Value>, StatusCode> {
-    if let Ok(v) = state.rebase.handle_instructions(&instructions_req).await {
-        Ok(Json(v))
-    } else {
-        Err(StatusCode::BAD_REQUEST)
-    }
+) -> Result<Json<Value>, ProblemResponse> {
+    Ok(Json(
+        state.rebase.handle_instructions(&instructions_req).await?,
+    ))
 }
 
 pub async fn statement_handler(
     State(state): State<Arc<Config>>,
     statement_req: Json<Statements>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ProblemResponse> {
     let req = statement_req.deref();
-    if let Ok(v) = state
-        .rebase
-        .handle_statement(req.to_owned(), state.issuer.clone())
-        .await
-    {
-        Ok(Json(v))
-    } else {
-        Err(StatusCode::BAD_REQUEST)
-    }
+    Ok(Json(
+        state
+            .rebase
+            .handle_statement(req.to_owned(), state.issuer.clone())
+            .await?,
+    ))
 }
 
 pub async fn witness_ld_handler(
     State(state): State<Arc<Config>>,
     witness_ld_req: Json<Proofs>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ProblemResponse> {
     let req = witness_ld_req.deref();
-    if let Ok(v) = state
-        .rebase
-        .handle_ld(req.to_owned(), state.issuer.clone())
-        .await
-    {
-        Ok(Json(v))
-    } else {
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
-    }
+    Ok(Json(
+        state
+            .rebase
+            .handle_ld(req.to_owned(), state.issuer.clone())
+            .await?,
+    ))
 }
 
 pub async fn witness_jwt_handler(
     State(state): State<Arc<Config>>,
     witness_jwt_req: Json<Proofs>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ProblemResponse> {
     let req = witness_jwt_req.deref();
-    if let Ok(v) = state
-        .rebase
-        .handle_jwt(req.to_owned(), state.issuer.clone())
-        .await
-    {
-        Ok(Json(v))
-    } else {
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
-    }
+    Ok(Json(
+        state
+            .rebase
+            .handle_jwt(req.to_owned(), state.issuer.clone())
+            .await?,
+    ))
 }
 
+// The report is returned whether or not the credential verified, so callers can show what
+// failed. Only a credential that can't be decoded gets a problem response.
 pub async fn verify_credential_handler(
-    State(_state): State<Arc<Config>>,
+    State(state): State<Arc<Config>>,
     verify_req: Json<VCWrapper>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<(StatusCode, Json<Value>), ProblemResponse> {
     let req = verify_req.deref();
-    if handle_verify(req, &None).await.is_ok() {
-        Ok(Json(serde_json::json!({"success": true})))
+    let r = handle_verify_report(req, &state.resolver_opts, &state.verification_policy).await?;
+    let status = if r.success {
+        StatusCode::OK
     } else {
-        Err(StatusCode::BAD_REQUEST)
-    }
+        StatusCode::BAD_REQUEST
+    };
+    Ok((status, Json(serde_json::json!(r))))
 }
 
-pub async fn service(config: Config) -> Router {
+// Holders ask for a challenge to sign into the presentation they then send to
+// /verify_presentation. The service runs as a single process, so challenges are kept in memory.
+pub async fn presentation_challenge_handler(
+    State(state): State<Arc<Config>>,
+) -> Result<Json<Value>, ProblemResponse> {
+    let options =
+        handle_presentation_challenge(nonce_store(&None), &state.presentation_domain).await?;
+    Ok(Json(serde_json::json!(options)))
+}
+
+pub async fn verify_presentation_handler(
+    State(state): State<Arc<Config>>,
+    verify_req: Json<VerifyPresentationReq>,
+) -> Result<Json<Value>, ProblemResponse> {
+    let req = verify_req.deref();
+    handle_verify_presentation(
+        req,
+        &state.resolver_opts,
+        &state.verification_policy,
+        nonce_store(&None),
+        &state.presentation_domain,
+    )
+    .await?;
+    Ok(Json(serde_json::json!({"success": true})))
+}
+
+pub async fn revoke_handler(
+    State(state): State<Arc<Config>>,
+    revoke_req: Json<SignedRevocation>,
+) -> Result<Json<Value>, ProblemResponse> {
+    Ok(Json(state.rebase.handle_revoke(revoke_req.deref()).await?))
+}
+
+pub async fn refresh_handler(
+    State(state): State<Arc<Config>>,
+    refresh_req: Json<RefreshReq>,
+) -> Result<Json<Value>, ProblemResponse> {
+    Ok(Json(
+        state
+            .rebase
+            .handle_refresh(refresh_req.deref(), state.issuer.clone())
+            .await?,
+    ))
+}
+
+pub async fn status_list_handler(
+    State(state): State<Arc<Config>>,
+) -> Result<Json<Value>, ProblemResponse> {
+    Ok(Json(
+        state
+            .rebase
+            .handle_status_list(state.issuer.clone())
+            .await?,
+    ))
+}
+
+// service builds the witness's routes, failing if a configured store can't be opened.
+pub async fn service(mut config: Config) -> Result<Router, RebaseError> {
+    let resolver_opts = config.resolver_opts.clone();
+    config.rebase.set_resolver_opts(&resolver_opts);
+    if config.issuer.resolver_opts.is_none() {
+        config.issuer.resolver_opts = resolver_opts;
+    }
+    let eth_rpc = config.eth_rpc.clone();
+    config.rebase.set_eth_rpc_opts(&eth_rpc);
+    if let (Some(path), Some(flow)) = (
+        &config.revocation_file,
+        &mut config.rebase.delegated_attestation,
+    ) {
+        flow.revocation_store = Some(Arc::new(FileRevocationStore::open(path)?));
+    }
+    // The service runs as a single process, so it can count issuances in memory.
+    if let Some(flow) = &mut config.rebase.delegated_attestation {
+        if flow.expected_domain.is_empty() {
+            return Err(FlowError::Misconfigured(
+                "DelegatedAttestation needs an expected_domain".to_string(),
+            )
+            .into());
+        }
+        if flow.issuance_store.is_none() {
+            flow.issuance_store = Some(Arc::new(MemoryIssuanceStore::new()));
+        }
+    }
+    if let (Some(path), Some(status_list)) =
+        (&config.status_list_file, &mut config.rebase.status_list)
+    {
+        status_list.store = Some(Arc::new(FileStatusListStore::open(path)?));
+    }
+    config.verification_policy = Some(VerificationPolicy::or_issuer(
+        &config.verification_policy,
+        &config.issuer.did,
+    ));
     let state = Arc::new(config);
 
-    Router::new()
+    Ok(Router::new()
         .route("/instructions", post(instructions_handler))
         .route("/statement", post(statement_handler))
         .route("/witness_ld", post(witness_ld_handler))
         .route("/witness_jwt", post(witness_jwt_handler))
         .route("/verify", post(verify_credential_handler))
-        .with_state(state)
+        .route(
+            "/presentation_challenge",
+            post(presentation_challenge_handler),
+        )
+        .route("/verify_presentation", post(verify_presentation_handler))
+        .route("/revoke", post(revoke_handler))
+        .route("/refresh", post(refresh_handler))
+        .route("/status_list", get(status_list_handler))
+        .with_state(state))
 }
@@ -7,16 +7,19 @@ use rebase_witness_sdk::{
 use std::env;
 use url::Url;
 
-fn new_client(base_url: &str) -> Result<Client, String> {
+fn new_client(base_url: &str, witness_did: &str) -> Result<Client, String> {
     let endpoints = Endpoints {
         witness_jwt: Some(Url::parse(&format!("{}/witness_jwt", base_url)).unwrap()),
         witness_ld: Some(Url::parse(&format!("{}/witness_ld", base_url)).unwrap()),
         statement: Url::parse(&format!("{}/statement", base_url)).unwrap(),
         instructions: Url::parse(&format!("{}/instructions", base_url)).unwrap(),
         verify: None,
+        revoke: None,
     };
 
-    Client::new(endpoints, None).map_err(|e| e.to_string())
+    Client::new(endpoints, None)
+        .map(|c| c.with_witness_did(witness_did))
+        .map_err(|e| e.to_string())
 }
 
 async fn check_statement(
@@ -39,7 +42,8 @@ async fn check_statement(
 async fn main() {
     println!("Building client...");
     let url = env::args().nth(1).unwrap();
-    let client = new_client(&url).unwrap();
+    let witness_did = env::args().nth(2).unwrap();
+    let client = new_client(&url, &witness_did).unwrap();
 
     println!("Starting Ethereum tests:");
     println!("Testing DNS...");
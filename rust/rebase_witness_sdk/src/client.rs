@@ -1,17 +1,19 @@
 use crate::types::{
-    handle_verify, to_action, AttestationTypes, CredentialWrapper, InstructionsReq, JWTWrapper,
-    Proofs, ResolverOpts, SessionConfig, StatementResponse, Statements, VCWrapper, VerifyRes, JWK,
+    handle_verify, handle_verify_report, to_action, AttestationTypes, CredentialWrapper,
+    DelegatedCapability, InstructionsReq, JWTWrapper, Problem, Proofs, ResolverOpts, SessionConfig,
+    SignedRevocation, StatementResponse, Statements, VCWrapper, VerificationPolicy,
+    VerificationReport, VerifyRes, JWK,
 };
 use base64::{engine::general_purpose, Engine as _};
 use rebase::{
     issuer::ed25519::Ed25519Jwk,
-    proof::delegated_attestation::{parse_siwe_recap, DelegatedAttestationProof, ParsedReCap},
+    proof::delegated_attestation::{
+        verify_siwe_recap, DelegatedAttestationProof, ParsedReCap, SignedReCap,
+    },
     statement::attestation::statement::AttestationStatement,
     types::{
-        defs::{
-            eip55, get_verification_method, DIDKey, DIDMethod, Issuer, Message, Source, Statement,
-            Subject,
-        },
+        capability::caip122::SignInMessage,
+        defs::{get_verification_method, DIDKey, DIDMethod, Issuer, Source, Statement},
         enums::{
             attestation::Attestation,
             subject::{Pkh, Subjects},
@@ -41,6 +43,11 @@ pub enum ClientError {
     Capability(String),
     #[error("failed in delegated attestation config: {0}")]
     DelegatedConf(String),
+    #[error("failed in revocation: {0}")]
+    Revocation(String),
+    // A problem response from the witness, with the code of the error it encountered.
+    #[error("witness responded with {0}")]
+    Witness(Problem),
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
@@ -51,6 +58,8 @@ pub struct Endpoints {
     pub statement: Url,
     pub instructions: Url,
     pub verify: Option<Url>,
+    #[serde(default)]
+    pub revoke: Option<Url>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
@@ -59,6 +68,14 @@ pub struct Client {
     pub endpoints: Endpoints,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resolver_opts: Option<ResolverOpts>,
+    // The DID the witness at endpoints issues credentials as. Without it, verify and
+    // verify_report only accept credentials from the issuers policy names.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness_did: Option<String>,
+    // What verify and verify_report require of credentials beyond a valid signature. Unless it
+    // names the issuers it trusts, only credentials issued by witness_did are accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<VerificationPolicy>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
@@ -76,6 +93,9 @@ pub struct DelegatedAttestationConfig {
     session_config: SessionConfig,
     siwe_recap_message: String,
     siwe_recap_signature: String,
+    // The ReCaps siwe_recap_message was delegated from, keyed by CID, when re-delegated.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    parents: BTreeMap<String, SignedReCap>,
 }
 
 impl DelegatedAttestationConfig {
@@ -89,36 +109,30 @@ impl DelegatedAttestationConfig {
     }
 
     pub async fn is_valid(&self) -> Result<ParsedReCap, ClientError> {
-        // Is the SIWE Recap Valid?
-        let p = parse_siwe_recap(&self.siwe_recap_message, &self.service_key)
-            .map_err(|e| ClientError::DelegatedConf(e.to_string()))?;
-        p.subject
-            .valid_signature(&self.siwe_recap_message, &self.siwe_recap_signature)
-            .await
-            .map_err(|e| ClientError::DelegatedConf(e.to_string()))?;
-
-        // Create the SIWE message to use a source of information for validation.
-        let m = Message::from_str(&self.siwe_recap_message).map_err(|_e| {
-            ClientError::DelegatedConf("Failed to parse ReCap into Message".to_string())
-        })?;
-
-        // Use SIWE's own validation to determine if the ReCap is valid from a timing perspective
-        if !m.valid_now() {
-            return Err(ClientError::DelegatedConf(
-                "Capability is not valid at this time".to_string(),
-            ));
-        };
+        // Is the SIWE Recap, and any it was delegated from, valid?
+        let p = verify_siwe_recap(
+            &self.siwe_recap_message,
+            &self.siwe_recap_signature,
+            &self.service_key,
+            &self.parents,
+            &None,
+            &None,
+            // Revocations are only known to the witness.
+            None,
+        )
+        .await
+        .map_err(|e| ClientError::DelegatedConf(e.to_string()))?;
 
         Ok(p)
     }
 
     pub async fn is_jwk_valid(&self) -> Result<Ed25519Jwk, ClientError> {
         let j = self.jwk()?;
-        let m = Message::from_str(&self.siwe_recap_message).map_err(|_e| {
+        let m = SignInMessage::from_str(&self.siwe_recap_message).map_err(|_e| {
             ClientError::DelegatedConf("Failed to parse ReCap into Message".to_string())
         })?;
 
-        let expected_did = m.uri.to_string();
+        let expected_did = m.uri;
         let dk = DIDKey {};
 
         let d =
@@ -177,37 +191,26 @@ impl DelegatedAttestationConfig {
             )));
         }
 
-        let m = Message::from_str(&self.siwe_recap_message).map_err(|e| {
-            ClientError::DelegatedConf(format!("Failed to parse ReCap into Message: {}", e))
-        })?;
-
         // Check that the subject of the attestation matches the ReCap
-        let expected_addr = eip55(&m.address);
-        let expected_chain_id = format!("{}", m.chain_id);
-        match statement.subject() {
-            Subjects::Pkh(Pkh::Eip155(s)) => {
-                if s.address != expected_addr {
-                    return Err(ClientError::DelegatedConf(format!(
-                        "Expected subject address {} got {}",
-                        expected_addr, s.address
-                    )));
-                }
+        let subject = statement.subject();
+        p.check_subject(&subject)
+            .map_err(|e| ClientError::DelegatedConf(e.to_string()))?;
 
-                if s.chain_id != expected_chain_id {
-                    return Err(ClientError::DelegatedConf(format!(
-                        "Expected chain id {} got {}",
-                        expected_chain_id, s.chain_id
-                    )));
-                }
-            }
-            _ => {
-                // TODO: Update if other keys are supported
-                return Err(ClientError::DelegatedConf(
-                    "Expected statement to have did:pkh:eip155, no other keys currently supported"
-                        .to_string(),
-                ));
+        if let (Subjects::Pkh(Pkh::Eip155(expected)), Subjects::Pkh(Pkh::Eip155(s))) =
+            (&p.subject, &subject)
+        {
+            if s.chain_id != expected.chain_id {
+                return Err(ClientError::DelegatedConf(format!(
+                    "Expected chain id {} got {}",
+                    expected.chain_id, s.chain_id
+                )));
             }
-        };
+        }
+
+        // Check the statement against the ReCap's caveats before sending it to the witness.
+        p.caveats(&t)
+            .check(&statement)
+            .map_err(|e| ClientError::DelegatedConf(e.to_string()))?;
 
         let plain_text = statement
             .generate_statement()
@@ -224,6 +227,7 @@ impl DelegatedAttestationConfig {
             service_key: self.service_key.clone(),
             siwe_message: self.siwe_recap_message.clone(),
             siwe_signature: self.siwe_recap_signature.clone(),
+            parents: self.parents.clone(),
         })
     }
 }
@@ -234,6 +238,23 @@ struct WitnessErr {
     pub error: String,
 }
 
+// witness_err decodes an error response that didn't parse as the expected type, p being the
+// parse error. Problem responses are kept whole, older witnesses' errors are wrapped with f.
+fn witness_err(
+    val: serde_json::Value,
+    p: serde_json::Error,
+    f: fn(String) -> ClientError,
+) -> ClientError {
+    if let Ok(problem) = serde_json::from_value::<Problem>(val.clone()) {
+        return ClientError::Witness(problem);
+    }
+
+    match serde_json::from_value::<WitnessErr>(val) {
+        Err(_) => f(p.to_string()),
+        Ok(w) => f(w.error),
+    }
+}
+
 impl Client {
     pub fn new(
         endpoints: Endpoints,
@@ -246,16 +267,37 @@ impl Client {
         Ok(Client {
             endpoints,
             resolver_opts,
+            witness_did: None,
+            policy: None,
         })
     }
 
+    pub fn with_witness_did(mut self, witness_did: &str) -> Self {
+        self.witness_did = Some(witness_did.to_string());
+        self
+    }
+
+    // policy errs rather than accept credentials from any issuer if neither witness_did nor
+    // the policy say which are trusted.
+    fn policy(&self) -> Result<Option<VerificationPolicy>, ClientError> {
+        match &self.witness_did {
+            Some(did) => Ok(Some(VerificationPolicy::or_issuer(&self.policy, did))),
+            None => match &self.policy {
+                Some(p) if !p.issuers.is_empty() => Ok(Some(p.clone())),
+                _ => Err(ClientError::Config(
+                    "No witness_did or trusted issuers to verify credentials with".to_string(),
+                )),
+            },
+        }
+    }
+
     pub async fn instructions(
         &self,
         req: InstructionsReq,
     ) -> Result<serde_json::Value, ClientError> {
         let client = HttpClient::new();
 
-        let res = client
+        let res: serde_json::Value = client
             .post(self.endpoints.instructions.clone())
             .json(&req)
             .send()
@@ -265,7 +307,10 @@ impl Client {
             .await
             .map_err(|e| ClientError::Statement(e.to_string()))?;
 
-        Ok(res)
+        match serde_json::from_value::<Problem>(res.clone()) {
+            Ok(problem) => Err(ClientError::Witness(problem)),
+            Err(_) => Ok(res),
+        }
     }
 
     pub async fn statement(&self, req: Statements) -> Result<StatementResponse, ClientError> {
@@ -282,10 +327,7 @@ impl Client {
             Err(e) => Err(ClientError::Statement(e.to_string())),
             Ok(val) => match serde_json::from_value::<StatementResponse>(val.clone()) {
                 Ok(r) => Ok(r),
-                Err(p) => match serde_json::from_value::<WitnessErr>(val) {
-                    Err(_) => Err(ClientError::Statement(p.to_string())),
-                    Ok(w) => Err(ClientError::Statement(w.error)),
-                },
+                Err(p) => Err(witness_err(val, p, ClientError::Statement)),
             },
         }
     }
@@ -306,10 +348,7 @@ impl Client {
                     Err(e) => Err(ClientError::JWT(e.to_string())),
                     Ok(val) => match serde_json::from_value::<JWTWrapper>(val.clone()) {
                         Ok(r) => Ok(r),
-                        Err(p) => match serde_json::from_value::<WitnessErr>(val) {
-                            Err(_) => Err(ClientError::JWT(p.to_string())),
-                            Ok(w) => Err(ClientError::JWT(w.error)),
-                        },
+                        Err(p) => Err(witness_err(val, p, ClientError::JWT)),
                     },
                 }
             }
@@ -333,10 +372,7 @@ impl Client {
                     Err(e) => Err(ClientError::Ld(e.to_string())),
                     Ok(val) => match serde_json::from_value::<CredentialWrapper>(val.clone()) {
                         Ok(r) => Ok(r),
-                        Err(p) => match serde_json::from_value::<WitnessErr>(val) {
-                            Err(_) => Err(ClientError::Ld(p.to_string())),
-                            Ok(w) => Err(ClientError::Ld(w.error)),
-                        },
+                        Err(p) => Err(witness_err(val, p, ClientError::Ld)),
                     },
                 }
             }
@@ -346,10 +382,47 @@ impl Client {
 
     pub async fn verify(&self, req: VCWrapper) -> Result<VerifyRes, ClientError> {
         Ok(VerifyRes {
-            success: handle_verify(&req, &self.resolver_opts).await.is_ok(),
+            success: handle_verify(&req, &self.resolver_opts, &self.policy()?)
+                .await
+                .is_ok(),
         })
     }
 
+    // verify_report is verify with the result of each check, for showing users what failed.
+    pub async fn verify_report(&self, req: VCWrapper) -> Result<VerificationReport, ClientError> {
+        handle_verify_report(&req, &self.resolver_opts, &self.policy()?)
+            .await
+            .map_err(|e| ClientError::Ld(e.to_string()))
+    }
+
+    // revoke asks the witness to refuse the delegated sessions targeted by the revocation,
+    // which must be signed by the delegator.
+    pub async fn revoke(&self, req: SignedRevocation) -> Result<VerifyRes, ClientError> {
+        match &self.endpoints.revoke {
+            Some(endpoint) => {
+                let client = HttpClient::new();
+
+                let res = client
+                    .post(endpoint.clone())
+                    .json(&req)
+                    .send()
+                    .await
+                    .map_err(|e| ClientError::Revocation(e.to_string()))?;
+
+                match res.json::<serde_json::Value>().await {
+                    Err(e) => Err(ClientError::Revocation(e.to_string())),
+                    Ok(val) => match serde_json::from_value::<VerifyRes>(val.clone()) {
+                        Ok(r) => Ok(r),
+                        Err(p) => Err(witness_err(val, p, ClientError::Revocation)),
+                    },
+                }
+            }
+            None => Err(ClientError::Revocation(
+                "No configured revoke endpoint".to_string(),
+            )),
+        }
+    }
+
     pub async fn witness_verify(&self, req: VCWrapper) -> Result<VerifyRes, ClientError> {
         match &self.endpoints.verify {
             Some(endpoint) => {
@@ -366,10 +439,7 @@ impl Client {
                     Err(e) => Err(ClientError::Ld(e.to_string())),
                     Ok(val) => match serde_json::from_value::<VerifyRes>(val.clone()) {
                         Ok(r) => Ok(r),
-                        Err(p) => match serde_json::from_value::<WitnessErr>(val) {
-                            Err(_) => Err(ClientError::Ld(p.to_string())),
-                            Ok(w) => Err(ClientError::Ld(w.error)),
-                        },
+                        Err(p) => Err(witness_err(val, p, ClientError::Ld)),
                     },
                 }
             }
@@ -399,6 +469,25 @@ impl Client {
         })
     }
 
+    // siwe_message_with_caveats is siwe_message with each capability restricted by its caveats.
+    pub async fn siwe_message_with_caveats(
+        session_config: SessionConfig,
+        service_key: &str,
+        delegated_capabilities: &[DelegatedCapability],
+    ) -> Result<DelegatedAttestationPreConfig, ClientError> {
+        let mut session_config = session_config;
+        let s = session_config
+            .generate_message_with_caveats(service_key, delegated_capabilities)
+            .await
+            .map_err(|e| ClientError::Capability(e.to_string()))?;
+
+        Ok(DelegatedAttestationPreConfig {
+            service_key: service_key.to_string(),
+            session_config,
+            siwe_recap_message: s,
+        })
+    }
+
     // TODO: Something better with T?
     // TODO: This could possibly be a stand-alone fn since it has no ref to self
     pub fn attestation_types_to_actions<T>(
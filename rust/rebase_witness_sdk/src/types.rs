@@ -1,9 +1,11 @@
 pub use rebase::{
     content::{
-        attestation::content::AttestationContent,
+        attestation::content::AttestationContent, bluesky_verification::BlueskyVerificationContent,
         delegated_attestation::content::DelegatedAttestationContent,
-        dns_verification::DnsVerificationContent, email_verification::EmailVerificationContent,
+        discord_verification::DiscordVerificationContent, dns_verification::DnsVerificationContent,
+        email_verification::EmailVerificationContent, forge_verification::ForgeVerificationContent,
         github_verification::GitHubVerificationContent,
+        mastodon_verification::MastodonVerificationContent,
         nft_ownership_verification::NftOwnershipVerificationContent,
         poap_ownership_verification::PoapOwnershipVerificationContent,
         reddit_verification::RedditVerificationContent,
@@ -14,10 +16,14 @@ pub use rebase::{
     context::context_loader::context_loader,
     flow::{
         attestation::AttestationFlow,
+        bluesky_verification::BlueskyVerificationFlow,
         delegated_attestation::DelegatedAttestationFlow,
+        discord_verification::DiscordVerificationFlow,
         dns_verification::DnsVerificationFlow,
         email_verification::SendGridBasicFlow as EmailVerificationFlow,
+        forge_verification::{ForgeInstance, ForgeKind, ForgeVerificationFlow},
         github_verification::GitHubVerificationFlow,
+        mastodon_verification::MastodonVerificationFlow,
         nft_ownership_verification::{Alchemy, NftOwnershipVerificationFlow},
         poap_ownership_verification::PoapOwnershipVerificationFlow,
         reddit_verification::RedditVerificationFlow,
@@ -27,17 +33,27 @@ pub use rebase::{
     },
     issuer,
     proof::{
-        attestation::proof::AttestationProof, delegated_attestation::DelegatedAttestationProof,
-        email_verification::EmailVerificationProof, github_verification::GitHubVerificationProof,
+        attestation::proof::AttestationProof,
+        bluesky_verification::BlueskyVerificationProof,
+        delegated_attestation::{DelegatedAttestationProof, SignedReCap},
+        discord_verification::DiscordVerificationProof,
+        email_verification::EmailVerificationProof,
+        forge_verification::ForgeVerificationProof,
+        github_verification::GitHubVerificationProof,
+        mastodon_verification::MastodonVerificationProof,
         nft_ownership_verification::NftOwnershipVerificationProof,
         poap_ownership_verification::PoapOwnershipVerificationProof,
         same_controller_assertion::SameControllerAssertionProof,
         twitter_verification::TwitterVerificationProof,
     },
     statement::{
-        attestation::statement::AttestationStatement, dns_verification::DnsVerificationStatement,
-        email_verification::EmailVerificationStatement,
+        attestation::statement::AttestationStatement,
+        bluesky_verification::BlueskyVerificationStatement,
+        discord_verification::DiscordVerificationStatement,
+        dns_verification::DnsVerificationStatement, email_verification::EmailVerificationStatement,
+        forge_verification::ForgeVerificationStatement,
         github_verification::GitHubVerificationStatement,
+        mastodon_verification::MastodonVerificationStatement,
         nft_ownership_verification::NftOwnershipVerificationStatement,
         poap_ownership_verification::PoapOwnershipVerificationStatement,
         reddit_verification::RedditVerificationStatement,
@@ -46,18 +62,44 @@ pub use rebase::{
         twitter_verification::TwitterVerificationStatement,
     },
     types::{
+        capability::{
+            recap::{recap_cid, redelegation_message, IssuanceStore, MemoryIssuanceStore},
+            revocation::{
+                MemoryRevocationStore, Revocation, RevocationStore, RevocationTarget,
+                SignedRevocation,
+            },
+        },
         defs::{
-            get_verification_method, make_resolver, to_action, Capability, Content, ContextLoader,
-            Credential, DIDKey, DIDMethod, DIDMethods, DIDResolver, Evidence, Flow, Instructions,
-            Issuer, LinkedDataProofOptions, OneOrMany, Proof, ResolverOpts, SessionConfig, Source,
-            Statement, StatementResponse, UCanCapability, UcanResource, UcanScope, DIDURL, JWK,
-            URI,
+            check_validity, get_verification_method, make_resolver, set_validity, to_action,
+            verify_jwt, Capability, Caveats, Content, ContextLoader, Credential, CredentialOrJWT,
+            DIDKey, DIDMethod, DIDMethods, DIDResolver, DateTime, DelegatedCapability, EthRpcOpts,
+            Evidence, Flow, Holder, Instructions, Issuer, LinkedDataProofOptions, OneOrMany,
+            Presentation, Proof, ResolverOpts, SessionConfig, Source, Statement, StatementResponse,
+            Subject, UCanCapability, UcanResource, UcanScope, Utc, DIDURL, JWK, URI,
+        },
+        enums::{attestation::AttestationTypes, subject::Subjects},
+        error::{
+            CapabilityError, ContentError, FlowError, PolicyError, PresentationError, Problem,
+            ProofError, RebaseError, StatementError, StatusError,
+        },
+        nonce::{
+            issue_challenge, nonce_store, redeem_challenge, MemoryNonceStore, NonceStore, RateLimit,
+        },
+        policy::VerificationPolicy,
+        presentation::{
+            check_holder_binding, credentials, presentation, presentation_jwt,
+            unsigned_presentation, PresentationOptions,
+        },
+        status::{
+            check_status, MemoryStatusListStore, StatusListManager, StatusListState,
+            StatusListStore,
         },
-        enums::attestation::AttestationTypes,
-        error::{CapabilityError, ContentError, FlowError, ProofError, StatementError},
     },
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use rebase::types::{capability::revocation::FileRevocationStore, status::FileStatusListStore};
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -70,9 +112,13 @@ use wasm_bindgen::prelude::*;
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum FlowType {
+    BlueskyVerification,
+    DiscordVerification,
     DnsVerification,
     EmailVerification,
+    ForgeVerification,
     GitHubVerification,
+    MastodonVerification,
     NftOwnershipVerification,
     PoapOwnershipVerification,
     RedditVerification,
@@ -86,9 +132,13 @@ pub enum FlowType {
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum Contents {
+    BlueskyVerification(BlueskyVerificationContent),
+    DiscordVerification(DiscordVerificationContent),
     DnsVerification(DnsVerificationContent),
     EmailVerification(EmailVerificationContent),
+    ForgeVerification(ForgeVerificationContent),
     GitHubVerification(GitHubVerificationContent),
+    MastodonVerification(MastodonVerificationContent),
     NftOwnershipVerification(NftOwnershipVerificationContent),
     PoapOwnershipVerification(PoapOwnershipVerificationContent),
     RedditVerification(RedditVerificationContent),
@@ -103,9 +153,13 @@ pub enum Contents {
 impl Content for Contents {
     fn context(&self) -> Result<serde_json::Value, ContentError> {
         match self {
+            Contents::BlueskyVerification(x) => x.context(),
+            Contents::DiscordVerification(x) => x.context(),
             Contents::DnsVerification(x) => x.context(),
             Contents::EmailVerification(x) => x.context(),
+            Contents::ForgeVerification(x) => x.context(),
             Contents::GitHubVerification(x) => x.context(),
+            Contents::MastodonVerification(x) => x.context(),
             Contents::NftOwnershipVerification(x) => x.context(),
             Contents::PoapOwnershipVerification(x) => x.context(),
             Contents::RedditVerification(x) => x.context(),
@@ -119,9 +173,13 @@ impl Content for Contents {
 
     fn evidence(&self) -> Result<Option<OneOrMany<Evidence>>, ContentError> {
         match self {
+            Contents::BlueskyVerification(x) => x.evidence(),
+            Contents::DiscordVerification(x) => x.evidence(),
             Contents::DnsVerification(x) => x.evidence(),
             Contents::EmailVerification(x) => x.evidence(),
+            Contents::ForgeVerification(x) => x.evidence(),
             Contents::GitHubVerification(x) => x.evidence(),
+            Contents::MastodonVerification(x) => x.evidence(),
             Contents::NftOwnershipVerification(x) => x.evidence(),
             Contents::PoapOwnershipVerification(x) => x.evidence(),
             Contents::RedditVerification(x) => x.evidence(),
@@ -135,9 +193,13 @@ impl Content for Contents {
 
     fn subject(&self) -> Result<serde_json::Value, ContentError> {
         match self {
+            Contents::BlueskyVerification(x) => x.subject(),
+            Contents::DiscordVerification(x) => x.subject(),
             Contents::DnsVerification(x) => x.subject(),
             Contents::EmailVerification(x) => x.subject(),
+            Contents::ForgeVerification(x) => x.subject(),
             Contents::GitHubVerification(x) => x.subject(),
+            Contents::MastodonVerification(x) => x.subject(),
             Contents::NftOwnershipVerification(x) => x.subject(),
             Contents::PoapOwnershipVerification(x) => x.subject(),
             Contents::RedditVerification(x) => x.subject(),
@@ -151,9 +213,13 @@ impl Content for Contents {
 
     fn types(&self) -> Result<Vec<String>, ContentError> {
         match self {
+            Contents::BlueskyVerification(x) => x.types(),
+            Contents::DiscordVerification(x) => x.types(),
             Contents::DnsVerification(x) => x.types(),
             Contents::EmailVerification(x) => x.types(),
+            Contents::ForgeVerification(x) => x.types(),
             Contents::GitHubVerification(x) => x.types(),
+            Contents::MastodonVerification(x) => x.types(),
             Contents::NftOwnershipVerification(x) => x.types(),
             Contents::PoapOwnershipVerification(x) => x.types(),
             Contents::RedditVerification(x) => x.types(),
@@ -169,9 +235,13 @@ impl Content for Contents {
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum Statements {
+    BlueskyVerification(BlueskyVerificationStatement),
+    DiscordVerification(DiscordVerificationStatement),
     DnsVerification(DnsVerificationStatement),
     EmailVerification(EmailVerificationStatement),
+    ForgeVerification(ForgeVerificationStatement),
     GitHubVerification(GitHubVerificationStatement),
+    MastodonVerification(MastodonVerificationStatement),
     // NOTE: If adding non-alchemy providers, this will need to change
     // to an enum.
     NftOwnershipVerification(NftOwnershipVerificationStatement),
@@ -186,9 +256,13 @@ pub enum Statements {
 impl Statement for Statements {
     fn generate_statement(&self) -> Result<String, StatementError> {
         match &self {
+            Statements::BlueskyVerification(x) => x.generate_statement(),
+            Statements::DiscordVerification(x) => x.generate_statement(),
             Statements::DnsVerification(x) => x.generate_statement(),
             Statements::EmailVerification(x) => x.generate_statement(),
+            Statements::ForgeVerification(x) => x.generate_statement(),
             Statements::GitHubVerification(x) => x.generate_statement(),
+            Statements::MastodonVerification(x) => x.generate_statement(),
             Statements::NftOwnershipVerification(x) => x.generate_statement(),
             Statements::PoapOwnershipVerification(x) => x.generate_statement(),
             Statements::RedditVerification(x) => x.generate_statement(),
@@ -203,9 +277,13 @@ impl Statement for Statements {
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum Proofs {
+    BlueskyVerification(BlueskyVerificationProof),
+    DiscordVerification(DiscordVerificationProof),
     DnsVerification(DnsVerificationStatement),
     EmailVerification(EmailVerificationProof),
+    ForgeVerification(ForgeVerificationProof),
     GitHubVerification(GitHubVerificationProof),
+    MastodonVerification(MastodonVerificationProof),
     // NOTE: If adding non-alchemy providers, this will need to change
     // to an enum.
     NftOwnershipVerification(NftOwnershipVerificationProof),
@@ -221,9 +299,13 @@ pub enum Proofs {
 impl Statement for Proofs {
     fn generate_statement(&self) -> Result<String, StatementError> {
         match &self {
+            Proofs::BlueskyVerification(x) => x.generate_statement(),
+            Proofs::DiscordVerification(x) => x.generate_statement(),
             Proofs::DnsVerification(x) => x.generate_statement(),
             Proofs::EmailVerification(x) => x.generate_statement(),
+            Proofs::ForgeVerification(x) => x.generate_statement(),
             Proofs::GitHubVerification(x) => x.generate_statement(),
+            Proofs::MastodonVerification(x) => x.generate_statement(),
             Proofs::NftOwnershipVerification(x) => x.generate_statement(),
             Proofs::PoapOwnershipVerification(x) => x.generate_statement(),
             Proofs::RedditVerification(x) => x.generate_statement(),
@@ -239,15 +321,27 @@ impl Statement for Proofs {
 impl Proof<Contents> for Proofs {
     fn to_content(&self, statement: &str, signature: &str) -> Result<Contents, ProofError> {
         match self {
+            Proofs::BlueskyVerification(x) => Ok(Contents::BlueskyVerification(
+                x.to_content(statement, signature)?,
+            )),
+            Proofs::DiscordVerification(x) => Ok(Contents::DiscordVerification(
+                x.to_content(statement, signature)?,
+            )),
             Proofs::DnsVerification(x) => Ok(Contents::DnsVerification(
                 x.to_content(statement, signature)?,
             )),
             Proofs::EmailVerification(x) => Ok(Contents::EmailVerification(
                 x.to_content(statement, signature)?,
             )),
+            Proofs::ForgeVerification(x) => Ok(Contents::ForgeVerification(
+                x.to_content(statement, signature)?,
+            )),
             Proofs::GitHubVerification(x) => Ok(Contents::GitHubVerification(
                 x.to_content(statement, signature)?,
             )),
+            Proofs::MastodonVerification(x) => Ok(Contents::MastodonVerification(
+                x.to_content(statement, signature)?,
+            )),
             Proofs::NftOwnershipVerification(x) => Ok(Contents::NftOwnershipVerification(
                 x.to_content(statement, signature)?,
             )),
@@ -276,13 +370,152 @@ impl Proof<Contents> for Proofs {
     }
 }
 
+impl Proofs {
+    // from_credential rebuilds the proof a web2 flow issued vc for, so its evidence can be
+    // looked up again. The signature isn't kept in the credential, so it must still be
+    // where the flow found it. subject is needed if it can't be recovered from its DID.
+    pub fn from_credential(vc: &Credential, subject: Option<Subjects>) -> Result<Self, FlowError> {
+        let cs = match &vc.credential_subject {
+            OneOrMany::One(cs) => cs,
+            OneOrMany::Many(_) => {
+                return Err(FlowError::Validation(
+                    "Credential has more than one subject".to_string(),
+                ))
+            }
+        };
+
+        let did = cs
+            .id
+            .as_ref()
+            .ok_or_else(|| FlowError::Validation("Credential subject has no id".to_string()))?
+            .to_string();
+        let subject = match subject {
+            Some(s) if s.did()? == did => s,
+            Some(_) => {
+                return Err(FlowError::Validation(format!(
+                    "Subject does not match the credential subject {}",
+                    did
+                )))
+            }
+            None => Subjects::from_did(&did)?,
+        };
+
+        let field = |props: Option<&std::collections::HashMap<String, serde_json::Value>>,
+                     key: &str|
+         -> Result<String, FlowError> {
+            props
+                .and_then(|p| p.get(key))
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string())
+                .ok_or_else(|| FlowError::Validation(format!("Credential has no {}", key)))
+        };
+        let same_as = || field(cs.property_set.as_ref(), "sameAs");
+        let evidence = |key: &str| match &vc.evidence {
+            Some(OneOrMany::One(e)) => field(e.property_set.as_ref(), key),
+            _ => Err(FlowError::Validation(format!(
+                "Credential has no evidence of {}",
+                key
+            ))),
+        };
+        let is = |t: &str| vc.type_.contains(&t.to_string());
+
+        if is("BlueskyVerification") {
+            Ok(Proofs::BlueskyVerification(BlueskyVerificationProof {
+                post_url: evidence("postUrl").ok(),
+                statement: BlueskyVerificationStatement {
+                    handle: evidence("handle")?,
+                    subject,
+                },
+            }))
+        } else if is("DiscordVerification") {
+            Ok(Proofs::DiscordVerification(DiscordVerificationProof {
+                channel_id: evidence("channelId")?,
+                message_id: evidence("messageId")?,
+                statement: DiscordVerificationStatement {
+                    username: evidence("username")?,
+                    user_id: evidence("userId").ok(),
+                    subject,
+                },
+            }))
+        } else if is("DnsVerification") {
+            let domain = same_as()?;
+            Ok(Proofs::DnsVerification(DnsVerificationStatement {
+                domain: domain.trim_start_matches("dns:").to_string(),
+                prefix: evidence("prefix")?,
+                subject,
+            }))
+        } else if is("ForgeVerification") {
+            Ok(Proofs::ForgeVerification(ForgeVerificationProof {
+                snippet_id: evidence("snippetId")?,
+                statement: ForgeVerificationStatement {
+                    handle: evidence("handle")?,
+                    domain: evidence("domain")?,
+                    subject,
+                },
+            }))
+        } else if is("GitHubVerification") {
+            Ok(Proofs::GitHubVerification(GitHubVerificationProof {
+                gist_id: evidence("gistId")?,
+                statement: GitHubVerificationStatement {
+                    handle: evidence("handle")?,
+                    subject,
+                },
+            }))
+        } else if is("MastodonVerification") {
+            Ok(Proofs::MastodonVerification(MastodonVerificationProof {
+                status_url: evidence("statusUrl").ok(),
+                statement: MastodonVerificationStatement {
+                    handle: evidence("handle")?,
+                    domain: evidence("domain")?,
+                    subject,
+                },
+            }))
+        } else if is("RedditVerification") {
+            Ok(Proofs::RedditVerification(RedditVerificationStatement {
+                handle: evidence("handle")?,
+                subject,
+            }))
+        } else if is("SoundCloudVerification") {
+            Ok(Proofs::SoundCloudVerification(
+                SoundCloudVerificationStatement {
+                    permalink: evidence("permalink")?,
+                    subject,
+                },
+            ))
+        } else if is("TwitterVerification") {
+            let handle = evidence("handle")?;
+            Ok(Proofs::TwitterVerification(TwitterVerificationProof {
+                tweet_url: format!(
+                    "https://twitter.com/{}/status/{}",
+                    handle,
+                    evidence("tweetId")?
+                ),
+                statement: TwitterVerificationStatement { handle, subject },
+            }))
+        } else {
+            Err(FlowError::Validation(format!(
+                "Credentials of type {} cannot be refreshed",
+                vc.type_
+                    .clone()
+                    .into_iter()
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )))
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct WitnessFlow {
+    pub bluesky_verification: Option<BlueskyVerificationFlow>,
+    pub discord_verification: Option<DiscordVerificationFlow>,
     pub dns_verification: Option<DnsVerificationFlow>,
     pub email_verification: Option<EmailVerificationFlow>,
+    pub forge_verification: Option<ForgeVerificationFlow>,
     #[serde(rename = "GitHubVerification")]
     pub github_verification: Option<GitHubVerificationFlow>,
+    pub mastodon_verification: Option<MastodonVerificationFlow>,
     pub nft_ownership_verification: Option<NftOwnershipVerificationFlow>,
     pub poap_ownership_verification: Option<PoapOwnershipVerificationFlow>,
     pub reddit_verification: Option<RedditVerificationFlow>,
@@ -292,94 +525,358 @@ pub struct WitnessFlow {
     pub twitter_verification: Option<TwitterVerificationFlow>,
     pub attestation: Option<AttestationFlow>,
     pub delegated_attestation: Option<DelegatedAttestationFlow>,
+    // When set, issued credentials get a credentialStatus in this list so they can be revoked.
+    #[serde(default)]
+    pub status_list: Option<StatusListManager>,
+    // How long issued credentials are valid for, unless the flow sets its own validity.
+    #[serde(default)]
+    pub validity_minutes: Option<i64>,
+}
+
+// or_default sets field to the witness-wide default if the flow didn't configure its own.
+fn or_default<T: Clone>(field: &mut Option<T>, default: &Option<T>) {
+    if field.is_none() {
+        *field = default.clone();
+    }
+}
+
+impl WitnessFlow {
+    // set_resolver_opts has every configured flow that doesn't set its own ResolverOpts use opts
+    // to resolve the DIDs of the subjects whose signatures it checks.
+    pub fn set_resolver_opts(&mut self, opts: &Option<ResolverOpts>) {
+        if let Some(x) = &mut self.bluesky_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.discord_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.dns_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.email_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.forge_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.github_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.mastodon_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.reddit_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.same_controller_assertion {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.soundcloud_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.twitter_verification {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.attestation {
+            or_default(&mut x.resolver_opts, opts);
+        }
+        if let Some(x) = &mut self.delegated_attestation {
+            or_default(&mut x.resolver_opts, opts);
+        }
+    }
+
+    // set_eth_rpc_opts has every configured flow that doesn't set its own EthRpcOpts use opts
+    // to check the signatures of Ethereum contract wallets.
+    pub fn set_eth_rpc_opts(&mut self, opts: &Option<EthRpcOpts>) {
+        if let Some(x) = &mut self.bluesky_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.discord_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.dns_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.email_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.forge_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.github_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.mastodon_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(NftOwnershipVerificationFlow::Alchemy(x)) = &mut self.nft_ownership_verification
+        {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.poap_ownership_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.reddit_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.same_controller_assertion {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.soundcloud_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.twitter_verification {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.attestation {
+            or_default(&mut x.eth_rpc, opts);
+        }
+        if let Some(x) = &mut self.delegated_attestation {
+            or_default(&mut x.eth_rpc, opts);
+        }
+    }
+
+    // validity_for returns the validity of credentials issued for proof.
+    fn validity_for(&self, proof: &Proofs) -> Option<i64> {
+        let v = match proof {
+            Proofs::BlueskyVerification(_) => self
+                .bluesky_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::DiscordVerification(_) => self
+                .discord_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::DnsVerification(_) => self
+                .dns_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::EmailVerification(_) => self
+                .email_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::ForgeVerification(_) => self
+                .forge_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::GitHubVerification(_) => self
+                .github_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::MastodonVerification(_) => self
+                .mastodon_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::NftOwnershipVerification(_) => self
+                .nft_ownership_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::PoapOwnershipVerification(_) => self
+                .poap_ownership_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::RedditVerification(_) => self
+                .reddit_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::SoundCloudVerification(_) => self
+                .soundcloud_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::TwitterVerification(_) => self
+                .twitter_verification
+                .as_ref()
+                .and_then(|x| x.validity_minutes()),
+            Proofs::SameControllerAssertion(_)
+            | Proofs::Attestation(_)
+            | Proofs::DelegatedAttestation(_) => None,
+        };
+
+        v.or(self.validity_minutes)
+    }
+
+    // status_credential validates the proof and returns the unsigned credential, with a
+    // credentialStatus if a status list is configured.
+    async fn status_credential<I: Issuer + Send + Clone>(
+        &self,
+        proof: Proofs,
+        issuer: I,
+    ) -> Result<Credential, FlowError> {
+        let f = self.unsigned_credential(proof, issuer.clone(), issuer);
+        let mut vc = f.await?;
+        if let Some(s) = &self.status_list {
+            s.add_status(&mut vc).await?;
+        }
+        Ok(vc)
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 impl Flow<Contents, Statements, Proofs> for WitnessFlow {
+    async fn credential<I: Issuer + Send + Clone>(
+        &self,
+        proof: Proofs,
+        issuer: I,
+    ) -> Result<Credential, FlowError> {
+        let f = self.status_credential(proof, issuer.clone());
+        let mut vc = f.await?;
+        let f = issuer.sign_vc(&mut vc);
+        f.await?;
+        Ok(vc)
+    }
+
     // NOTE: This is unused, currently
     fn instructions(&self) -> Result<Instructions, FlowError> {
         Err(FlowError::Validation("Cannot use generalized Instructions function for generalized witness, use get_instructions".to_owned()))
     }
 
+    async fn jwt<I: Issuer + Send + Clone>(
+        &self,
+        proof: Proofs,
+        issuer: I,
+    ) -> Result<String, FlowError> {
+        let f = self.status_credential(proof, issuer.clone());
+        let vc = f.await?;
+        let f = issuer.generate_jwt(&vc);
+        Ok(f.await?)
+    }
+
     async fn statement<I: Issuer + Send + Clone>(
         &self,
         stmt: Statements,
         issuer: I,
     ) -> Result<StatementResponse, FlowError> {
         match stmt {
+            Statements::BlueskyVerification(s) => match &self.bluesky_verification {
+                Some(x) => Ok(x.statement(s, issuer).await?),
+                None => Err(FlowError::Misconfigured(
+                    "no bluesky_verification flow configured".to_owned(),
+                )),
+            },
+            Statements::DiscordVerification(s) => match &self.discord_verification {
+                Some(x) => Ok(x.statement(s, issuer).await?),
+                None => Err(FlowError::Misconfigured(
+                    "no discord flow configured".to_owned(),
+                )),
+            },
             Statements::DnsVerification(s) => match &self.dns_verification {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no dns_verification flow configured".to_owned(),
                 )),
             },
             Statements::EmailVerification(s) => match &self.email_verification {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no email_verification flow configured".to_owned(),
                 )),
             },
+            Statements::ForgeVerification(s) => match &self.forge_verification {
+                Some(x) => Ok(x.statement(s, issuer).await?),
+                None => Err(FlowError::Misconfigured(
+                    "no forge_verification flow configured".to_owned(),
+                )),
+            },
             Statements::GitHubVerification(s) => match &self.github_verification {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no github_verification flow configured".to_owned(),
                 )),
             },
+            Statements::MastodonVerification(s) => match &self.mastodon_verification {
+                Some(x) => Ok(x.statement(s, issuer).await?),
+                None => Err(FlowError::Misconfigured(
+                    "no mastodon_verification flow configured".to_owned(),
+                )),
+            },
             Statements::NftOwnershipVerification(s) => match &self.nft_ownership_verification {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no nft_ownership flow configured".to_owned(),
                 )),
             },
             Statements::PoapOwnershipVerification(s) => match &self.poap_ownership_verification {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no poap_ownership flow configured".to_owned(),
                 )),
             },
             Statements::RedditVerification(s) => match &self.reddit_verification {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no reddit flow configured".to_owned(),
                 )),
             },
             Statements::SameControllerAssertion(s) => match &self.same_controller_assertion {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation("no same flow configured".to_owned())),
+                None => Err(FlowError::Misconfigured(
+                    "no same flow configured".to_owned(),
+                )),
             },
             Statements::SoundCloudVerification(s) => match &self.soundcloud_verification {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no soundcloud flow configured".to_owned(),
                 )),
             },
             Statements::TwitterVerification(s) => match &self.twitter_verification {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no twitter flow configured".to_owned(),
                 )),
             },
             Statements::Attestation(s) => match &self.attestation {
                 Some(x) => Ok(x.statement(s, issuer).await?),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no attestation flow configured".to_owned(),
                 )),
             },
         }
     }
 
+    async fn unsigned_credential<Subj: Subject + Send, I: Issuer + Send>(
+        &self,
+        proof: Proofs,
+        subj: Subj,
+        issuer: I,
+    ) -> Result<Credential, FlowError> {
+        let validity = self.validity_for(&proof);
+        let f = self.validate_proof(proof, issuer);
+        let content = f.await?;
+        let f = content.unsigned_credential(subj);
+        let mut vc = f.await?;
+        set_validity(&mut vc, validity)?;
+        Ok(vc)
+    }
+
     async fn validate_proof<I: Issuer + Send>(
         &self,
         proof: Proofs,
         issuer: I,
     ) -> Result<Contents, FlowError> {
         match proof {
+            Proofs::BlueskyVerification(p) => match &self.bluesky_verification {
+                Some(x) => Ok(Contents::BlueskyVerification(
+                    x.validate_proof(p, issuer).await?,
+                )),
+                None => Err(FlowError::Misconfigured(
+                    "no bluesky flow configured".to_owned(),
+                )),
+            },
+            Proofs::DiscordVerification(p) => match &self.discord_verification {
+                Some(x) => Ok(Contents::DiscordVerification(
+                    x.validate_proof(p, issuer).await?,
+                )),
+                None => Err(FlowError::Misconfigured(
+                    "no discord flow configured".to_owned(),
+                )),
+            },
             Proofs::DnsVerification(p) => match &self.dns_verification {
                 Some(x) => Ok(Contents::DnsVerification(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no dns_verification flow configured".to_owned(),
                 )),
             },
@@ -387,23 +884,39 @@ impl Flow<Contents, Statements, Proofs> for WitnessFlow {
                 Some(x) => Ok(Contents::EmailVerification(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no email_verification flow configured".to_owned(),
                 )),
             },
+            Proofs::ForgeVerification(p) => match &self.forge_verification {
+                Some(x) => Ok(Contents::ForgeVerification(
+                    x.validate_proof(p, issuer).await?,
+                )),
+                None => Err(FlowError::Misconfigured(
+                    "no forge flow configured".to_owned(),
+                )),
+            },
             Proofs::GitHubVerification(p) => match &self.github_verification {
                 Some(x) => Ok(Contents::GitHubVerification(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no github flow configured".to_owned(),
                 )),
             },
+            Proofs::MastodonVerification(p) => match &self.mastodon_verification {
+                Some(x) => Ok(Contents::MastodonVerification(
+                    x.validate_proof(p, issuer).await?,
+                )),
+                None => Err(FlowError::Misconfigured(
+                    "no mastodon flow configured".to_owned(),
+                )),
+            },
             Proofs::NftOwnershipVerification(p) => match &self.nft_ownership_verification {
                 Some(x) => Ok(Contents::NftOwnershipVerification(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no nft_ownership flow configured".to_owned(),
                 )),
             },
@@ -411,7 +924,7 @@ impl Flow<Contents, Statements, Proofs> for WitnessFlow {
                 Some(x) => Ok(Contents::PoapOwnershipVerification(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no poap_ownership flow configured".to_owned(),
                 )),
             },
@@ -419,7 +932,7 @@ impl Flow<Contents, Statements, Proofs> for WitnessFlow {
                 Some(x) => Ok(Contents::RedditVerification(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no reddit flow configured".to_owned(),
                 )),
             },
@@ -427,13 +940,15 @@ impl Flow<Contents, Statements, Proofs> for WitnessFlow {
                 Some(x) => Ok(Contents::SameControllerAssertion(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation("no same flow configured".to_owned())),
+                None => Err(FlowError::Misconfigured(
+                    "no same flow configured".to_owned(),
+                )),
             },
             Proofs::SoundCloudVerification(p) => match &self.soundcloud_verification {
                 Some(x) => Ok(Contents::SoundCloudVerification(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no soundcloud flow configured".to_owned(),
                 )),
             },
@@ -441,13 +956,13 @@ impl Flow<Contents, Statements, Proofs> for WitnessFlow {
                 Some(x) => Ok(Contents::TwitterVerification(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no twitter flow configured".to_owned(),
                 )),
             },
             Proofs::Attestation(p) => match &self.attestation {
                 Some(x) => Ok(Contents::Attestation(x.validate_proof(p, issuer).await?)),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no attestation flow configured".to_owned(),
                 )),
             },
@@ -455,7 +970,7 @@ impl Flow<Contents, Statements, Proofs> for WitnessFlow {
                 Some(x) => Ok(Contents::DelegatedAttestation(
                     x.validate_proof(p, issuer).await?,
                 )),
-                None => Err(FlowError::Validation(
+                None => Err(FlowError::Misconfigured(
                     "no delegated attesation flow configured".to_owned(),
                 )),
             },
@@ -484,11 +999,47 @@ pub struct CredentialWrapper {
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum VCWrapper {
     Ld(CredentialWrapper),
     Jwt(JWTWrapper),
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PresentationWrapper {
+    pub presentation: Presentation,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum VPWrapper {
+    Ld(PresentationWrapper),
+    Jwt(JWTWrapper),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct VerifyPresentationReq {
+    #[serde(flatten)]
+    pub presentation: VPWrapper,
+    // The challenge handle_presentation_challenge issued, which the holder signed.
+    #[serde(default)]
+    pub challenge: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RefreshReq {
+    #[serde(flatten)]
+    pub credential: VCWrapper,
+    // Needed when the subject can't be recovered from its DID, e.g. for did:web or Tezos.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Subjects>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct VerifyRes {
@@ -498,65 +1049,93 @@ pub struct VerifyRes {
 impl WitnessFlow {
     pub fn get_instructions(&self, t: FlowType) -> Result<Instructions, FlowError> {
         match t {
+            FlowType::BlueskyVerification => match &self.bluesky_verification {
+                Some(x) => x.instructions(),
+                _ => Err(FlowError::Misconfigured(
+                    "no bluesky flow configured".to_owned(),
+                )),
+            },
+            FlowType::DiscordVerification => match &self.discord_verification {
+                Some(x) => x.instructions(),
+                _ => Err(FlowError::Misconfigured(
+                    "no discord flow configured".to_owned(),
+                )),
+            },
             FlowType::DnsVerification => match &self.dns_verification {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation("no dns flow configured".to_owned())),
+                _ => Err(FlowError::Misconfigured(
+                    "no dns flow configured".to_owned(),
+                )),
             },
             FlowType::EmailVerification => match &self.email_verification {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no email_verification flow configured".to_owned(),
                 )),
             },
+            FlowType::ForgeVerification => match &self.forge_verification {
+                Some(x) => x.instructions(),
+                _ => Err(FlowError::Misconfigured(
+                    "no forge flow configured".to_owned(),
+                )),
+            },
             FlowType::GitHubVerification => match &self.github_verification {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no github flow configured".to_owned(),
                 )),
             },
+            FlowType::MastodonVerification => match &self.mastodon_verification {
+                Some(x) => x.instructions(),
+                _ => Err(FlowError::Misconfigured(
+                    "no mastodon flow configured".to_owned(),
+                )),
+            },
             FlowType::NftOwnershipVerification => match &self.nft_ownership_verification {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no nft_ownership flow configured".to_owned(),
                 )),
             },
             FlowType::PoapOwnershipVerification => match &self.poap_ownership_verification {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no poap_ownership flow configured".to_owned(),
                 )),
             },
             FlowType::RedditVerification => match &self.reddit_verification {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no reddit flow configured".to_owned(),
                 )),
             },
             FlowType::SameControllerAssertion => match &self.same_controller_assertion {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation("no same flow configured".to_owned())),
+                _ => Err(FlowError::Misconfigured(
+                    "no same flow configured".to_owned(),
+                )),
             },
             FlowType::SoundCloudVerification => match &self.soundcloud_verification {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no soundcloud flow configured".to_owned(),
                 )),
             },
             FlowType::TwitterVerification => match &self.twitter_verification {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no twitter flow configured".to_owned(),
                 )),
             },
             FlowType::Attestation => match &self.attestation {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no attestation flow configured".to_owned(),
                 )),
             },
             FlowType::DelegatedAttestation => match &self.delegated_attestation {
                 Some(x) => x.instructions(),
-                _ => Err(FlowError::Validation(
+                _ => Err(FlowError::Misconfigured(
                     "no delegated attestation flow configured".to_owned(),
                 )),
             },
@@ -586,6 +1165,69 @@ impl WitnessFlow {
         Ok(json!(self.get_instructions(req.instruction_type.clone())?))
     }
 
+    pub async fn handle_revoke(
+        &self,
+        req: &SignedRevocation,
+    ) -> Result<serde_json::Value, FlowError> {
+        match &self.delegated_attestation {
+            Some(x) => {
+                x.revoke(req).await?;
+                Ok(json!({ "success": true }))
+            }
+            None => Err(FlowError::Misconfigured(
+                "no delegated attestation flow configured".to_owned(),
+            )),
+        }
+    }
+
+    // handle_refresh looks up the evidence of a credential this witness issued again, and
+    // reissues it in the same format if the claim still holds.
+    pub async fn handle_refresh<I: Issuer + Send + Clone>(
+        &self,
+        req: &RefreshReq,
+        issuer: I,
+    ) -> Result<serde_json::Value, FlowError> {
+        // The proof isn't checked, since the claim is verified again from scratch. The
+        // credential only has to say what was claimed, and that this witness issued it.
+        let vc = match &req.credential {
+            VCWrapper::Jwt(r) => Credential::from_jwt_unsigned(&r.jwt)
+                .map_err(|e| FlowError::Validation(e.to_string()))?,
+            VCWrapper::Ld(r) => r.credential.clone(),
+        };
+        if vc.issuer.as_ref().map(|i| i.get_id()) != Some(issuer.did()?) {
+            return Err(FlowError::Validation(
+                "Credential was not issued by this witness".to_string(),
+            ));
+        }
+
+        let proof = Proofs::from_credential(&vc, req.subject.clone())?;
+        let res = match &req.credential {
+            VCWrapper::Jwt(_) => self.handle_jwt(proof, issuer).await,
+            VCWrapper::Ld(_) => self.handle_ld(proof, issuer).await,
+        };
+
+        // Only missing evidence or a signature that no longer matches means the claim doesn't
+        // hold anymore, any other failure says nothing about it and is returned as is.
+        res.map_err(|e| match e.code() {
+            "evidence_not_found" | "signature_invalid" => {
+                FlowError::Validation(format!("Claim is no longer valid: {}", e))
+            }
+            _ => e,
+        })
+    }
+
+    pub async fn handle_status_list<I: Issuer + Send + Clone>(
+        &self,
+        issuer: I,
+    ) -> Result<serde_json::Value, FlowError> {
+        match &self.status_list {
+            Some(x) => Ok(json!(x.list_credential(&issuer).await?)),
+            None => Err(FlowError::Misconfigured(
+                "no status list configured".to_owned(),
+            )),
+        }
+    }
+
     pub async fn handle_statement<I: Issuer + Send + Clone>(
         &self,
         statement: Statements,
@@ -595,49 +1237,322 @@ impl WitnessFlow {
     }
 }
 
-pub async fn handle_verify(
+// CheckResult is the outcome of one check made while verifying a credential.
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct CheckResult {
+    pub passed: bool,
+    // Set if the check wasn't made because an earlier one failed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    // The FlowError code of the failure, as returned in problem responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    fn from_result(r: &Result<(), FlowError>) -> Self {
+        match r {
+            Ok(_) => CheckResult {
+                passed: true,
+                skipped: false,
+                code: None,
+                error: None,
+            },
+            Err(e) => CheckResult {
+                passed: false,
+                skipped: false,
+                code: Some(e.code().to_string()),
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    fn skipped(reason: &str) -> Self {
+        CheckResult {
+            passed: false,
+            skipped: true,
+            code: None,
+            error: Some(reason.to_string()),
+        }
+    }
+}
+
+// VerificationReport says what was found in a credential and which of its checks failed.
+#[derive(Clone, Debug, Deserialize, Serialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct VerificationReport {
+    // True if every check passed.
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    // Whether the issuer's DID resolved to a verification method.
+    pub issuer_resolved: CheckResult,
+    pub signature: CheckResult,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_purpose: Option<String>,
+    // Whether the credential is within its issuance and expiration dates.
+    pub validity: CheckResult,
+    // Present if the credential has a credentialStatus.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<CheckResult>,
+    // Present if the credential was checked against a VerificationPolicy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<CheckResult>,
+    // The Rebase credential type, if the credential is one Rebase issues.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_type: Option<String>,
+    #[tsify(type = "any")]
+    pub credential_subject: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tsify(type = "any")]
+    pub evidence: Option<serde_json::Value>,
+}
+
+impl VerificationReport {
+    // error returns why the credential failed to verify, if it did. Skipped checks are passed
+    // over for the failure that caused them to be skipped.
+    pub fn error(&self) -> Option<String> {
+        [
+            Some(&self.issuer_resolved),
+            Some(&self.signature),
+            Some(&self.validity),
+            self.status.as_ref(),
+            self.policy.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|c| !c.skipped)
+        .find_map(|c| c.error.clone())
+    }
+}
+
+// rebase_type returns the first of the credential's types that Rebase issues.
+fn rebase_type(vc: &Credential) -> Option<String> {
+    vc.type_.clone().into_iter().find(|t| {
+        let name = json!(t.trim_start_matches("Delegated"));
+        serde_json::from_value::<FlowType>(name.clone()).is_ok()
+            || serde_json::from_value::<AttestationTypes>(name).is_ok()
+    })
+}
+
+// verify_credential makes each of the checks of a credential, returning the report of them with
+// the error of the first that failed.
+async fn verify_credential(
     req: &VCWrapper,
     resolver_opts: &Option<ResolverOpts>,
-) -> Result<(), FlowError> {
-    let issuer = match &req {
-        VCWrapper::Jwt(r) => {
-            let c = Credential::from_jwt_unsigned(&r.jwt)
-                .map_err(|e| FlowError::Validation(e.to_string()))?;
-            if c.issuer.is_none() {
-                return Err(FlowError::Validation(
-                    "No issuer found in the Credential".to_string(),
-                ));
-            }
+    policy: &Option<VerificationPolicy>,
+) -> Result<(VerificationReport, Option<FlowError>), FlowError> {
+    let vc = match &req {
+        VCWrapper::Jwt(r) => Credential::from_jwt_unsigned(&r.jwt)
+            .map_err(|e| FlowError::Validation(e.to_string()))?,
+        VCWrapper::Ld(r) => r.credential.clone(),
+    };
 
-            c.issuer.unwrap().get_id()
-        }
-        VCWrapper::Ld(r) => {
-            if r.credential.issuer.is_none() {
-                return Err(FlowError::Validation(
-                    "No issuer found in the Credential".to_string(),
-                ));
+    let issuer = vc.issuer.as_ref().map(|i| i.get_id());
+    let vm = match &issuer {
+        Some(i) => get_verification_method(i, &make_resolver(resolver_opts)).await,
+        None => None,
+    };
+    let issuer_resolved = match (&issuer, &vm) {
+        (None, _) => Err(FlowError::Validation(
+            "No issuer found in the Credential".to_string(),
+        )),
+        (Some(i), None) => Err(FlowError::BadLookup(format!(
+            "Could not resolve issuer {}",
+            i
+        ))),
+        (Some(_), Some(_)) => Ok(()),
+    };
+
+    let signature = match vm {
+        None => Err(FlowError::BadLookup(
+            "Could not generate verifcation method".to_string(),
+        )),
+        Some(vm) => {
+            let ldpo = LinkedDataProofOptions {
+                verification_method: Some(URI::String(vm)),
+                ..Default::default()
+            };
+
+            let res = match req {
+                VCWrapper::Jwt(r) => {
+                    verify_jwt(
+                        &r.jwt,
+                        Some(ldpo),
+                        &make_resolver(resolver_opts),
+                        &mut context_loader()?,
+                    )
+                    .await
+                }
+                VCWrapper::Ld(r) => {
+                    r.credential
+                        .verify(
+                            Some(ldpo),
+                            &make_resolver(resolver_opts),
+                            &mut context_loader()?,
+                        )
+                        .await
+                }
+            };
+
+            if res.errors.is_empty() {
+                Ok(())
+            } else {
+                Err(FlowError::Signature(res.errors.join(" ")))
             }
+        }
+    };
 
-            r.credential.issuer.as_ref().unwrap().get_id()
+    let proof_purpose = vc
+        .proof
+        .as_ref()
+        .and_then(|p| p.first())
+        .and_then(|p| p.proof_purpose.as_ref())
+        .and_then(|p| serde_json::to_value(p).ok())
+        .and_then(|p| p.as_str().map(|p| p.to_string()));
+
+    let validity = check_validity(&vc);
+    let policy = policy
+        .as_ref()
+        .map(|p| p.check(&vc).map_err(FlowError::from));
+
+    // The status list is at a URL of the issuer's choosing, so it's only fetched once the
+    // credential is known to be from an issuer the verifier accepts.
+    let trusted = signature.is_ok() && policy.as_ref().is_none_or(|p| p.is_ok());
+    let (status, status_check) = match &vc.credential_status {
+        None => (None, None),
+        Some(_) if !trusted => (
+            None,
+            Some(CheckResult::skipped(
+                "Status was not checked since the credential's issuer was not accepted",
+            )),
+        ),
+        Some(_) => {
+            let status = check_status(&vc, resolver_opts, &None)
+                .await
+                .map_err(FlowError::from);
+            let check = CheckResult::from_result(&status);
+            (Some(status), Some(check))
         }
     };
 
-    let v_method = get_verification_method(&issuer, &make_resolver(resolver_opts)).await;
-    if v_method.is_none() {
-        return Err(FlowError::Validation(
-            "Could not generate verifcation method".to_string(),
-        ));
+    let mut report = VerificationReport {
+        success: false,
+        issuer,
+        issuer_resolved: CheckResult::from_result(&issuer_resolved),
+        signature: CheckResult::from_result(&signature),
+        proof_purpose,
+        validity: CheckResult::from_result(&validity),
+        status: status_check,
+        policy: policy.as_ref().map(CheckResult::from_result),
+        credential_type: rebase_type(&vc),
+        credential_subject: serde_json::to_value(&vc.credential_subject)
+            .map_err(ContentError::from)?,
+        evidence: match &vc.evidence {
+            Some(e) => Some(serde_json::to_value(e).map_err(ContentError::from)?),
+            None => None,
+        },
+    };
+    report.success = report.error().is_none();
+
+    let err = [
+        Some(issuer_resolved),
+        Some(signature),
+        Some(validity),
+        status,
+        policy,
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|r| r.err());
+
+    Ok((report, err))
+}
+
+// handle_verify_report verifies a credential, reporting on each check rather than stopping at
+// the first failure. It only errs if the credential can't be decoded at all.
+pub async fn handle_verify_report(
+    req: &VCWrapper,
+    resolver_opts: &Option<ResolverOpts>,
+    policy: &Option<VerificationPolicy>,
+) -> Result<VerificationReport, FlowError> {
+    Ok(verify_credential(req, resolver_opts, policy).await?.0)
+}
+
+// handle_verify errs if the credential fails any check, including those of the policy if given.
+pub async fn handle_verify(
+    req: &VCWrapper,
+    resolver_opts: &Option<ResolverOpts>,
+    policy: &Option<VerificationPolicy>,
+) -> Result<(), FlowError> {
+    match verify_credential(req, resolver_opts, policy).await?.1 {
+        Some(e) => Err(e),
+        None => Ok(()),
     }
-    let vm = v_method.unwrap();
+}
+
+// How long a holder has to present after asking the verifier for a challenge.
+pub const PRESENTATION_CHALLENGE_MINUTES: i64 = 10;
+
+fn presentation_scope(domain: &Option<String>) -> String {
+    format!("presentation:{}", domain.as_deref().unwrap_or_default())
+}
+
+// handle_presentation_challenge issues the options a holder signs their next presentation to
+// this verifier with. The challenge can be used once, within PRESENTATION_CHALLENGE_MINUTES.
+pub async fn handle_presentation_challenge(
+    store: &dyn NonceStore,
+    domain: &Option<String>,
+) -> Result<PresentationOptions, FlowError> {
+    Ok(PresentationOptions {
+        challenge: Some(
+            issue_challenge(
+                store,
+                &presentation_scope(domain),
+                PRESENTATION_CHALLENGE_MINUTES,
+            )
+            .await?,
+        ),
+        domain: domain.clone(),
+    })
+}
 
-    let ldpo = LinkedDataProofOptions {
-        verification_method: Some(URI::String(vm)),
-        ..Default::default()
+// handle_verify_presentation errs unless the holder signed the presentation over a challenge
+// this verifier issued for its domain, and each credential in it passes handle_verify. The
+// policy must name trusted issuers, since only their SameControllerAssertions bind other DIDs
+// to the holder.
+pub async fn handle_verify_presentation(
+    req: &VerifyPresentationReq,
+    resolver_opts: &Option<ResolverOpts>,
+    policy: &Option<VerificationPolicy>,
+    store: &dyn NonceStore,
+    domain: &Option<String>,
+) -> Result<(), FlowError> {
+    let issuers = match policy {
+        Some(p) if !p.issuers.is_empty() => &p.issuers,
+        _ => {
+            return Err(FlowError::Misconfigured(
+                "verifying presentations requires a policy with trusted issuers".to_string(),
+            ))
+        }
     };
 
-    let res = match req {
-        VCWrapper::Jwt(r) => {
-            Credential::verify_jwt(
+    let challenge = req.challenge.as_ref().ok_or_else(|| {
+        FlowError::Validation("presentations must sign a challenge from this verifier".to_string())
+    })?;
+    let options = PresentationOptions {
+        challenge: Some(challenge.clone()),
+        domain: domain.clone(),
+    };
+
+    // With no verification method given, the proof must be by one of the holder's.
+    let ldpo = options.proof_options(None);
+    let (vp, res) = match &req.presentation {
+        VPWrapper::Jwt(r) => {
+            Presentation::decode_verify_jwt(
                 &r.jwt,
                 Some(ldpo),
                 &make_resolver(resolver_opts),
@@ -645,21 +1560,689 @@ pub async fn handle_verify(
             )
             .await
         }
-        VCWrapper::Ld(r) => {
-            r.credential
+        VPWrapper::Ld(r) => {
+            let res = r
+                .presentation
                 .verify(
                     Some(ldpo),
                     &make_resolver(resolver_opts),
                     &mut context_loader()?,
                 )
-                .await
+                .await;
+            (Some(r.presentation.clone()), res)
         }
     };
 
-    if res.errors.is_empty() {
-        Ok(())
-    } else {
+    if !res.errors.is_empty() {
         let message = res.errors.join(" ");
-        Err(FlowError::BadLookup(message))
+        return Err(FlowError::Signature(message));
+    }
+
+    // Only the holder's signature over the challenge uses it up.
+    redeem_challenge(
+        store,
+        &presentation_scope(domain),
+        challenge,
+        PRESENTATION_CHALLENGE_MINUTES,
+    )
+    .await?;
+
+    let vp =
+        vp.ok_or_else(|| FlowError::Validation("Could not decode the Presentation".to_string()))?;
+    check_holder_binding(&vp, issuers)?;
+
+    // A SameControllerAssertion only binds DIDs to the holder, so it's held to the policy's
+    // issuers and age, but not to the types and evidence asked of the presented claims.
+    let binding = policy.as_ref().map(|p| VerificationPolicy {
+        types: Vec::new(),
+        required_evidence: Vec::new(),
+        ..p.clone()
+    });
+
+    // The holder's proof doesn't vouch for the credentials, so each is verified in turn.
+    for (c, decoded) in vp
+        .verifiable_credential
+        .iter()
+        .flatten()
+        .zip(credentials(&vp)?)
+    {
+        let policy = if decoded
+            .type_
+            .contains(&"SameControllerAssertion".to_string())
+        {
+            &binding
+        } else {
+            policy
+        };
+        let vc = match c {
+            CredentialOrJWT::Credential(credential) => VCWrapper::Ld(CredentialWrapper {
+                credential: credential.clone(),
+            }),
+            CredentialOrJWT::JWT(jwt) => VCWrapper::Jwt(JWTWrapper { jwt: jwt.clone() }),
+        };
+        handle_verify(&vc, resolver_opts, policy).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rebase::{
+        issuer::ed25519::Ed25519Jwk,
+        test_util::util::{test_did_keypair, StubTransport},
+        types::defs::HttpOpts,
+    };
+    use std::sync::Arc;
+
+    fn stub(routes: Vec<(&str, String)>) -> Option<HttpOpts> {
+        Some(HttpOpts {
+            base_url: Some("http://stub.test".to_string()),
+            transport: Some(Arc::new(StubTransport {
+                routes: routes
+                    .into_iter()
+                    .map(|(prefix, body)| (prefix.to_string(), body))
+                    .collect(),
+            })),
+        })
+    }
+
+    fn no_flows() -> WitnessFlow {
+        serde_json::from_value(json!({})).unwrap()
+    }
+
+    // sign returns the statement of proof and the subject's signature of it.
+    async fn sign(key: &Ed25519Jwk, proof: &Proofs) -> (String, String) {
+        let statement = proof.generate_statement().unwrap();
+        let signature = key.sign(&statement).await.unwrap();
+        (statement, signature)
+    }
+
+    fn dns_flow(record: String) -> WitnessFlow {
+        WitnessFlow {
+            dns_verification: Some(DnsVerificationFlow {
+                http_opts: stub(vec![(
+                    "http://stub.test/dns-query?name=example.com",
+                    json!({ "Answer": [{ "name": "example.com", "data": record }] }).to_string(),
+                )]),
+                resolver_opts: None,
+                eth_rpc: None,
+                validity_minutes: None,
+            }),
+            ..no_flows()
+        }
+    }
+
+    // refreshable returns a witness for each type of credential that can be refreshed, along
+    // with a proof it finds the evidence of, signed by key.
+    async fn refreshable(subject: &Subjects, key: &Ed25519Jwk) -> Vec<(WitnessFlow, Proofs)> {
+        let mut cases = Vec::new();
+
+        let proof = Proofs::BlueskyVerification(BlueskyVerificationProof {
+            post_url: Some("https://bsky.app/profile/alice.bsky.social/post/3kabc".to_string()),
+            statement: BlueskyVerificationStatement {
+                handle: "alice.bsky.social".to_string(),
+                subject: subject.clone(),
+            },
+        });
+        let (statement, signature) = sign(key, &proof).await;
+        let text = format!("{}\n\n{}", statement, signature);
+        let flow = BlueskyVerificationFlow {
+            delimiter: "\n\n".to_string(),
+            http_opts: stub(vec![
+                (
+                    "http://stub.test/xrpc/com.atproto.identity.resolveHandle",
+                    json!({ "did": "did:plc:alice" }).to_string(),
+                ),
+                (
+                    "http://stub.test/xrpc/app.bsky.actor.getProfile",
+                    json!({
+                        "did": "did:plc:alice",
+                        "handle": "alice.bsky.social",
+                        "description": text
+                    })
+                    .to_string(),
+                ),
+                (
+                    "http://stub.test/xrpc/app.bsky.feed.getPosts",
+                    json!({
+                        "posts": [{
+                            "author": { "did": "did:plc:alice" },
+                            "record": { "text": text }
+                        }]
+                    })
+                    .to_string(),
+                ),
+            ]),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+        cases.push((
+            WitnessFlow {
+                bluesky_verification: Some(flow),
+                ..no_flows()
+            },
+            proof,
+        ));
+
+        let proof = Proofs::DiscordVerification(DiscordVerificationProof {
+            channel_id: "1100000000000000001".to_string(),
+            message_id: "1200000000000000002".to_string(),
+            statement: DiscordVerificationStatement {
+                username: "alice".to_string(),
+                user_id: None,
+                subject: subject.clone(),
+            },
+        });
+        let (statement, signature) = sign(key, &proof).await;
+        let flow = DiscordVerificationFlow {
+            bot_token: "token".to_string(),
+            delimiter: "\n\n".to_string(),
+            channel_ids: vec!["1100000000000000001".to_string()],
+            http_opts: stub(vec![(
+                "http://stub.test/api/v10/channels/1100000000000000001/messages/1200000000000000002",
+                json!({
+                    "channel_id": "1100000000000000001",
+                    "content": format!("{}\n\n{}", statement, signature),
+                    "timestamp": "2024-03-01T12:00:00.123000+00:00",
+                    "author": {
+                        "id": "1300000000000000003",
+                        "username": "alice",
+                        "discriminator": "0"
+                    }
+                })
+                .to_string(),
+            )]),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+        cases.push((
+            WitnessFlow {
+                discord_verification: Some(flow),
+                ..no_flows()
+            },
+            proof,
+        ));
+
+        // The prefix is read back from the credential, so it needn't be the default.
+        let proof = Proofs::DnsVerification(DnsVerificationStatement {
+            domain: "example.com".to_string(),
+            prefix: "custom_sig=".to_string(),
+            subject: subject.clone(),
+        });
+        let (_, signature) = sign(key, &proof).await;
+        cases.push((dns_flow(format!("\"custom_sig={}\"", signature)), proof));
+
+        let proof = Proofs::ForgeVerification(ForgeVerificationProof {
+            snippet_id: "42".to_string(),
+            statement: ForgeVerificationStatement {
+                handle: "alice".to_string(),
+                domain: "gitlab.com".to_string(),
+                subject: subject.clone(),
+            },
+        });
+        let (statement, signature) = sign(key, &proof).await;
+        let flow = ForgeVerificationFlow {
+            user_agent: "rebase-test".to_string(),
+            delimiter: "\n\n".to_string(),
+            instances: vec![ForgeInstance {
+                kind: ForgeKind::GitLab,
+                url: "https://gitlab.com".to_string(),
+            }],
+            http_opts: stub(vec![
+                (
+                    "http://stub.test/api/v4/snippets/42/raw",
+                    format!("{}\n\n{}", statement, signature),
+                ),
+                (
+                    "http://stub.test/api/v4/snippets/42",
+                    json!({ "author": { "username": "alice" } }).to_string(),
+                ),
+            ]),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+        cases.push((
+            WitnessFlow {
+                forge_verification: Some(flow),
+                ..no_flows()
+            },
+            proof,
+        ));
+
+        let proof = Proofs::GitHubVerification(GitHubVerificationProof {
+            gist_id: "aaaabbbbccccddddeeeeffff00001111".to_string(),
+            statement: GitHubVerificationStatement {
+                handle: "foo".to_string(),
+                subject: subject.clone(),
+            },
+        });
+        let (statement, signature) = sign(key, &proof).await;
+        let flow = GitHubVerificationFlow {
+            user_agent: "rebase-test".to_string(),
+            delimiter: "\n\n".to_string(),
+            http_opts: stub(vec![(
+                "http://stub.test/gists/aaaabbbbccccddddeeeeffff00001111",
+                json!({
+                    "files": {
+                        "rebase.txt": { "content": format!("{}\n\n{}", statement, signature) }
+                    },
+                    "owner": { "login": "Foo" },
+                    "history": []
+                })
+                .to_string(),
+            )]),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+        cases.push((
+            WitnessFlow {
+                github_verification: Some(flow),
+                ..no_flows()
+            },
+            proof,
+        ));
+
+        let proof = Proofs::MastodonVerification(MastodonVerificationProof {
+            status_url: Some("https://mastodon.example/@alice/1".to_string()),
+            statement: MastodonVerificationStatement {
+                handle: "alice".to_string(),
+                domain: "mastodon.example".to_string(),
+                subject: subject.clone(),
+            },
+        });
+        let (statement, signature) = sign(key, &proof).await;
+        let actor = "https://mastodon.example/users/alice";
+        let flow = MastodonVerificationFlow {
+            delimiter: "\n\n".to_string(),
+            http_opts: stub(vec![
+                (
+                    "http://stub.test/.well-known/webfinger",
+                    json!({
+                        "subject": "acct:alice@mastodon.example",
+                        "links": [{
+                            "rel": "self",
+                            "type": "application/activity+json",
+                            "href": actor
+                        }]
+                    })
+                    .to_string(),
+                ),
+                (
+                    "http://stub.test/@alice/1",
+                    json!({
+                        "attributedTo": actor,
+                        "content": format!("<p>{}</p><p>{}</p>", statement, signature)
+                    })
+                    .to_string(),
+                ),
+                (
+                    "http://stub.test/users/alice",
+                    json!({
+                        "id": actor,
+                        "preferredUsername": "alice",
+                        "summary": "<p>hello</p>",
+                        "attachment": []
+                    })
+                    .to_string(),
+                ),
+            ]),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+        cases.push((
+            WitnessFlow {
+                mastodon_verification: Some(flow),
+                ..no_flows()
+            },
+            proof,
+        ));
+
+        let proof = Proofs::RedditVerification(RedditVerificationStatement {
+            handle: "alice".to_string(),
+            subject: subject.clone(),
+        });
+        let (_, signature) = sign(key, &proof).await;
+        let flow = RedditVerificationFlow {
+            user_agent: "rebase-test".to_string(),
+            http_opts: stub(vec![(
+                "http://stub.test/user/alice/about/.json",
+                json!({ "data": { "subreddit": { "public_description": signature } } }).to_string(),
+            )]),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+        cases.push((
+            WitnessFlow {
+                reddit_verification: Some(flow),
+                ..no_flows()
+            },
+            proof,
+        ));
+
+        let proof = Proofs::SoundCloudVerification(SoundCloudVerificationStatement {
+            permalink: "alice".to_string(),
+            subject: subject.clone(),
+        });
+        let (_, signature) = sign(key, &proof).await;
+        let flow = SoundCloudVerificationFlow {
+            client_id: "client".to_string(),
+            limit: 50,
+            max_offset: 0,
+            http_opts: stub(vec![(
+                "http://stub.test/search/users",
+                json!({ "collection": [{ "permalink": "alice", "description": signature }] })
+                    .to_string(),
+            )]),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+        cases.push((
+            WitnessFlow {
+                soundcloud_verification: Some(flow),
+                ..no_flows()
+            },
+            proof,
+        ));
+
+        let proof = Proofs::TwitterVerification(TwitterVerificationProof {
+            tweet_url: "https://twitter.com/foo/status/1234".to_string(),
+            statement: TwitterVerificationStatement {
+                handle: "foo".to_string(),
+                subject: subject.clone(),
+            },
+        });
+        let (statement, signature) = sign(key, &proof).await;
+        let flow = TwitterVerificationFlow {
+            api_key: "secret".to_string(),
+            delimiter: "\n\n".to_string(),
+            http_opts: stub(vec![(
+                "http://stub.test/2/tweets?ids=1234",
+                json!({
+                    "data": [{ "text": format!("{}\n\n{}", statement, signature) }],
+                    "includes": { "users": [{ "username": "foo" }] }
+                })
+                .to_string(),
+            )]),
+            resolver_opts: None,
+            eth_rpc: None,
+            validity_minutes: None,
+        };
+        cases.push((
+            WitnessFlow {
+                twitter_verification: Some(flow),
+                ..no_flows()
+            },
+            proof,
+        ));
+
+        cases
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        let (_, witness) = test_did_keypair().await.unwrap();
+        let (subject, key) = test_did_keypair().await.unwrap();
+
+        for (flow, proof) in refreshable(&subject, &key).await {
+            let jwt = flow.jwt(proof.clone(), witness.clone()).await.unwrap();
+            let vc = Credential::from_jwt_unsigned(&jwt).unwrap();
+            let rebuilt = Proofs::from_credential(&vc, None).unwrap();
+            assert_eq!(
+                rebuilt.generate_statement().unwrap(),
+                proof.generate_statement().unwrap()
+            );
+
+            let credential = flow
+                .credential(proof.clone(), witness.clone())
+                .await
+                .unwrap();
+            for credential in [
+                VCWrapper::Jwt(JWTWrapper { jwt }),
+                VCWrapper::Ld(CredentialWrapper { credential }),
+            ] {
+                let req = RefreshReq {
+                    credential,
+                    subject: None,
+                };
+                flow.handle_refresh(&req, witness.clone()).await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_no_longer_valid() {
+        let (_, witness) = test_did_keypair().await.unwrap();
+        let (_, other_witness) = test_did_keypair().await.unwrap();
+        let (subject, key) = test_did_keypair().await.unwrap();
+        let (other_subject, other_key) = test_did_keypair().await.unwrap();
+
+        let proof = Proofs::DnsVerification(DnsVerificationStatement {
+            domain: "example.com".to_string(),
+            prefix: "rebase_sig=".to_string(),
+            subject,
+        });
+        let (statement, signature) = sign(&key, &proof).await;
+        let flow = dns_flow(format!("\"rebase_sig={}\"", signature));
+        let jwt = flow.jwt(proof.clone(), witness.clone()).await.unwrap();
+        let req = RefreshReq {
+            credential: VCWrapper::Jwt(JWTWrapper { jwt }),
+            subject: None,
+        };
+        flow.handle_refresh(&req, witness.clone()).await.unwrap();
+
+        // The record now holds a signature by someone else.
+        let replaced = other_key.sign(&statement).await.unwrap();
+        let flow = dns_flow(format!("\"rebase_sig={}\"", replaced));
+        match flow.handle_refresh(&req, witness.clone()).await {
+            Err(e) => assert!(e.to_string().contains("no longer valid"), "{}", e),
+            Ok(_) => panic!("Refreshed a claim whose record was replaced"),
+        }
+
+        if flow.handle_refresh(&req, other_witness).await.is_ok() {
+            panic!("Refreshed a credential issued by another witness");
+        }
+
+        // Nothing listens here, so the record can't be looked up.
+        let mut unreachable = dns_flow(String::new());
+        if let Some(f) = unreachable.dns_verification.as_mut() {
+            f.http_opts = Some(HttpOpts {
+                base_url: Some("http://127.0.0.1:9".to_string()),
+                transport: None,
+            });
+        }
+        match unreachable.handle_refresh(&req, witness.clone()).await {
+            Err(FlowError::Upstream(_)) => {}
+            r => panic!("Expected the upstream error, got {:?}", r),
+        }
+
+        let mismatched = RefreshReq {
+            subject: Some(other_subject),
+            ..req
+        };
+        if flow.handle_refresh(&mismatched, witness).await.is_ok() {
+            panic!("Refreshed a credential for another subject");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_report() {
+        let (witness_subj, witness) = test_did_keypair().await.unwrap();
+        let (subject, key) = test_did_keypair().await.unwrap();
+
+        let proof = Proofs::DnsVerification(DnsVerificationStatement {
+            domain: "example.com".to_string(),
+            prefix: "rebase_sig=".to_string(),
+            subject,
+        });
+        let (_, signature) = sign(&key, &proof).await;
+        let flow = WitnessFlow {
+            // Nothing listens here, so the list can't be loaded if it's fetched.
+            status_list: Some(StatusListManager {
+                store: Some(Arc::new(MemoryStatusListStore::new())),
+                ..StatusListManager::new("http://127.0.0.1:9/status_list")
+            }),
+            ..dns_flow(format!("\"rebase_sig={}\"", signature))
+        };
+        let credential = flow.credential(proof, witness).await.unwrap();
+        let trusted = Some(VerificationPolicy {
+            issuers: vec![witness_subj.did().unwrap()],
+            ..Default::default()
+        });
+
+        let req = VCWrapper::Ld(CredentialWrapper {
+            credential: credential.clone(),
+        });
+        let report = handle_verify_report(&req, &None, &trusted).await.unwrap();
+        assert!(report.signature.passed);
+        assert!(report.policy.unwrap().passed);
+        let status = report.status.unwrap();
+        assert!(!status.passed && !status.skipped, "{:?}", status);
+        assert!(!report.success);
+
+        // A tampered credential's status list isn't fetched.
+        let mut tampered = credential.clone();
+        tampered.id = Some(serde_json::from_value(json!("urn:uuid:tampered")).unwrap());
+        let req = VCWrapper::Ld(CredentialWrapper {
+            credential: tampered,
+        });
+        let report = handle_verify_report(&req, &None, &trusted).await.unwrap();
+        assert!(!report.signature.passed);
+        assert_eq!(report.error(), report.signature.error);
+        assert!(report.status.unwrap().skipped);
+
+        // Nor is that of a credential from an issuer the policy doesn't accept.
+        let untrusted = Some(VerificationPolicy {
+            issuers: vec!["did:web:example.com".to_string()],
+            ..Default::default()
+        });
+        let req = VCWrapper::Ld(CredentialWrapper { credential });
+        let report = handle_verify_report(&req, &None, &untrusted).await.unwrap();
+        assert!(report.signature.passed);
+        assert!(report.status.unwrap().skipped);
+        assert!(!report.policy.unwrap().passed);
+    }
+
+    #[tokio::test]
+    async fn test_verify_presentation() {
+        let (witness_subj, witness) = test_did_keypair().await.unwrap();
+        let (subj1, holder) = test_did_keypair().await.unwrap();
+        let (subj2, _) = test_did_keypair().await.unwrap();
+
+        let github = |subject| GitHubVerificationContent {
+            gist_id: "1".to_string(),
+            handle: "example".to_string(),
+            subject,
+            statement: "unimplemented".to_string(),
+            signature: "unimplemented".to_string(),
+        };
+        let own = github(subj1.clone())
+            .credential(witness.clone())
+            .await
+            .unwrap();
+        let linked = github(subj2.clone())
+            .credential(witness.clone())
+            .await
+            .unwrap();
+        let same_controller = SameControllerAssertionContent {
+            id1: subj1,
+            id2: subj2,
+            statement: "unimplemented".to_string(),
+            signature1: "unimplemented".to_string(),
+            signature2: "unimplemented".to_string(),
+        }
+        .credential(witness)
+        .await
+        .unwrap();
+
+        let store = MemoryNonceStore::new();
+        let domain = Some("verifier.example".to_string());
+        let options = handle_presentation_challenge(&store, &domain)
+            .await
+            .unwrap();
+        let vp = presentation(
+            &holder,
+            vec![own.clone(), linked, same_controller]
+                .into_iter()
+                .map(CredentialOrJWT::Credential)
+                .collect(),
+            &options,
+        )
+        .await
+        .unwrap();
+        let req = VerifyPresentationReq {
+            presentation: VPWrapper::Ld(PresentationWrapper { presentation: vp }),
+            challenge: options.challenge,
+        };
+
+        // The SameControllerAssertion isn't held to the types and evidence of the claims.
+        let policy = Some(VerificationPolicy {
+            issuers: vec![witness_subj.did().unwrap()],
+            types: vec!["GitHubVerification".to_string()],
+            required_evidence: vec!["gistId".to_string()],
+            ..Default::default()
+        });
+        if handle_verify_presentation(&req, &None, &None, &store, &domain)
+            .await
+            .is_ok()
+        {
+            panic!("Verified a presentation without trusted issuers");
+        }
+        let unchallenged = VerifyPresentationReq {
+            challenge: None,
+            ..req.clone()
+        };
+        if handle_verify_presentation(&unchallenged, &None, &policy, &store, &domain)
+            .await
+            .is_ok()
+        {
+            panic!("Verified a presentation without a challenge");
+        }
+        if handle_verify_presentation(&req, &None, &policy, &store, &None)
+            .await
+            .is_ok()
+        {
+            panic!("Verified a presentation for another domain");
+        }
+
+        handle_verify_presentation(&req, &None, &policy, &store, &domain)
+            .await
+            .unwrap();
+        if handle_verify_presentation(&req, &None, &policy, &store, &domain)
+            .await
+            .is_ok()
+        {
+            panic!("Verified a replayed presentation");
+        }
+
+        // A challenge the verifier didn't issue is refused.
+        let vp = presentation(
+            &holder,
+            vec![CredentialOrJWT::Credential(own)],
+            &PresentationOptions {
+                challenge: Some("made-up".to_string()),
+                domain: domain.clone(),
+            },
+        )
+        .await
+        .unwrap();
+        let forged = VerifyPresentationReq {
+            presentation: VPWrapper::Ld(PresentationWrapper { presentation: vp }),
+            challenge: Some("made-up".to_string()),
+        };
+        if handle_verify_presentation(&forged, &None, &policy, &store, &domain)
+            .await
+            .is_ok()
+        {
+            panic!("Verified a presentation with a challenge that wasn't issued");
+        }
     }
 }